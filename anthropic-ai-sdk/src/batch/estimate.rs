@@ -0,0 +1,358 @@
+//! Estimating the cost of a message batch before it is submitted
+//!
+//! [`BatchEstimator`] counts the input tokens of every request, either with
+//! `count_tokens` or with the local [`TokenEstimator`], assumes each request generates
+//! its full `max_tokens`, and prices the result at batch rates. The estimate is
+//! therefore an upper bound on what the batch can cost.
+//!
+//! Identical prompts are counted once: counts are cached by a hash of the request
+//! content, and the cache is shared by clones of the estimator.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::batch::estimate::BatchEstimator;
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::types::message::MessageError;
+//! use anthropic_ai_sdk::types::message_batches::CreateMessageBatchParams;
+//!
+//! # async fn example(params: CreateMessageBatchParams) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+//!
+//! let estimate = BatchEstimator::new().estimate_batch(&client, &params).await?;
+//! println!("At most ${:.2}", estimate.totals.cost.total());
+//! for (model, part) in &estimate.models {
+//!     println!("{}: {} requests, ${:.2}", model, part.requests, part.cost.total());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cost::{CostBreakdown, CostCalculator, CostError};
+use crate::tokens::TokenEstimator;
+use crate::types::message::{CountMessageTokensParams, MessageClient, MessageError, Usage};
+use crate::types::message_batches::{CreateMessageBatchParams, MessageRequest};
+use futures_util::{StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Error types for batch estimation
+#[derive(Debug, Error)]
+pub enum EstimateError {
+    /// Counting the tokens of a request failed
+    #[error("Failed to count tokens: {0}")]
+    Count(#[from] MessageError),
+    /// A request uses a model without known prices
+    #[error(transparent)]
+    Cost(#[from] CostError),
+}
+
+/// Estimates the tokens and cost of message batches
+#[derive(Debug, Clone)]
+pub struct BatchEstimator {
+    calculator: CostCalculator,
+    estimator: TokenEstimator,
+    concurrency: usize,
+    largest: usize,
+    /// Input token counts keyed by the hash of the serialized `count_tokens` request
+    cache: Arc<Mutex<HashMap<u64, u32>>>,
+}
+
+impl Default for BatchEstimator {
+    fn default() -> Self {
+        Self {
+            calculator: CostCalculator::new(),
+            estimator: TokenEstimator::new(),
+            concurrency: 8,
+            largest: 10,
+            cache: Arc::default(),
+        }
+    }
+}
+
+impl BatchEstimator {
+    /// Create an estimator with the built-in prices, sending up to 8 `count_tokens`
+    /// requests at a time and reporting the 10 largest requests
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the calculator requests are priced with
+    pub fn with_calculator(mut self, calculator: CostCalculator) -> Self {
+        self.calculator = calculator;
+        self
+    }
+
+    /// Set the estimator used by [`estimate_batch_locally`](Self::estimate_batch_locally)
+    pub fn with_token_estimator(mut self, estimator: TokenEstimator) -> Self {
+        self.estimator = estimator;
+        self
+    }
+
+    /// Set how many `count_tokens` requests are sent at a time
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set how many of the most expensive requests are reported
+    pub fn with_largest(mut self, largest: usize) -> Self {
+        self.largest = largest;
+        self
+    }
+
+    /// Forget the cached token counts, for every clone of the estimator
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Estimate a batch, counting input tokens with `count_tokens`
+    ///
+    /// # Errors
+    ///
+    /// Returns an `EstimateError` if counting the tokens of a request fails, or if a
+    /// request uses a model without known prices.
+    pub async fn estimate_batch<C>(
+        &self,
+        client: &C,
+        batch: &CreateMessageBatchParams,
+    ) -> Result<BatchEstimate, EstimateError>
+    where
+        C: MessageClient + Sync,
+    {
+        let input_tokens = self.count_tokens(client, &batch.requests).await?;
+        Ok(self.summarize(&batch.requests, &input_tokens)?)
+    }
+
+    /// Estimate a batch, estimating input tokens locally
+    ///
+    /// This makes no requests, but the input token counts are approximate.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `CostError` if a request uses a model without known prices.
+    pub fn estimate_batch_locally(
+        &self,
+        batch: &CreateMessageBatchParams,
+    ) -> Result<BatchEstimate, CostError> {
+        let input_tokens: Vec<u32> = batch
+            .requests
+            .iter()
+            .map(|request| self.estimator.estimate(&request.params))
+            .collect();
+        self.summarize(&batch.requests, &input_tokens)
+    }
+
+    /// Count the input tokens of every request, sending each distinct request once
+    async fn count_tokens<C>(
+        &self,
+        client: &C,
+        requests: &[MessageRequest],
+    ) -> Result<Vec<u32>, MessageError>
+    where
+        C: MessageClient + Sync,
+    {
+        let mut keys = Vec::with_capacity(requests.len());
+        let mut pending = HashMap::new();
+        {
+            let cache = self.cache.lock().unwrap();
+            for request in requests {
+                let params = CountMessageTokensParams::from(&request.params);
+                let json = serde_json::to_string(&params).map_err(|e| {
+                    MessageError::ApiError(format!("Failed to serialize request: {}", e))
+                })?;
+                let mut hasher = DefaultHasher::new();
+                json.hash(&mut hasher);
+                let key = hasher.finish();
+                if !cache.contains_key(&key) {
+                    pending.entry(key).or_insert(params);
+                }
+                keys.push(key);
+            }
+        }
+
+        let counted: Vec<(u64, u32)> = futures_util::stream::iter(pending)
+            .map(|(key, params)| async move {
+                let response = client.count_tokens(Some(&params)).await?;
+                Ok::<_, MessageError>((key, response.input_tokens))
+            })
+            .buffer_unordered(self.concurrency)
+            .try_collect()
+            .await?;
+
+        let mut cache = self.cache.lock().unwrap();
+        cache.extend(counted);
+        Ok(keys.iter().map(|key| cache[key]).collect())
+    }
+
+    fn summarize(
+        &self,
+        requests: &[MessageRequest],
+        input_tokens: &[u32],
+    ) -> Result<BatchEstimate, CostError> {
+        let mut estimate = BatchEstimate::default();
+        let mut estimates = Vec::with_capacity(requests.len());
+        for (request, &input_tokens) in requests.iter().zip(input_tokens) {
+            let params = &request.params;
+            let usage = Usage {
+                input_tokens,
+                output_tokens: params.max_tokens,
+                ..Default::default()
+            };
+            let cost = self.calculator.batch_cost(&params.model, &usage)?;
+
+            let model = estimate.models.entry(params.model.clone()).or_default();
+            for totals in [&mut estimate.totals, model] {
+                totals.requests += 1;
+                totals.input_tokens += u64::from(input_tokens);
+                totals.max_output_tokens += u64::from(params.max_tokens);
+                totals.cost += cost;
+            }
+            estimates.push(RequestEstimate {
+                custom_id: request.custom_id.clone(),
+                model: params.model.clone(),
+                input_tokens,
+                max_output_tokens: params.max_tokens,
+                cost,
+            });
+        }
+
+        estimates.sort_by(|a, b| b.cost.total().total_cmp(&a.cost.total()));
+        estimates.truncate(self.largest);
+        estimate.largest = estimates;
+        Ok(estimate)
+    }
+}
+
+/// Token and cost totals of a set of requests
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EstimateTotals {
+    /// Number of requests
+    pub requests: usize,
+    /// Input tokens
+    pub input_tokens: u64,
+    /// Output tokens if every request generates its full `max_tokens`
+    pub max_output_tokens: u64,
+    /// Cost at batch prices if every request generates its full `max_tokens`
+    pub cost: CostBreakdown,
+}
+
+/// Estimate of a single request
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestEstimate {
+    /// Custom identifier of the request
+    pub custom_id: Option<String>,
+    /// Model of the request
+    pub model: String,
+    /// Input tokens
+    pub input_tokens: u32,
+    /// `max_tokens` of the request
+    pub max_output_tokens: u32,
+    /// Cost at batch prices if the request generates its full `max_tokens`
+    pub cost: CostBreakdown,
+}
+
+/// Estimated tokens and cost of a message batch
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BatchEstimate {
+    /// Totals over every request; `totals.cost` is the most the batch can cost
+    pub totals: EstimateTotals,
+    /// Totals per model
+    pub models: BTreeMap<String, EstimateTotals>,
+    /// Most expensive requests, most expensive first
+    pub largest: Vec<RequestEstimate>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::test_support::{MockMessages, assert_close};
+    use crate::types::message::{
+        CountMessageTokensResponse, CreateMessageParams, Message, RequiredMessageParams, Role,
+    };
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts 1,000 input tokens per request
+    #[derive(Default)]
+    struct MockClient {
+        counted: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl MockMessages for MockClient {
+        async fn count(
+            &self,
+            _params: &CountMessageTokensParams,
+        ) -> Result<CountMessageTokensResponse, MessageError> {
+            self.counted.fetch_add(1, Ordering::SeqCst);
+            Ok(CountMessageTokensResponse {
+                input_tokens: 1_000,
+            })
+        }
+    }
+
+    fn request(custom_id: &str, model: &str, prompt: &str, max_tokens: u32) -> MessageRequest {
+        let params = CreateMessageParams::new(RequiredMessageParams {
+            model: model.to_string(),
+            messages: vec![Message::new_text(Role::User, prompt)],
+            max_tokens,
+        });
+        MessageRequest::new(params).with_custom_id(custom_id)
+    }
+
+    #[tokio::test]
+    async fn prices_batches_with_cached_counts() {
+        let batch = CreateMessageBatchParams::new(vec![
+            request("a", "claude-sonnet-4-5", "Hello", 1_000),
+            request("b", "claude-sonnet-4-5", "Hello", 1_000),
+            request("c", "claude-haiku-4-5", "Something else", 4_000),
+        ]);
+        let client = MockClient::default();
+        let estimator = BatchEstimator::new().with_largest(1);
+
+        let estimate = estimator.estimate_batch(&client, &batch).await.unwrap();
+        assert_eq!(client.counted.load(Ordering::SeqCst), 2);
+        assert_eq!(estimate.totals.requests, 3);
+        assert_eq!(estimate.totals.input_tokens, 3_000);
+        assert_eq!(estimate.totals.max_output_tokens, 6_000);
+
+        // Sonnet: 1,000 input at $3/M and 1,000 output at $15/M, halved
+        let sonnet = &estimate.models["claude-sonnet-4-5"];
+        assert_eq!(sonnet.requests, 2);
+        assert_close(sonnet.cost.total(), 2.0 * 0.009);
+        // Haiku: 1,000 input at $1/M and 4,000 output at $5/M, halved
+        let haiku = &estimate.models["claude-haiku-4-5"];
+        assert_close(haiku.cost.total(), 0.0105);
+        assert_close(estimate.totals.cost.total(), 0.0285);
+
+        assert_eq!(estimate.largest.len(), 1);
+        assert_eq!(estimate.largest[0].custom_id.as_deref(), Some("c"));
+
+        estimator.estimate_batch(&client, &batch).await.unwrap();
+        assert_eq!(client.counted.load(Ordering::SeqCst), 2);
+        estimator.clone().clear_cache();
+        estimator.estimate_batch(&client, &batch).await.unwrap();
+        assert_eq!(client.counted.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn estimates_locally_and_rejects_unknown_models() {
+        let batch =
+            CreateMessageBatchParams::new(vec![request("a", "claude-sonnet-4-5", "Hello", 100)]);
+        let estimate = BatchEstimator::new()
+            .estimate_batch_locally(&batch)
+            .unwrap();
+        assert!(estimate.totals.input_tokens > 0);
+        assert_eq!(estimate.totals.max_output_tokens, 100);
+
+        let batch = CreateMessageBatchParams::new(vec![request("a", "unknown", "Hello", 100)]);
+        assert!(matches!(
+            BatchEstimator::new().estimate_batch_locally(&batch),
+            Err(CostError::UnknownModel(_))
+        ));
+    }
+}
@@ -0,0 +1,154 @@
+//! Handle over several message batches
+//!
+//! Requests that do not fit in one batch are submitted as several.
+//! [`BatchGroup`] keeps their ids together so they can be polled, canceled and read as
+//! if they were a single batch.
+
+use crate::batch::poll::{MessageBatchClientExt, PollOptions};
+use crate::types::message_batches::{
+    CancelMessageBatchParams, MessageBatch, MessageBatchClient, MessageBatchError,
+    MessageBatchResult, ProcessingStatus, RequestCounts, RetrieveMessageBatchParams,
+    RetrieveMessageBatchResultsParams,
+};
+use futures_util::{Stream, StreamExt, TryStreamExt};
+
+/// Message batches tracked as one
+#[derive(Debug, Clone, Default)]
+pub struct BatchGroup {
+    batches: Vec<RetrieveMessageBatchResultsParams>,
+}
+
+impl BatchGroup {
+    /// Create a group from batch ids
+    pub fn new(batch_ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            batches: batch_ids
+                .into_iter()
+                .map(RetrieveMessageBatchResultsParams::new)
+                .collect(),
+        }
+    }
+
+    /// Ids of the batches, in the order they were created
+    pub fn batch_ids(&self) -> impl Iterator<Item = &str> {
+        self.batches
+            .iter()
+            .map(|batch| batch.message_batch_id.as_str())
+    }
+
+    /// Number of batches
+    pub fn len(&self) -> usize {
+        self.batches.len()
+    }
+
+    /// Whether the group has no batches
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    /// Retrieve every batch of the group
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of retrieving a batch.
+    pub async fn retrieve<C>(&self, client: &C) -> Result<Vec<MessageBatch>, MessageBatchError>
+    where
+        C: MessageBatchClient + Sync,
+    {
+        let mut batches = Vec::with_capacity(self.batches.len());
+        for id in self.batch_ids() {
+            let params = RetrieveMessageBatchParams::new(id);
+            batches.push(client.retrieve_message_batch(&params).await?);
+        }
+        Ok(batches)
+    }
+
+    /// Request counts summed over every batch of the group
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of retrieving a batch.
+    pub async fn request_counts<C>(&self, client: &C) -> Result<RequestCounts, MessageBatchError>
+    where
+        C: MessageBatchClient + Sync,
+    {
+        let mut total = RequestCounts::default();
+        for batch in self.retrieve(client).await? {
+            let counts = batch.request_counts;
+            total.processing += counts.processing;
+            total.succeeded += counts.succeeded;
+            total.errored += counts.errored;
+            total.canceled += counts.canceled;
+            total.expired += counts.expired;
+        }
+        Ok(total)
+    }
+
+    /// Whether every batch of the group has ended
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of retrieving a batch.
+    pub async fn is_ended<C>(&self, client: &C) -> Result<bool, MessageBatchError>
+    where
+        C: MessageBatchClient + Sync,
+    {
+        let batches = self.retrieve(client).await?;
+        Ok(batches.iter().all(MessageBatch::is_ended))
+    }
+
+    /// Wait for every batch of the group to end, one after the other
+    ///
+    /// A deadline in `options` applies to each batch separately.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of [`wait_for_batch`](MessageBatchClientExt::wait_for_batch).
+    pub async fn wait<C>(
+        &self,
+        client: &C,
+        options: PollOptions,
+    ) -> Result<Vec<MessageBatch>, MessageBatchError>
+    where
+        C: MessageBatchClient + Sync,
+    {
+        let mut batches = Vec::with_capacity(self.batches.len());
+        for id in self.batch_ids() {
+            batches.push(client.wait_for_batch(id, options.clone()).await?);
+        }
+        Ok(batches)
+    }
+
+    /// Cancel every batch of the group that is still in progress
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of retrieving or canceling a batch.
+    pub async fn cancel<C>(&self, client: &C) -> Result<(), MessageBatchError>
+    where
+        C: MessageBatchClient + Sync,
+    {
+        for batch in self.retrieve(client).await? {
+            if batch.processing_status == ProcessingStatus::InProgress {
+                let params = CancelMessageBatchParams::new(batch.id);
+                client.cancel_message_batch(&params).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stream the results of every batch of the group, one batch after the other
+    ///
+    /// The stream yields an error if a batch has not ended yet.
+    pub fn results<'a, C>(
+        &'a self,
+        client: &'a C,
+    ) -> impl Stream<Item = Result<MessageBatchResult, MessageBatchError>> + 'a
+    where
+        C: MessageBatchClient + Sync,
+    {
+        futures_util::stream::iter(&self.batches)
+            .then(move |params| client.retrieve_message_batch_results(params))
+            .try_flatten()
+    }
+}
@@ -0,0 +1,187 @@
+//! Working with a single message batch
+//!
+//! [`MessageBatchHandle`] keeps a batch id together with the client that created it,
+//! so the batch can be refreshed, waited on, canceled, read and deleted without
+//! building request parameters by hand.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::batch::poll::{MessageBatchClientExt, PollOptions};
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::types::message_batches::{
+//!     CreateMessageBatchParams, MessageBatchClient, MessageBatchError,
+//! };
+//! use futures_util::StreamExt;
+//!
+//! # async fn example(params: CreateMessageBatchParams) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageBatchError>("your-api-key", "2023-06-01")?;
+//!
+//! let batch = client.create_message_batch(&params).await?;
+//! let handle = client.batch_handle(&batch.id);
+//!
+//! let batch = handle.wait(PollOptions::new()).await?;
+//! println!("{:.0}% done", batch.progress_fraction() * 100.0);
+//!
+//! let mut results = Box::pin(handle.results().await?);
+//! while let Some(result) = results.next().await {
+//!     println!("{}", result?.custom_id);
+//! }
+//! handle.delete().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::batch::poll::{MessageBatchClientExt, PollOptions};
+use crate::types::message_batches::{
+    CancelMessageBatchParams, DeleteMessageBatchParams, DeleteResponse, MessageBatch,
+    MessageBatchClient, MessageBatchError, MessageBatchResult, RetrieveMessageBatchParams,
+    RetrieveMessageBatchResultsParams,
+};
+use futures_util::Stream;
+
+/// A message batch and the client it belongs to
+#[derive(Debug)]
+pub struct MessageBatchHandle<'a, C> {
+    client: &'a C,
+    params: RetrieveMessageBatchResultsParams,
+}
+
+impl<C> Clone for MessageBatchHandle<'_, C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client,
+            params: self.params.clone(),
+        }
+    }
+}
+
+impl<'a, C: MessageBatchClient + Sync> MessageBatchHandle<'a, C> {
+    /// Create a handle for the batch `batch_id`
+    pub fn new(client: &'a C, batch_id: impl Into<String>) -> Self {
+        Self {
+            client,
+            params: RetrieveMessageBatchResultsParams::new(batch_id),
+        }
+    }
+
+    /// The batch id
+    pub fn id(&self) -> &str {
+        &self.params.message_batch_id
+    }
+
+    /// The client the batch belongs to
+    pub fn client(&self) -> &'a C {
+        self.client
+    }
+
+    /// Retrieve the batch
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if the request fails.
+    pub async fn refresh(&self) -> Result<MessageBatch, MessageBatchError> {
+        let params = RetrieveMessageBatchParams::new(self.id());
+        self.client.retrieve_message_batch(&params).await
+    }
+
+    /// Wait for the batch to end
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`MessageBatchClientExt::wait_for_batch`].
+    pub async fn wait(&self, options: PollOptions) -> Result<MessageBatch, MessageBatchError> {
+        self.client.wait_for_batch(self.id(), options).await
+    }
+
+    /// Cancel the batch
+    ///
+    /// # Returns
+    ///
+    /// Returns the batch, whose processing status is `canceling` until the requests
+    /// in flight finish.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if the request fails.
+    pub async fn cancel(&self) -> Result<MessageBatch, MessageBatchError> {
+        let params = CancelMessageBatchParams::new(self.id());
+        self.client.cancel_message_batch(&params).await
+    }
+
+    /// Stream the results of the ended batch
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if the batch has not ended or the request fails.
+    pub async fn results(
+        &self,
+    ) -> Result<
+        impl Stream<Item = Result<MessageBatchResult, MessageBatchError>> + Send + '_,
+        MessageBatchError,
+    > {
+        self.client
+            .retrieve_message_batch_results(&self.params)
+            .await
+    }
+
+    /// Delete the batch
+    ///
+    /// Batches can only be deleted once they have ended.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if the batch has not ended or the request fails.
+    pub async fn delete(&self) -> Result<DeleteResponse, MessageBatchError> {
+        let params = DeleteMessageBatchParams::new(self.id());
+        self.client.delete_message_batch(&params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::local::LocalBatchExecutor;
+    use crate::batch::test_support::MockClient;
+    use crate::types::message::{CreateMessageParams, Message, RequiredMessageParams, Role};
+    use crate::types::message_batches::{
+        CreateMessageBatchParams, MessageRequest, ProcessingStatus,
+    };
+    use futures_util::TryStreamExt;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn runs_a_batch_through_its_lifecycle() {
+        let executor = LocalBatchExecutor::new(MockClient);
+        let params = CreateMessageParams::new(RequiredMessageParams {
+            model: "claude-3-5-haiku-20241022".to_string(),
+            messages: vec![Message::new_text(Role::User, "Hello!")],
+            max_tokens: 100,
+        });
+        let requests = (0..3)
+            .map(|i| MessageRequest::new(params.clone()).with_custom_id(format!("req-{}", i)))
+            .collect();
+        let batch = executor
+            .create_message_batch(&CreateMessageBatchParams::new(requests))
+            .await
+            .unwrap();
+
+        let handle = executor.batch_handle(&batch.id);
+        assert_eq!(handle.id(), batch.id);
+
+        let options = PollOptions::new().with_interval(Duration::from_millis(1));
+        let ended = handle.wait(options).await.unwrap();
+        assert_eq!(ended.processing_status, ProcessingStatus::Ended);
+        assert_eq!(ended.progress_fraction(), 1.0);
+        assert_eq!(handle.refresh().await.unwrap().request_counts.succeeded, 3);
+
+        let results: Vec<_> = handle.results().await.unwrap().try_collect().await.unwrap();
+        assert_eq!(results.len(), 3);
+
+        let canceled = handle.cancel().await.unwrap();
+        assert_eq!(canceled.processing_status, ProcessingStatus::Ended);
+
+        assert_eq!(handle.delete().await.unwrap().id, batch.id);
+        assert!(handle.refresh().await.is_err());
+    }
+}
@@ -0,0 +1,518 @@
+//! Running message batches through the Messages API
+//!
+//! [`LocalBatchExecutor`] implements [`MessageBatchClient`] by sending every request of
+//! a batch to `create_message` right away, a few at a time, instead of queueing the
+//! batch with the Batches API. Results come back within minutes instead of hours, at
+//! realtime prices, which suits small jobs, tests and urgent reruns. Code written
+//! against [`MessageBatchClient`] runs unchanged on either.
+//!
+//! Batches live in memory and are lost when the executor is dropped, which also stops
+//! sending the requests of batches still in progress. They have no `results_url`;
+//! read their results with `retrieve_message_batch_results`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::batch::local::LocalBatchExecutor;
+//! use anthropic_ai_sdk::batch::poll::{MessageBatchClientExt, PollOptions};
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::types::message::MessageError;
+//! use anthropic_ai_sdk::types::message_batches::{
+//!     CreateMessageBatchParams, MessageBatchClient, RetrieveMessageBatchResultsParams,
+//! };
+//! use futures_util::StreamExt;
+//!
+//! # async fn example(params: CreateMessageBatchParams) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+//! let executor = LocalBatchExecutor::new(client).with_concurrency(8);
+//!
+//! let batch = executor.create_message_batch(&params).await?;
+//! let params = RetrieveMessageBatchResultsParams::new(batch.id);
+//! let mut results = Box::pin(
+//!     executor
+//!         .wait_for_batch_results(&params, PollOptions::new())
+//!         .await?,
+//! );
+//! while let Some(result) = results.next().await {
+//!     println!("{}", result?.custom_id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::types::error::{ApiErrorDetail, ApiErrorKind, ApiErrorResponse};
+use crate::types::message::MessageClient;
+use crate::types::message_batches::{
+    BatchRequestResult, CancelMessageBatchParams, CreateMessageBatchParams,
+    DeleteMessageBatchParams, DeleteResponse, ListMessageBatchesParams, ListMessageBatchesResponse,
+    MessageBatch, MessageBatchClient, MessageBatchError, MessageBatchResult, MessageRequest,
+    ProcessingStatus, RequestCounts, RetrieveMessageBatchParams, RetrieveMessageBatchResponse,
+    RetrieveMessageBatchResultsParams,
+};
+use async_trait::async_trait;
+use futures_util::{Stream, StreamExt};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use time::{Duration, OffsetDateTime};
+use tokio::task::AbortHandle;
+
+/// Runs message batches through the realtime Messages API
+#[derive(Debug)]
+pub struct LocalBatchExecutor<C> {
+    inner: Arc<Inner<C>>,
+    concurrency: usize,
+    /// Tasks sending the requests of batches, aborted when the executor is dropped
+    tasks: Mutex<Vec<AbortHandle>>,
+}
+
+#[derive(Debug)]
+struct Inner<C> {
+    client: C,
+    next_id: AtomicU64,
+    /// Batches with their results, oldest first
+    batches: Mutex<Vec<(MessageBatch, Vec<MessageBatchResult>)>>,
+}
+
+impl<C> LocalBatchExecutor<C>
+where
+    C: MessageClient + Send + Sync + 'static,
+{
+    /// Create an executor sending up to 4 requests at a time through `client`
+    pub fn new(client: C) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                client,
+                next_id: AtomicU64::new(1),
+                batches: Mutex::new(Vec::new()),
+            }),
+            concurrency: 4,
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Set how many requests are sent at a time
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// The client requests are sent with
+    pub fn client(&self) -> &C {
+        &self.inner.client
+    }
+}
+
+impl<C> Drop for LocalBatchExecutor<C> {
+    fn drop(&mut self) {
+        let tasks = self.tasks.get_mut().unwrap_or_else(|e| e.into_inner());
+        for task in tasks.drain(..) {
+            task.abort();
+        }
+    }
+}
+
+impl<C> Inner<C>
+where
+    C: MessageClient + Send + Sync + 'static,
+{
+    /// Apply `update` to the batch with `id`
+    fn update<T>(
+        &self,
+        id: &str,
+        update: impl FnOnce(&mut MessageBatch, &mut Vec<MessageBatchResult>) -> T,
+    ) -> Result<T, MessageBatchError> {
+        let mut batches = self.batches.lock().unwrap();
+        let (batch, results) = batches
+            .iter_mut()
+            .find(|(batch, _)| batch.id == id)
+            .ok_or_else(|| not_found(id))?;
+        Ok(update(batch, results))
+    }
+
+    /// Send every request of the batch with `id`, recording the results as they arrive
+    async fn run(self: Arc<Self>, id: String, requests: Vec<MessageRequest>, concurrency: usize) {
+        let this = &self;
+        let id = id.as_str();
+        futures_util::stream::iter(requests)
+            .map(|request| async move {
+                let canceling = this
+                    .update(id, |batch, _| {
+                        batch.processing_status == ProcessingStatus::Canceling
+                    })
+                    .unwrap_or(true);
+                let result = if canceling {
+                    BatchRequestResult::Canceled
+                } else {
+                    match this.client.create_message(Some(&request.params)).await {
+                        Ok(message) => BatchRequestResult::Succeeded { message },
+                        Err(error) => BatchRequestResult::Errored {
+                            error: ApiErrorResponse {
+                                type_: "error".to_string(),
+                                error: error.error_detail(),
+                            },
+                        },
+                    }
+                };
+                MessageBatchResult {
+                    custom_id: request.custom_id.unwrap_or_default(),
+                    result,
+                }
+            })
+            .buffer_unordered(concurrency)
+            .for_each(|result| {
+                let _ = this.update(id, |batch, results| {
+                    let counts = &mut batch.request_counts;
+                    counts.processing = counts.processing.saturating_sub(1);
+                    match result.result {
+                        BatchRequestResult::Succeeded { .. } => counts.succeeded += 1,
+                        BatchRequestResult::Errored { .. } => counts.errored += 1,
+                        BatchRequestResult::Canceled => counts.canceled += 1,
+                        BatchRequestResult::Expired => counts.expired += 1,
+                    }
+                    results.push(result);
+                });
+                std::future::ready(())
+            })
+            .await;
+
+        let _ = self.update(id, |batch, _| {
+            batch.processing_status = ProcessingStatus::Ended;
+            batch.ended_at = Some(OffsetDateTime::now_utc());
+        });
+    }
+}
+
+#[async_trait]
+impl<C> MessageBatchClient for LocalBatchExecutor<C>
+where
+    C: MessageClient + Send + Sync + 'static,
+{
+    /// Creates a batch and starts sending its requests in the background
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if the batch is invalid, see
+    /// [`CreateMessageBatchParams::validate`].
+    async fn create_message_batch<'a>(
+        &'a self,
+        params: &'a CreateMessageBatchParams,
+    ) -> Result<MessageBatch, MessageBatchError> {
+        params.validate()?;
+        let number = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let created_at = OffsetDateTime::now_utc();
+        let batch = MessageBatch {
+            id: format!("msgbatch_local_{}", number),
+            type_: "message_batch".to_string(),
+            created_at,
+            expires_at: created_at + Duration::days(1),
+            archived_at: None,
+            cancel_initiated_at: None,
+            ended_at: None,
+            processing_status: ProcessingStatus::InProgress,
+            request_counts: RequestCounts {
+                processing: params.requests.len() as u32,
+                ..Default::default()
+            },
+            results_url: None,
+        };
+        self.inner
+            .batches
+            .lock()
+            .unwrap()
+            .push((batch.clone(), Vec::new()));
+        let task = tokio::spawn(self.inner.clone().run(
+            batch.id.clone(),
+            params.requests.clone(),
+            self.concurrency,
+        ));
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(task.abort_handle());
+        Ok(batch)
+    }
+
+    /// Lists the batches of this executor, newest first
+    async fn list_message_batches<'a>(
+        &'a self,
+        params: Option<&'a ListMessageBatchesParams>,
+    ) -> Result<ListMessageBatchesResponse, MessageBatchError> {
+        let batches: Vec<MessageBatch> = {
+            let batches = self.inner.batches.lock().unwrap();
+            batches
+                .iter()
+                .rev()
+                .map(|(batch, _)| batch.clone())
+                .collect()
+        };
+        let position = |id: &Option<String>| {
+            id.as_deref()
+                .and_then(|id| batches.iter().position(|batch| batch.id == id))
+        };
+        let limit = params.and_then(|p| p.limit).unwrap_or(20).max(1) as usize;
+        let start = params
+            .and_then(|p| position(&p.after_id))
+            .map_or(0, |i| i + 1);
+        let end = params
+            .and_then(|p| position(&p.before_id))
+            .unwrap_or(batches.len())
+            .max(start);
+
+        let before = params.is_some_and(|p| p.before_id.is_some());
+        let (start, end) = if before {
+            (end.saturating_sub(limit).max(start), end)
+        } else {
+            (start, end.min(start + limit))
+        };
+        let has_more = if before {
+            start > 0
+        } else {
+            end < batches.len()
+        };
+        let data = batches[start..end].to_vec();
+        Ok(ListMessageBatchesResponse {
+            first_id: data.first().map(|batch| batch.id.clone()),
+            last_id: data.last().map(|batch| batch.id.clone()),
+            has_more,
+            data,
+        })
+    }
+
+    async fn retrieve_message_batch<'a>(
+        &'a self,
+        params: &'a RetrieveMessageBatchParams,
+    ) -> Result<RetrieveMessageBatchResponse, MessageBatchError> {
+        self.inner
+            .update(&params.message_batch_id, |batch, _| batch.clone())
+    }
+
+    /// Streams the results of an ended batch, in the order the requests completed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MessageBatchError::ResultsNotAvailable`] if the batch has not ended.
+    async fn retrieve_message_batch_results<'a>(
+        &'a self,
+        params: &'a RetrieveMessageBatchResultsParams,
+    ) -> Result<
+        impl Stream<Item = Result<MessageBatchResult, MessageBatchError>> + Send + 'a,
+        MessageBatchError,
+    > {
+        let id = &params.message_batch_id;
+        let results = self.inner.update(id, |batch, results| {
+            (batch.processing_status == ProcessingStatus::Ended).then(|| results.clone())
+        })?;
+        let results = results.ok_or_else(|| MessageBatchError::ResultsNotAvailable(id.clone()))?;
+        Ok(futures_util::stream::iter(results.into_iter().map(Ok)))
+    }
+
+    /// Cancels a batch: requests that have not been sent yet end as canceled
+    async fn cancel_message_batch<'a>(
+        &'a self,
+        params: &'a CancelMessageBatchParams,
+    ) -> Result<MessageBatch, MessageBatchError> {
+        self.inner.update(&params.message_batch_id, |batch, _| {
+            if batch.processing_status == ProcessingStatus::InProgress {
+                batch.processing_status = ProcessingStatus::Canceling;
+                batch.cancel_initiated_at = Some(OffsetDateTime::now_utc());
+            }
+            batch.clone()
+        })
+    }
+
+    /// Deletes an ended batch and its results
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if the batch does not exist or has not ended.
+    async fn delete_message_batch<'a>(
+        &'a self,
+        params: &'a DeleteMessageBatchParams,
+    ) -> Result<DeleteResponse, MessageBatchError> {
+        let id = &params.message_batch_id;
+        let mut batches = self.inner.batches.lock().unwrap();
+        let index = batches
+            .iter()
+            .position(|(batch, _)| &batch.id == id)
+            .ok_or_else(|| not_found(id))?;
+        if batches[index].0.processing_status != ProcessingStatus::Ended {
+            return Err(MessageBatchError::InvalidRequest(format!(
+                "message batch {} must end before it can be deleted",
+                id
+            )));
+        }
+        batches.remove(index);
+        Ok(DeleteResponse {
+            id: id.clone(),
+            obj_type: "message_batch_deleted".to_string(),
+        })
+    }
+}
+
+/// The error the API returns for an unknown batch id
+fn not_found(id: &str) -> MessageBatchError {
+    let response = ApiErrorResponse {
+        type_: "error".to_string(),
+        error: ApiErrorDetail {
+            kind: ApiErrorKind::NotFound,
+            message: format!("Message batch {} not found", id),
+        },
+    };
+    MessageBatchError::ApiError(
+        serde_json::to_string(&response).expect("API error responses serialize"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::poll::{MessageBatchClientExt, PollOptions};
+    use crate::batch::test_support::{MockMessages, response};
+    use crate::types::message::{
+        CreateMessageParams, CreateMessageResponse, Message, MessageError, RequiredMessageParams,
+        Role,
+    };
+
+    /// Answers after `delay`; requests to the model `overloaded` fail
+    struct MockClient {
+        delay: std::time::Duration,
+    }
+
+    #[async_trait]
+    impl MockMessages for MockClient {
+        async fn create(
+            &self,
+            params: &CreateMessageParams,
+        ) -> Result<CreateMessageResponse, MessageError> {
+            tokio::time::sleep(self.delay).await;
+            if params.model == "overloaded" {
+                return Err(MessageError::Api(ApiErrorDetail {
+                    kind: ApiErrorKind::Overloaded,
+                    message: "Overloaded".to_string(),
+                }));
+            }
+            Ok(response(&params.model))
+        }
+    }
+
+    fn batch(models: &[&str]) -> CreateMessageBatchParams {
+        let requests = models.iter().enumerate().map(|(i, model)| {
+            let params = CreateMessageParams::new(RequiredMessageParams {
+                model: model.to_string(),
+                messages: vec![Message::new_text(Role::User, "Hello!")],
+                max_tokens: 100,
+            });
+            MessageRequest::new(params).with_custom_id(format!("req-{}", i))
+        });
+        CreateMessageBatchParams::new(requests.collect())
+    }
+
+    fn fast() -> PollOptions {
+        PollOptions::new().with_interval(std::time::Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn runs_batches_through_create_message() {
+        let executor = LocalBatchExecutor::new(MockClient {
+            delay: std::time::Duration::ZERO,
+        })
+        .with_concurrency(2);
+        let created = executor
+            .create_message_batch(&batch(&["claude", "overloaded", "claude"]))
+            .await
+            .unwrap();
+        assert_eq!(created.request_counts.processing, 3);
+
+        let params = RetrieveMessageBatchResultsParams::new(created.id.as_str());
+        let results: Vec<_> = executor
+            .wait_for_batch_results(&params, fast())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(results.len(), 3);
+        let errored = results
+            .iter()
+            .map(|result| result.as_ref().unwrap())
+            .find(|result| result.custom_id == "req-1")
+            .unwrap();
+        assert_eq!(
+            errored.result.error().unwrap().kind,
+            ApiErrorKind::Overloaded
+        );
+
+        let ended = executor
+            .retrieve_message_batch(&RetrieveMessageBatchParams::new(created.id.as_str()))
+            .await
+            .unwrap();
+        assert_eq!(ended.request_counts.succeeded, 2);
+        assert_eq!(ended.request_counts.errored, 1);
+
+        let listed = executor.list_message_batches(None).await.unwrap();
+        assert_eq!(listed.data.len(), 1);
+        executor
+            .delete_message_batch(&DeleteMessageBatchParams::new(created.id.as_str()))
+            .await
+            .unwrap();
+        let params = RetrieveMessageBatchParams::new(created.id.as_str());
+        match executor.retrieve_message_batch(&params).await {
+            Err(MessageBatchError::ApiError(body)) => {
+                let detail = ApiErrorDetail::parse(&body).unwrap();
+                assert_eq!(detail.kind, ApiErrorKind::NotFound);
+            }
+            other => panic!("expected a not found error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn canceling_skips_requests_not_sent_yet() {
+        let executor = LocalBatchExecutor::new(MockClient {
+            delay: std::time::Duration::from_millis(20),
+        })
+        .with_concurrency(1);
+        let created = executor
+            .create_message_batch(&batch(&["claude", "claude", "claude"]))
+            .await
+            .unwrap();
+        assert!(
+            executor
+                .retrieve_message_batch_results(&RetrieveMessageBatchResultsParams::new(
+                    created.id.as_str()
+                ))
+                .await
+                .is_err()
+        );
+
+        let canceled = executor
+            .cancel_message_batch(&CancelMessageBatchParams::new(created.id.as_str()))
+            .await
+            .unwrap();
+        assert_eq!(canceled.processing_status, ProcessingStatus::Canceling);
+
+        let ended = executor.wait_for_batch(&created.id, fast()).await.unwrap();
+        assert!(ended.request_counts.canceled >= 2);
+        assert_eq!(
+            ended.request_counts.succeeded + ended.request_counts.canceled,
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn dropping_the_executor_stops_its_batches() {
+        let executor = LocalBatchExecutor::new(MockClient {
+            delay: std::time::Duration::from_millis(20),
+        })
+        .with_concurrency(1);
+        executor
+            .create_message_batch(&batch(&["claude", "claude", "claude"]))
+            .await
+            .unwrap();
+        let inner = executor.inner.clone();
+
+        drop(executor);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(Arc::strong_count(&inner), 1);
+        let batches = inner.batches.lock().unwrap();
+        assert_eq!(batches[0].0.processing_status, ProcessingStatus::InProgress);
+        assert_eq!(batches[0].0.request_counts.processing, 3);
+    }
+}
@@ -0,0 +1,417 @@
+//! Batch jobs that survive process restarts
+//!
+//! [`BatchJobManager`] submits a job through a [`BatchPlanner`], records it in a
+//! [`BatchJobStore`], and later polls its batches and downloads their results. Since
+//! everything it needs is in the store, a restarted process calls
+//! [`resume`](BatchJobManager::resume) to continue where the previous one stopped,
+//! even if it stopped while submitting.
+//! Results of a batch are downloaded once: batches already marked as downloaded are
+//! never fetched again.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::batch::manager::BatchJobManager;
+//! use anthropic_ai_sdk::batch::store::{BatchJobStore, FileJobStore};
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::types::message_batches::{MessageBatchError, MessageRequest};
+//!
+//! # async fn example(requests: Vec<MessageRequest>) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageBatchError>("your-api-key", "2023-06-01")?;
+//! let manager = BatchJobManager::new(client, FileJobStore::open("batch-jobs")?);
+//!
+//! if !manager.store().jobs()?.contains(&"nightly".to_string()) {
+//!     manager.submit("nightly", requests).await?;
+//! }
+//! // After a restart, this picks up the batches submitted by the previous process
+//! manager.wait("nightly").await?;
+//!
+//! if let Some(result) = manager.result("nightly", "request-1")? {
+//!     println!("{:?}", result.result);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::batch::group::BatchGroup;
+use crate::batch::planner::BatchPlanner;
+use crate::batch::poll::{MessageBatchClientExt, PollOptions};
+use crate::batch::store::{BatchJobStore, BatchRecord};
+use crate::types::message_batches::{
+    MessageBatch, MessageBatchClient, MessageBatchError, MessageBatchResult, MessageRequest,
+    RetrieveMessageBatchParams, RetrieveMessageBatchResultsParams,
+};
+use futures_util::TryStreamExt;
+
+/// Submits batch jobs and collects their results through a store
+#[derive(Debug)]
+pub struct BatchJobManager<C, S> {
+    client: C,
+    store: S,
+    planner: BatchPlanner,
+    poll_options: PollOptions,
+}
+
+impl<C, S> BatchJobManager<C, S>
+where
+    C: MessageBatchClient + Sync,
+    S: BatchJobStore,
+{
+    /// Create a manager with the default planner and poll options
+    pub fn new(client: C, store: S) -> Self {
+        Self {
+            client,
+            store,
+            planner: BatchPlanner::default(),
+            poll_options: PollOptions::default(),
+        }
+    }
+
+    /// Set the planner used to split jobs into batches
+    pub fn with_planner(mut self, planner: BatchPlanner) -> Self {
+        self.planner = planner;
+        self
+    }
+
+    /// Set how [`wait`](Self::wait) polls batches
+    pub fn with_poll_options(mut self, poll_options: PollOptions) -> Self {
+        self.poll_options = poll_options;
+        self
+    }
+
+    /// The client batches are sent with
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    /// The store jobs are recorded in
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Record a new job and submit its requests
+    ///
+    /// Each batch is recorded as soon as it is created. If submission stops part way,
+    /// because of an error or because the process exits, the requests that were not
+    /// submitted stay in the store and are submitted by
+    /// [`submit_pending`](Self::submit_pending), [`wait`](Self::wait) or
+    /// [`resume`](Self::resume).
+    ///
+    /// # Returns
+    ///
+    /// Returns the batches the job was submitted as.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if the requests are invalid, or the job cannot be
+    /// recorded or submitted. If some batches were created before the failure,
+    /// [`MessageBatchError::PartiallySubmitted`] is returned.
+    pub async fn submit(
+        &self,
+        job_id: &str,
+        requests: impl IntoIterator<Item = MessageRequest>,
+    ) -> Result<BatchGroup, MessageBatchError> {
+        let requests: Vec<MessageRequest> = requests.into_iter().collect();
+        self.planner.plan(requests.iter().cloned())?;
+        self.store.create_job(job_id, &requests)?;
+        self.submit_pending(job_id).await
+    }
+
+    /// Submit the requests of a job that are not in any of its recorded batches
+    ///
+    /// Requests are submitted in order, so the requests already submitted are the first
+    /// ones of the job, as many as the recorded batches hold. A batch created right
+    /// before the process exits, before it could be recorded, is submitted again.
+    ///
+    /// # Returns
+    ///
+    /// Returns every batch of the job.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if the store or the API fails. If the job already
+    /// has batches, the error is [`MessageBatchError::PartiallySubmitted`] with their
+    /// ids.
+    pub async fn submit_pending(&self, job_id: &str) -> Result<BatchGroup, MessageBatchError> {
+        let records = self.store.batches(job_id)?;
+        let pending = self.pending(job_id, &records)?;
+        let mut batch_ids: Vec<String> = records.into_iter().map(|r| r.batch_id).collect();
+        let submitted = async {
+            if pending.is_empty() {
+                return Ok(());
+            }
+            for batch in self.planner.plan(pending)? {
+                let created = self.client.create_message_batch(&batch).await?;
+                batch_ids.push(created.id.clone());
+                self.store
+                    .save_batch(job_id, &BatchRecord::from(&created))?;
+            }
+            Ok(())
+        }
+        .await;
+
+        match submitted {
+            Ok(()) => Ok(BatchGroup::new(batch_ids)),
+            Err(error) if batch_ids.is_empty() => Err(error),
+            Err(error) => Err(MessageBatchError::PartiallySubmitted {
+                batch_ids,
+                error: Box::new(error),
+            }),
+        }
+    }
+
+    /// Retrieve every batch of a job once, downloading the results of those that ended
+    ///
+    /// # Returns
+    ///
+    /// Returns whether every request of the job has been submitted and the results of
+    /// every batch have been downloaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of the store or the API.
+    pub async fn refresh(&self, job_id: &str) -> Result<bool, MessageBatchError> {
+        let records = self.store.batches(job_id)?;
+        let mut done = self.pending(job_id, &records)?.is_empty();
+        for record in records {
+            if record.downloaded {
+                continue;
+            }
+            let params = RetrieveMessageBatchParams::new(record.batch_id.as_str());
+            let batch = self.client.retrieve_message_batch(&params).await?;
+            done &= self.update(job_id, &batch).await?;
+        }
+        Ok(done)
+    }
+
+    /// Submit the pending requests of a job, then wait for every batch of the job to
+    /// end and download their results
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of [`submit_pending`](Self::submit_pending), the store
+    /// or the API, including [`MessageBatchError::Timeout`] if the poll options set a
+    /// deadline.
+    pub async fn wait(&self, job_id: &str) -> Result<(), MessageBatchError> {
+        self.submit_pending(job_id).await?;
+        for record in self.store.batches(job_id)? {
+            if record.downloaded {
+                continue;
+            }
+            let batch = self
+                .client
+                .wait_for_batch(&record.batch_id, self.poll_options.clone())
+                .await?;
+            self.update(job_id, &batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Wait for every stored job, one after the other
+    ///
+    /// Call this after a restart to finish the jobs the previous process submitted,
+    /// including jobs whose submission was interrupted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of [`wait`](Self::wait).
+    pub async fn resume(&self) -> Result<(), MessageBatchError> {
+        for job_id in self.store.jobs()? {
+            self.wait(&job_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Downloaded result of the request with `custom_id`
+    ///
+    /// Returns `None` until the batch holding the request has been downloaded.
+    pub fn result(
+        &self,
+        job_id: &str,
+        custom_id: &str,
+    ) -> Result<Option<MessageBatchResult>, MessageBatchError> {
+        self.store.result(job_id, custom_id)
+    }
+
+    /// Requests of a job that are not in any of `records`
+    fn pending(
+        &self,
+        job_id: &str,
+        records: &[BatchRecord],
+    ) -> Result<Vec<MessageRequest>, MessageBatchError> {
+        // The request counts of a batch always add up to its number of requests
+        let submitted: usize = records
+            .iter()
+            .map(|record| record.request_counts.total() as usize)
+            .sum();
+        let mut requests = self.store.requests(job_id)?;
+        requests.drain(..submitted.min(requests.len()));
+        Ok(requests)
+    }
+
+    /// Record the state of `batch`, downloading its results if it ended
+    ///
+    /// Returns whether the results were downloaded.
+    async fn update(&self, job_id: &str, batch: &MessageBatch) -> Result<bool, MessageBatchError> {
+        let record = BatchRecord::from(batch);
+        self.store.save_batch(job_id, &record)?;
+        if !record.is_ended() {
+            return Ok(false);
+        }
+        let params = RetrieveMessageBatchResultsParams::new(batch.id.as_str());
+        let results: Vec<MessageBatchResult> = self
+            .client
+            .retrieve_message_batch_results(&params)
+            .await?
+            .try_collect()
+            .await?;
+        self.store.save_results(job_id, &batch.id, &results)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::store::FileJobStore;
+    use crate::batch::test_support::{MockBatches, batch_json, request};
+    use crate::types::message_batches::{
+        BatchRequestResult, CreateMessageBatchParams, RequestCounts,
+    };
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Ends batches once `ended` is set and expires every request, and fails to create
+    /// batches once `max_batches` exist
+    #[derive(Default)]
+    struct MockClient {
+        batches: Mutex<HashMap<String, Vec<String>>>,
+        max_batches: Mutex<Option<usize>>,
+        ended: AtomicBool,
+        downloads: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl MockBatches for MockClient {
+        async fn create(
+            &self,
+            params: &CreateMessageBatchParams,
+        ) -> Result<MessageBatch, MessageBatchError> {
+            let custom_ids = params
+                .requests
+                .iter()
+                .filter_map(|request| request.custom_id.clone())
+                .collect();
+            let id = {
+                let mut batches = self.batches.lock().unwrap();
+                if Some(batches.len()) == *self.max_batches.lock().unwrap() {
+                    return Err(MessageBatchError::ApiError("overloaded".to_string()));
+                }
+                let id = format!("msgbatch_{}", batches.len() + 1);
+                batches.insert(id.clone(), custom_ids);
+                id
+            };
+            self.retrieve(&id).await
+        }
+
+        async fn retrieve(&self, batch_id: &str) -> Result<MessageBatch, MessageBatchError> {
+            let requests = self.batches.lock().unwrap()[batch_id].len() as u32;
+            if self.ended.load(Ordering::SeqCst) {
+                let counts = RequestCounts {
+                    expired: requests,
+                    ..Default::default()
+                };
+                Ok(batch_json(batch_id, "ended", counts))
+            } else {
+                let counts = RequestCounts {
+                    processing: requests,
+                    ..Default::default()
+                };
+                Ok(batch_json(batch_id, "in_progress", counts))
+            }
+        }
+
+        async fn results(
+            &self,
+            batch_id: &str,
+        ) -> Result<Vec<MessageBatchResult>, MessageBatchError> {
+            self.downloads.fetch_add(1, Ordering::SeqCst);
+            let custom_ids = self.batches.lock().unwrap()[batch_id].clone();
+            let results = custom_ids.into_iter().map(|custom_id| MessageBatchResult {
+                custom_id,
+                result: BatchRequestResult::Expired,
+            });
+            Ok(results.collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn resumes_and_downloads_results_once() {
+        let dir = std::env::temp_dir().join(format!("batch-job-manager-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let manager =
+            BatchJobManager::new(MockClient::default(), FileJobStore::open(&dir).unwrap())
+                .with_planner(BatchPlanner::new().with_max_requests(2));
+
+        let requests = (0..3).map(|i| request(&format!("req-{}", i)));
+        let group = manager.submit("job-1", requests).await.unwrap();
+        assert_eq!(group.len(), 2);
+        assert!(!manager.refresh("job-1").await.unwrap());
+        assert!(manager.result("job-1", "req-2").unwrap().is_none());
+
+        // A new manager over the same store picks the job up
+        let BatchJobManager { client, .. } = manager;
+        client.ended.store(true, Ordering::SeqCst);
+        let manager = BatchJobManager::new(client, FileJobStore::open(&dir).unwrap());
+        manager.resume().await.unwrap();
+        assert!(manager.refresh("job-1").await.unwrap());
+        manager.resume().await.unwrap();
+        assert_eq!(manager.client().downloads.load(Ordering::SeqCst), 2);
+
+        let result = manager.result("job-1", "req-2").unwrap().unwrap();
+        assert!(matches!(result.result, BatchRequestResult::Expired));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn finishes_interrupted_submissions() {
+        let dir = std::env::temp_dir().join(format!("batch-job-submit-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let client = MockClient::default();
+        *client.max_batches.lock().unwrap() = Some(1);
+        let manager = BatchJobManager::new(client, FileJobStore::open(&dir).unwrap())
+            .with_planner(BatchPlanner::new().with_max_requests(1));
+
+        let requests = (0..3).map(|i| request(&format!("req-{}", i)));
+        let error = manager.submit("job-1", requests).await.unwrap_err();
+        assert!(matches!(
+            error,
+            MessageBatchError::PartiallySubmitted { ref batch_ids, .. } if batch_ids.len() == 1
+        ));
+        // Nothing was created for the second job, but it is recorded
+        let requests = (3..5).map(|i| request(&format!("req-{}", i)));
+        let error = manager.submit("job-2", requests).await.unwrap_err();
+        assert!(matches!(error, MessageBatchError::ApiError(_)));
+        assert!(manager.store().batches("job-2").unwrap().is_empty());
+        assert!(!manager.refresh("job-2").await.unwrap());
+
+        // A restarted process with a different planner submits the rest
+        let BatchJobManager { client, .. } = manager;
+        *client.max_batches.lock().unwrap() = None;
+        client.ended.store(true, Ordering::SeqCst);
+        let manager = BatchJobManager::new(client, FileJobStore::open(&dir).unwrap());
+        manager.resume().await.unwrap();
+        assert_eq!(manager.store().batches("job-1").unwrap().len(), 2);
+        assert_eq!(manager.store().batches("job-2").unwrap().len(), 1);
+        assert!(manager.refresh("job-1").await.unwrap());
+        assert!(manager.refresh("job-2").await.unwrap());
+        for i in 0..5 {
+            let custom_id = format!("req-{}", i);
+            let job_id = if i < 3 { "job-1" } else { "job-2" };
+            assert!(manager.result(job_id, &custom_id).unwrap().is_some());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
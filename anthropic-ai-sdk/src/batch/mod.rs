@@ -0,0 +1,48 @@
+//! Message batch helpers
+//!
+//! This module builds on the Message Batches API: [`planner::BatchPlanner`] splits any
+//! number of requests into batches that fit the API limits and submits them,
+//! [`group::BatchGroup`] tracks the resulting batches as one, and
+//! [`poll::MessageBatchClientExt`] waits for a batch to end and hands out a
+//! [`handle::MessageBatchHandle`] for working with a single batch.
+//! [`manager::BatchJobManager`] combines them with a [`store::BatchJobStore`] to run
+//! jobs that outlive the process submitting them.
+//! [`resubmit::ResubmitPolicy`] sends expired and failed requests again.
+//! [`local::LocalBatchExecutor`] runs batches through the realtime Messages API.
+//! [`estimate::BatchEstimator`] prices a batch before it is submitted.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::batch::planner::BatchPlanner;
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::types::message_batches::{MessageBatchError, MessageRequest};
+//! use futures_util::StreamExt;
+//!
+//! # async fn example(requests: Vec<MessageRequest>) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageBatchError>("your-api-key", "2023-06-01")?;
+//!
+//! let group = BatchPlanner::new().submit(&client, requests).await?;
+//! println!("Created {} batches", group.len());
+//!
+//! if group.is_ended(&client).await? {
+//!     let mut results = Box::pin(group.results(&client));
+//!     while let Some(result) = results.next().await {
+//!         println!("{}", result?.custom_id);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod estimate;
+pub mod group;
+pub mod handle;
+pub mod local;
+pub mod manager;
+pub mod planner;
+pub mod poll;
+pub mod resubmit;
+pub mod store;
+#[cfg(test)]
+pub(crate) mod test_support;
@@ -0,0 +1,274 @@
+//! Splitting requests into batches
+//!
+//! A single batch holds at most [`MAX_BATCH_REQUESTS`] requests and
+//! [`MAX_BATCH_BYTES`] of JSON. [`BatchPlanner`] fills batches up to those limits,
+//! checking every request and the uniqueness of `custom_id`s across all batches.
+
+use crate::batch::group::BatchGroup;
+use crate::types::message_batches::{
+    CreateMessageBatchParams, MAX_BATCH_BYTES, MAX_BATCH_REQUESTS, MessageBatchClient,
+    MessageBatchError, MessageRequest,
+};
+use std::collections::HashSet;
+
+/// Size of the JSON of a batch without its requests
+const BATCH_OVERHEAD: usize = r#"{"requests":[]}"#.len();
+
+/// Splits requests into batches that fit the API limits
+#[derive(Debug, Clone, Copy)]
+pub struct BatchPlanner {
+    max_requests: usize,
+    max_bytes: usize,
+}
+
+impl Default for BatchPlanner {
+    fn default() -> Self {
+        Self {
+            max_requests: MAX_BATCH_REQUESTS,
+            max_bytes: MAX_BATCH_BYTES,
+        }
+    }
+}
+
+impl BatchPlanner {
+    /// Create a planner filling batches up to the API limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the number of requests per batch, up to [`MAX_BATCH_REQUESTS`]
+    pub fn with_max_requests(mut self, max_requests: usize) -> Self {
+        self.max_requests = max_requests.clamp(1, MAX_BATCH_REQUESTS);
+        self
+    }
+
+    /// Limit the serialized size of each batch, up to [`MAX_BATCH_BYTES`]
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes.min(MAX_BATCH_BYTES);
+        self
+    }
+
+    /// Split `requests` into batches, keeping their order
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if a request is invalid, two requests share a
+    /// `custom_id`, or a single request does not fit in a batch.
+    pub fn plan(
+        &self,
+        requests: impl IntoIterator<Item = MessageRequest>,
+    ) -> Result<Vec<CreateMessageBatchParams>, MessageBatchError> {
+        let mut splitter = Splitter::new(self);
+        let mut batches = Vec::new();
+        for request in requests {
+            batches.extend(splitter.push(request)?);
+        }
+        batches.extend(splitter.finish());
+        Ok(batches)
+    }
+
+    /// Split `requests` into batches and create them
+    ///
+    /// Each batch is created as soon as it is full, so only one batch is held in
+    /// memory at a time. Requests are checked as they are read; if one is invalid,
+    /// the batches created before it are not canceled.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`plan`](Self::plan) or of creating a batch. If some
+    /// batches were already created, the error is
+    /// [`MessageBatchError::PartiallySubmitted`] with their ids.
+    pub async fn submit<C>(
+        &self,
+        client: &C,
+        requests: impl IntoIterator<Item = MessageRequest>,
+    ) -> Result<BatchGroup, MessageBatchError>
+    where
+        C: MessageBatchClient + Sync,
+    {
+        let mut splitter = Splitter::new(self);
+        let mut batch_ids = Vec::new();
+        let submitted = async {
+            for request in requests {
+                if let Some(batch) = splitter.push(request)? {
+                    batch_ids.push(client.create_message_batch(&batch).await?.id);
+                }
+            }
+            if let Some(batch) = splitter.finish() {
+                batch_ids.push(client.create_message_batch(&batch).await?.id);
+            }
+            Ok(())
+        }
+        .await;
+
+        match submitted {
+            Ok(()) => Ok(BatchGroup::new(batch_ids)),
+            Err(error) if batch_ids.is_empty() => Err(error),
+            Err(error) => Err(MessageBatchError::PartiallySubmitted {
+                batch_ids,
+                error: Box::new(error),
+            }),
+        }
+    }
+}
+
+/// Fills one batch at a time
+struct Splitter<'p> {
+    planner: &'p BatchPlanner,
+    custom_ids: HashSet<String>,
+    requests: Vec<MessageRequest>,
+    bytes: usize,
+}
+
+impl<'p> Splitter<'p> {
+    fn new(planner: &'p BatchPlanner) -> Self {
+        Self {
+            planner,
+            custom_ids: HashSet::new(),
+            requests: Vec::new(),
+            bytes: BATCH_OVERHEAD,
+        }
+    }
+
+    /// Add a request, returning the previous batch if the request does not fit in it
+    fn push(
+        &mut self,
+        request: MessageRequest,
+    ) -> Result<Option<CreateMessageBatchParams>, MessageBatchError> {
+        let custom_id = request.validate()?;
+        if !self.custom_ids.insert(custom_id.to_string()) {
+            return Err(MessageBatchError::DuplicateCustomId(custom_id.to_string()));
+        }
+        let size = request.serialized_size()?;
+        if BATCH_OVERHEAD + size > self.planner.max_bytes {
+            return Err(MessageBatchError::BatchSizeExceeded);
+        }
+
+        let full = self.requests.len() >= self.planner.max_requests
+            || self.bytes + 1 + size > self.planner.max_bytes;
+        let batch = if full { self.take() } else { None };
+        // Requests after the first are preceded by a comma
+        self.bytes += size + usize::from(!self.requests.is_empty());
+        self.requests.push(request);
+        Ok(batch)
+    }
+
+    /// Return the last batch
+    fn finish(&mut self) -> Option<CreateMessageBatchParams> {
+        self.take()
+    }
+
+    fn take(&mut self) -> Option<CreateMessageBatchParams> {
+        if self.requests.is_empty() {
+            return None;
+        }
+        self.bytes = BATCH_OVERHEAD;
+        Some(CreateMessageBatchParams::new(std::mem::take(
+            &mut self.requests,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::test_support::{MockBatches, batch_json, request};
+    use crate::types::message_batches::{MessageBatch, RequestCounts};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Creates batches until `fail_after` batches exist
+    struct MockClient {
+        created: Mutex<Vec<usize>>,
+        fail_after: usize,
+    }
+
+    #[async_trait]
+    impl MockBatches for MockClient {
+        async fn create(
+            &self,
+            params: &CreateMessageBatchParams,
+        ) -> Result<MessageBatch, MessageBatchError> {
+            let mut created = self.created.lock().unwrap();
+            if created.len() == self.fail_after {
+                return Err(MessageBatchError::ApiError("overloaded".to_string()));
+            }
+            created.push(params.requests.len());
+            let counts = RequestCounts {
+                processing: params.requests.len() as u32,
+                ..Default::default()
+            };
+            let id = format!("msgbatch_{}", created.len());
+            Ok(batch_json(&id, "in_progress", counts))
+        }
+    }
+
+    #[test]
+    fn splits_by_count_and_size() {
+        let requests = (0..5).map(|i| request(&format!("req-{}", i)));
+        let batches = BatchPlanner::new()
+            .with_max_requests(2)
+            .plan(requests)
+            .unwrap();
+        let sizes: Vec<_> = batches.iter().map(|b| b.requests.len()).collect();
+        assert_eq!(sizes, [2, 2, 1]);
+
+        let size = request("req-0").serialized_size().unwrap();
+        let max_bytes = BATCH_OVERHEAD + 3 * size + 2;
+        let requests = (0..7).map(|i| request(&format!("req-{}", i)));
+        let batches = BatchPlanner::new()
+            .with_max_bytes(max_bytes)
+            .plan(requests)
+            .unwrap();
+        let sizes: Vec<_> = batches.iter().map(|b| b.requests.len()).collect();
+        assert_eq!(sizes, [3, 3, 1]);
+        for batch in &batches {
+            assert!(batch.serialized_size().unwrap() <= max_bytes);
+        }
+    }
+
+    #[test]
+    fn rejects_duplicates_across_batches_and_oversized_requests() {
+        let requests = vec![request("a"), request("b"), request("a")];
+        let error = BatchPlanner::new()
+            .with_max_requests(1)
+            .plan(requests)
+            .unwrap_err();
+        assert!(matches!(error, MessageBatchError::DuplicateCustomId(id) if id == "a"));
+
+        let error = BatchPlanner::new()
+            .with_max_bytes(10)
+            .plan(vec![request("a")])
+            .unwrap_err();
+        assert!(matches!(error, MessageBatchError::BatchSizeExceeded));
+
+        assert!(BatchPlanner::new().plan(Vec::new()).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn submits_batches_as_they_fill() {
+        let client = MockClient {
+            created: Mutex::new(Vec::new()),
+            fail_after: usize::MAX,
+        };
+        let planner = BatchPlanner::new().with_max_requests(2);
+        let requests = (0..5).map(|i| request(&format!("req-{}", i)));
+        let group = planner.submit(&client, requests).await.unwrap();
+        assert_eq!(
+            group.batch_ids().collect::<Vec<_>>(),
+            ["msgbatch_1", "msgbatch_2", "msgbatch_3"]
+        );
+        assert_eq!(*client.created.lock().unwrap(), [2, 2, 1]);
+
+        let client = MockClient {
+            created: Mutex::new(Vec::new()),
+            fail_after: 1,
+        };
+        let requests = (0..5).map(|i| request(&format!("req-{}", i)));
+        let error = planner.submit(&client, requests).await.unwrap_err();
+        assert!(matches!(
+            error,
+            MessageBatchError::PartiallySubmitted { batch_ids, .. } if batch_ids == ["msgbatch_1"]
+        ));
+    }
+}
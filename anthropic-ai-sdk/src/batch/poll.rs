@@ -0,0 +1,496 @@
+//! Waiting for message batches to end
+//!
+//! [`MessageBatchClientExt`] polls a batch until its processing status is `ended`,
+//! backing off between polls, and reports progress whenever the request counts change.
+//! Transient failures to retrieve the batch are retried.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::batch::poll::{MessageBatchClientExt, PollOptions};
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::types::message_batches::MessageBatchError;
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageBatchError>("your-api-key", "2023-06-01")?;
+//!
+//! let options = PollOptions::new()
+//!     .with_deadline(Duration::from_secs(6 * 60 * 60))
+//!     .cancel_on_timeout()
+//!     .on_progress(|batch| {
+//!         let counts = &batch.request_counts;
+//!         println!("{} processing, {} succeeded", counts.processing, counts.succeeded);
+//!     });
+//! let batch = client.wait_for_batch("msgbatch_batch_id", options).await?;
+//! println!("Results: {:?}", batch.results_url);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::batch::handle::MessageBatchHandle;
+use crate::types::message_batches::{
+    CancelMessageBatchParams, MessageBatch, MessageBatchClient, MessageBatchError,
+    MessageBatchResult, RequestCounts, RetrieveMessageBatchParams,
+    RetrieveMessageBatchResultsParams,
+};
+use async_trait::async_trait;
+use futures_util::{Stream, StreamExt};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+type ProgressCallback = Arc<dyn Fn(&MessageBatch) + Send + Sync>;
+
+/// How to poll a batch until it ends
+#[derive(Clone)]
+pub struct PollOptions {
+    interval: Duration,
+    max_interval: Duration,
+    backoff_multiplier: f64,
+    deadline: Option<Duration>,
+    cancel_on_timeout: bool,
+    max_consecutive_failures: u32,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl fmt::Debug for PollOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PollOptions")
+            .field("interval", &self.interval)
+            .field("max_interval", &self.max_interval)
+            .field("backoff_multiplier", &self.backoff_multiplier)
+            .field("deadline", &self.deadline)
+            .field("cancel_on_timeout", &self.cancel_on_timeout)
+            .field("max_consecutive_failures", &self.max_consecutive_failures)
+            .field("on_progress", &self.on_progress.is_some())
+            .finish()
+    }
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            max_interval: Duration::from_secs(60),
+            backoff_multiplier: 1.5,
+            deadline: None,
+            cancel_on_timeout: false,
+            max_consecutive_failures: 5,
+            on_progress: None,
+        }
+    }
+}
+
+impl PollOptions {
+    /// Poll after 5 seconds, backing off by 1.5x up to one minute, without a deadline,
+    /// retrying up to 5 retryable failures in a row
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the delay before the second poll
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the longest delay between polls
+    pub fn with_max_interval(mut self, max_interval: Duration) -> Self {
+        self.max_interval = max_interval;
+        self
+    }
+
+    /// Set the factor the delay grows by after every poll
+    pub fn with_backoff_multiplier(mut self, backoff_multiplier: f64) -> Self {
+        self.backoff_multiplier = backoff_multiplier.max(1.0);
+        self
+    }
+
+    /// Give up with [`MessageBatchError::Timeout`] if the batch has not ended after
+    /// `deadline`
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Cancel the batch when the deadline is reached
+    pub fn cancel_on_timeout(mut self) -> Self {
+        self.cancel_on_timeout = true;
+        self
+    }
+
+    /// Set how many retryable failures to retrieve the batch in a row are retried
+    ///
+    /// Failed polls back off like successful ones and stop at the deadline. Errors that
+    /// are not [retryable](MessageBatchError::is_retryable) are returned right away.
+    pub fn with_max_consecutive_failures(mut self, max_consecutive_failures: u32) -> Self {
+        self.max_consecutive_failures = max_consecutive_failures;
+        self
+    }
+
+    /// Call `callback` with the batch whenever its request counts change, and when it
+    /// ends
+    pub fn on_progress(mut self, callback: impl Fn(&MessageBatch) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+
+    fn next_interval(&self, interval: Duration) -> Duration {
+        interval
+            .mul_f64(self.backoff_multiplier)
+            .min(self.max_interval)
+    }
+}
+
+/// Polling helpers for any [`MessageBatchClient`]
+#[async_trait]
+pub trait MessageBatchClientExt: MessageBatchClient + Sync {
+    /// Stream the batch whenever its request counts change, until it ends
+    ///
+    /// The last item is the ended batch. The stream yields an error and ends if
+    /// retrieving the batch fails more often in a row than the options allow, or the
+    /// deadline is reached.
+    fn watch_batch<'a>(
+        &'a self,
+        batch_id: &str,
+        options: PollOptions,
+    ) -> impl Stream<Item = Result<MessageBatch, MessageBatchError>> + Send + 'a
+    where
+        Self: Sized,
+    {
+        let poller = Poller {
+            client: self,
+            params: RetrieveMessageBatchParams::new(batch_id),
+            interval: options.interval,
+            options,
+            started: Instant::now(),
+            counts: None,
+            failures: 0,
+            polled: false,
+            done: false,
+        };
+        futures_util::stream::unfold(poller, Poller::next)
+    }
+
+    /// Wait for a batch to end
+    ///
+    /// # Returns
+    ///
+    /// Returns the ended batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if retrieving the batch keeps failing, or
+    /// [`MessageBatchError::Timeout`] if the deadline is reached. The batch is canceled
+    /// on timeout if the options ask for it.
+    async fn wait_for_batch(
+        &self,
+        batch_id: &str,
+        options: PollOptions,
+    ) -> Result<MessageBatch, MessageBatchError>
+    where
+        Self: Sized,
+    {
+        let mut updates = Box::pin(self.watch_batch(batch_id, options));
+        let mut last = None;
+        while let Some(batch) = updates.next().await {
+            last = Some(batch?);
+        }
+        last.ok_or_else(|| MessageBatchError::PollingEnded(batch_id.to_string()))
+    }
+
+    /// Wait for a batch to end and stream its results
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`wait_for_batch`](Self::wait_for_batch) and
+    /// `retrieve_message_batch_results`.
+    async fn wait_for_batch_results<'a>(
+        &'a self,
+        params: &'a RetrieveMessageBatchResultsParams,
+        options: PollOptions,
+    ) -> Result<
+        impl Stream<Item = Result<MessageBatchResult, MessageBatchError>> + Send + 'a,
+        MessageBatchError,
+    >
+    where
+        Self: Sized,
+    {
+        self.wait_for_batch(&params.message_batch_id, options)
+            .await?;
+        self.retrieve_message_batch_results(params).await
+    }
+
+    /// Get a [`MessageBatchHandle`] for the batch `batch_id`
+    fn batch_handle(&self, batch_id: impl Into<String>) -> MessageBatchHandle<'_, Self>
+    where
+        Self: Sized,
+    {
+        MessageBatchHandle::new(self, batch_id)
+    }
+}
+
+impl<C: MessageBatchClient + Sync> MessageBatchClientExt for C {}
+
+/// State of a batch being polled
+struct Poller<'a, C> {
+    client: &'a C,
+    params: RetrieveMessageBatchParams,
+    options: PollOptions,
+    interval: Duration,
+    started: Instant,
+    /// Request counts last reported
+    counts: Option<RequestCounts>,
+    /// Failed polls since the last successful one
+    failures: u32,
+    polled: bool,
+    done: bool,
+}
+
+impl<C: MessageBatchClient + Sync> Poller<'_, C> {
+    async fn next(mut self) -> Option<(Result<MessageBatch, MessageBatchError>, Self)> {
+        if self.done {
+            return None;
+        }
+        loop {
+            if self.polled {
+                if let Err(error) = self.sleep().await {
+                    self.done = true;
+                    return Some((Err(error), self));
+                }
+            }
+            self.polled = true;
+
+            let batch = match self.client.retrieve_message_batch(&self.params).await {
+                Ok(batch) => batch,
+                Err(error)
+                    if error.is_retryable()
+                        && self.failures < self.options.max_consecutive_failures =>
+                {
+                    self.failures += 1;
+                    tracing::warn!(
+                        "Failed to poll message batch {} (attempt {}): {}",
+                        self.params.message_batch_id,
+                        self.failures,
+                        error
+                    );
+                    continue;
+                }
+                Err(error) => {
+                    self.done = true;
+                    return Some((Err(error), self));
+                }
+            };
+            self.failures = 0;
+            let ended = batch.is_ended();
+            if ended || self.counts != Some(batch.request_counts) {
+                self.counts = Some(batch.request_counts);
+                self.done = ended;
+                if let Some(on_progress) = &self.options.on_progress {
+                    on_progress(&batch);
+                }
+                return Some((Ok(batch), self));
+            }
+        }
+    }
+
+    /// Wait before the next poll, or fail if the deadline has been reached
+    async fn sleep(&mut self) -> Result<(), MessageBatchError> {
+        let mut wait = self.interval;
+        if let Some(deadline) = self.options.deadline {
+            let elapsed = self.started.elapsed();
+            if elapsed >= deadline {
+                return Err(self.timeout(deadline).await);
+            }
+            wait = wait.min(deadline - elapsed);
+        }
+        tokio::time::sleep(wait).await;
+        self.interval = self.options.next_interval(self.interval);
+        Ok(())
+    }
+
+    async fn timeout(&self, deadline: Duration) -> MessageBatchError {
+        let batch_id = self.params.message_batch_id.clone();
+        if self.options.cancel_on_timeout {
+            let params = CancelMessageBatchParams::new(batch_id.as_str());
+            if let Err(error) = self.client.cancel_message_batch(&params).await {
+                tracing::warn!("Failed to cancel message batch {}: {}", batch_id, error);
+            }
+        }
+        MessageBatchError::Timeout(batch_id, deadline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::test_support::{MockBatches, batch_json};
+    use crate::types::message_batches::ProcessingStatus;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Reports the `(processing, succeeded)` counts of `polls` in order, ending the
+    /// batch on the last one if `ends`, after failing with `errors`
+    struct MockClient {
+        polls: Vec<(u32, u32)>,
+        ends: bool,
+        errors: Mutex<Vec<MessageBatchError>>,
+        retrieved: AtomicUsize,
+        canceled: Mutex<Vec<String>>,
+    }
+
+    impl MockClient {
+        fn new(polls: Vec<(u32, u32)>, ends: bool) -> Self {
+            Self {
+                polls,
+                ends,
+                errors: Mutex::new(Vec::new()),
+                retrieved: AtomicUsize::new(0),
+                canceled: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn failing(self, errors: Vec<MessageBatchError>) -> Self {
+            *self.errors.lock().unwrap() = errors;
+            self
+        }
+    }
+
+    #[async_trait]
+    impl MockBatches for MockClient {
+        async fn retrieve(&self, batch_id: &str) -> Result<MessageBatch, MessageBatchError> {
+            {
+                let mut errors = self.errors.lock().unwrap();
+                if !errors.is_empty() {
+                    return Err(errors.remove(0));
+                }
+            }
+            let poll = self.retrieved.fetch_add(1, Ordering::SeqCst);
+            let last = poll + 1 >= self.polls.len();
+            let (processing, succeeded) = self.polls[poll.min(self.polls.len() - 1)];
+            let status = if last && self.ends {
+                "ended"
+            } else {
+                "in_progress"
+            };
+            let counts = RequestCounts {
+                processing,
+                succeeded,
+                ..Default::default()
+            };
+            Ok(batch_json(batch_id, status, counts))
+        }
+
+        async fn cancel(&self, batch_id: &str) -> Result<MessageBatch, MessageBatchError> {
+            self.canceled.lock().unwrap().push(batch_id.to_string());
+            Err(MessageBatchError::ApiError("not mocked".to_string()))
+        }
+    }
+
+    fn fast() -> PollOptions {
+        PollOptions::new()
+            .with_interval(Duration::from_millis(1))
+            .with_max_interval(Duration::from_millis(2))
+    }
+
+    #[tokio::test]
+    async fn reports_progress_until_the_batch_ends() {
+        let client = MockClient::new(vec![(3, 0), (3, 0), (1, 2), (1, 2), (0, 3)], true);
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let options = fast().on_progress({
+            let reported = reported.clone();
+            move |batch: &MessageBatch| {
+                reported
+                    .lock()
+                    .unwrap()
+                    .push(batch.request_counts.succeeded)
+            }
+        });
+
+        let batch = client.wait_for_batch("msgbatch_1", options).await.unwrap();
+        assert_eq!(batch.processing_status, ProcessingStatus::Ended);
+        assert_eq!(batch.request_counts.succeeded, 3);
+        assert_eq!(*reported.lock().unwrap(), [0, 2, 3]);
+        assert_eq!(client.retrieved.load(Ordering::SeqCst), 5);
+
+        let client = MockClient::new(vec![(1, 0), (0, 1)], true);
+        let updates: Vec<_> = client.watch_batch("msgbatch_1", fast()).collect().await;
+        assert_eq!(updates.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn times_out_and_cancels() {
+        let client = MockClient::new(vec![(1, 0)], false);
+        let options = fast().with_deadline(Duration::from_millis(20));
+        let error = client
+            .wait_for_batch("msgbatch_1", options)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MessageBatchError::Timeout(ref id, _) if id == "msgbatch_1"));
+        assert!(client.canceled.lock().unwrap().is_empty());
+
+        let options = fast()
+            .with_deadline(Duration::from_millis(20))
+            .cancel_on_timeout();
+        let params = RetrieveMessageBatchResultsParams::new("msgbatch_2");
+        let error = client
+            .wait_for_batch_results(&params, options)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(error, MessageBatchError::Timeout(..)));
+        assert_eq!(*client.canceled.lock().unwrap(), ["msgbatch_2"]);
+    }
+
+    fn api_error(kind: &str) -> MessageBatchError {
+        let body = serde_json::json!({
+            "type": "error",
+            "error": {"type": kind, "message": "error"}
+        });
+        MessageBatchError::ApiError(body.to_string())
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures() {
+        let client = MockClient::new(vec![(1, 0), (0, 1)], true).failing(vec![
+            api_error("overloaded_error"),
+            MessageBatchError::RequestFailed("connection reset".to_string()),
+            api_error("rate_limit_error"),
+        ]);
+        let batch = client.wait_for_batch("msgbatch_1", fast()).await.unwrap();
+        assert!(batch.is_ended());
+        assert_eq!(client.retrieved.load(Ordering::SeqCst), 2);
+
+        let client = MockClient::new(vec![(0, 1)], true)
+            .failing(vec![api_error("api_error"), api_error("api_error")]);
+        let options = fast().with_max_consecutive_failures(1);
+        let error = client
+            .wait_for_batch("msgbatch_1", options)
+            .await
+            .unwrap_err();
+        assert!(error.is_retryable());
+        assert_eq!(client.retrieved.load(Ordering::SeqCst), 0);
+
+        let client =
+            MockClient::new(vec![(0, 1)], true).failing(vec![api_error("authentication_error")]);
+        let error = client
+            .wait_for_batch("msgbatch_1", fast())
+            .await
+            .unwrap_err();
+        assert!(!error.is_retryable());
+        assert_eq!(client.retrieved.load(Ordering::SeqCst), 0);
+
+        // Local failures and bodies that are not API errors are not retried
+        let client = MockClient::new(vec![(0, 1)], true)
+            .failing(vec![MessageBatchError::ApiError("Bad Gateway".to_string())]);
+        let error = client
+            .wait_for_batch("msgbatch_1", fast())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MessageBatchError::ApiError(_)));
+        assert!(!MessageBatchError::BudgetExceeded("spent".to_string()).is_retryable());
+        assert!(!MessageBatchError::PollingEnded("msgbatch_1".to_string()).is_retryable());
+    }
+}
@@ -0,0 +1,337 @@
+//! Resubmitting failed batch requests
+//!
+//! When a batch ends, some requests may have expired or failed with errors that could
+//! go away on their own, such as `overloaded_error`. [`ResubmitPolicy`] joins the
+//! results back to the original requests by `custom_id`, submits those requests again
+//! as follow-up batches, and merges every attempt into one [`ResubmitReport`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::batch::resubmit::ResubmitPolicy;
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::types::message_batches::{
+//!     MessageBatchError, MessageBatchResult, MessageRequest,
+//! };
+//!
+//! # async fn example(
+//! #     requests: Vec<MessageRequest>,
+//! #     results: Vec<MessageBatchResult>,
+//! # ) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageBatchError>("your-api-key", "2023-06-01")?;
+//!
+//! let report = ResubmitPolicy::new()
+//!     .with_max_rounds(2)
+//!     .resubmit(&client, &requests, results)
+//!     .await?;
+//! for item in report.failed() {
+//!     println!("{} failed after {} attempts", item.custom_id, item.attempts.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::batch::planner::BatchPlanner;
+use crate::batch::poll::PollOptions;
+use crate::types::message_batches::{
+    BatchRequestResult, MessageBatchClient, MessageBatchError, MessageBatchResult, MessageRequest,
+};
+use futures_util::TryStreamExt;
+use std::collections::HashMap;
+
+/// Which failed requests to resubmit, and how often
+#[derive(Debug, Clone)]
+pub struct ResubmitPolicy {
+    max_rounds: u32,
+    retry_expired: bool,
+    planner: BatchPlanner,
+    poll_options: PollOptions,
+}
+
+impl Default for ResubmitPolicy {
+    fn default() -> Self {
+        Self {
+            max_rounds: 3,
+            retry_expired: true,
+            planner: BatchPlanner::default(),
+            poll_options: PollOptions::default(),
+        }
+    }
+}
+
+impl ResubmitPolicy {
+    /// Resubmit expired requests and requests that failed with a retryable error, up to
+    /// 3 times
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many follow-up rounds may be submitted
+    pub fn with_max_rounds(mut self, max_rounds: u32) -> Self {
+        self.max_rounds = max_rounds;
+        self
+    }
+
+    /// Set whether expired requests are resubmitted
+    pub fn with_retry_expired(mut self, retry_expired: bool) -> Self {
+        self.retry_expired = retry_expired;
+        self
+    }
+
+    /// Set the planner used to split follow-up rounds into batches
+    pub fn with_planner(mut self, planner: BatchPlanner) -> Self {
+        self.planner = planner;
+        self
+    }
+
+    /// Set how follow-up batches are polled until they end
+    pub fn with_poll_options(mut self, poll_options: PollOptions) -> Self {
+        self.poll_options = poll_options;
+        self
+    }
+
+    /// Whether a request with this result should be resubmitted
+    pub fn should_resubmit(&self, result: &BatchRequestResult) -> bool {
+        match result {
+            BatchRequestResult::Expired => self.retry_expired,
+            other => other.is_retryable(),
+        }
+    }
+
+    /// Resubmit the requests whose results should be retried, until they all settle
+    /// or the rounds run out
+    ///
+    /// Each round submits the remaining requests as new batches, waits for them to end
+    /// and reads their results. Results without a matching request in `requests` are
+    /// kept as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client to submit batches with
+    /// * `requests` - The requests of the batch that ended
+    /// * `results` - The results of the batch that ended
+    ///
+    /// # Returns
+    ///
+    /// Returns the attempts of every request, in the order of `results`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of submitting, polling or reading a follow-up batch.
+    pub async fn resubmit<C>(
+        &self,
+        client: &C,
+        requests: &[MessageRequest],
+        results: impl IntoIterator<Item = MessageBatchResult>,
+    ) -> Result<ResubmitReport, MessageBatchError>
+    where
+        C: MessageBatchClient + Sync,
+    {
+        let requests: HashMap<&str, &MessageRequest> = requests
+            .iter()
+            .filter_map(|request| Some((request.custom_id.as_deref()?, request)))
+            .collect();
+        let mut report = ResubmitReport::default();
+        let mut index = HashMap::new();
+        for result in results {
+            index.insert(result.custom_id.clone(), report.items.len());
+            report.items.push(ItemHistory {
+                custom_id: result.custom_id,
+                attempts: vec![result.result],
+            });
+        }
+
+        while report.rounds < self.max_rounds {
+            let retries: Vec<MessageRequest> = report
+                .items
+                .iter()
+                .filter(|item| self.should_resubmit(item.result()))
+                .filter_map(|item| requests.get(item.custom_id.as_str()))
+                .map(|request| (*request).clone())
+                .collect();
+            if retries.is_empty() {
+                break;
+            }
+            tracing::debug!(
+                "Resubmitting {} requests, round {}",
+                retries.len(),
+                report.rounds + 1
+            );
+
+            let group = self.planner.submit(client, retries).await?;
+            report.batch_ids.extend(group.batch_ids().map(String::from));
+            report.rounds += 1;
+            group.wait(client, self.poll_options.clone()).await?;
+
+            let mut results = Box::pin(group.results(client));
+            while let Some(result) = results.try_next().await? {
+                if let Some(&i) = index.get(&result.custom_id) {
+                    report.items[i].attempts.push(result.result);
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Every result a request got, oldest first
+#[derive(Debug, Clone)]
+pub struct ItemHistory {
+    /// Custom identifier of the request
+    pub custom_id: String,
+    /// Result of each attempt; never empty
+    pub attempts: Vec<BatchRequestResult>,
+}
+
+impl ItemHistory {
+    /// Result of the last attempt
+    pub fn result(&self) -> &BatchRequestResult {
+        self.attempts
+            .last()
+            .expect("every item has at least one attempt")
+    }
+
+    /// Whether the request was resubmitted
+    pub fn was_resubmitted(&self) -> bool {
+        self.attempts.len() > 1
+    }
+}
+
+/// Merged results of a batch and its follow-up rounds
+#[derive(Debug, Clone, Default)]
+pub struct ResubmitReport {
+    /// History of every request
+    pub items: Vec<ItemHistory>,
+    /// Follow-up batches that were created, in order
+    pub batch_ids: Vec<String>,
+    /// Number of follow-up rounds submitted
+    pub rounds: u32,
+}
+
+impl ResubmitReport {
+    /// Final result of every request
+    pub fn results(&self) -> impl Iterator<Item = MessageBatchResult> + '_ {
+        self.items.iter().map(|item| MessageBatchResult {
+            custom_id: item.custom_id.clone(),
+            result: item.result().clone(),
+        })
+    }
+
+    /// Requests that did not succeed in the end
+    pub fn failed(&self) -> impl Iterator<Item = &ItemHistory> {
+        self.items
+            .iter()
+            .filter(|item| !item.result().is_succeeded())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::test_support::{MockBatches, batch_json, request};
+    use crate::types::error::{ApiErrorDetail, ApiErrorKind, ApiErrorResponse};
+    use crate::types::message_batches::{CreateMessageBatchParams, MessageBatch, RequestCounts};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Ends batches right away; `req-2` always expires and every other request is
+    /// canceled
+    #[derive(Default)]
+    struct MockClient {
+        batches: Mutex<HashMap<String, Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl MockBatches for MockClient {
+        async fn create(
+            &self,
+            params: &CreateMessageBatchParams,
+        ) -> Result<MessageBatch, MessageBatchError> {
+            let custom_ids = params
+                .requests
+                .iter()
+                .filter_map(|request| request.custom_id.clone())
+                .collect();
+            let id = {
+                let mut batches = self.batches.lock().unwrap();
+                let id = format!("msgbatch_{}", batches.len() + 1);
+                batches.insert(id.clone(), custom_ids);
+                id
+            };
+            self.retrieve(&id).await
+        }
+
+        async fn retrieve(&self, batch_id: &str) -> Result<MessageBatch, MessageBatchError> {
+            Ok(batch_json(batch_id, "ended", RequestCounts::default()))
+        }
+
+        async fn results(
+            &self,
+            batch_id: &str,
+        ) -> Result<Vec<MessageBatchResult>, MessageBatchError> {
+            let custom_ids = self.batches.lock().unwrap()[batch_id].clone();
+            let results = custom_ids.into_iter().map(|custom_id| {
+                let result = if custom_id == "req-2" {
+                    BatchRequestResult::Expired
+                } else {
+                    BatchRequestResult::Canceled
+                };
+                MessageBatchResult { custom_id, result }
+            });
+            Ok(results.collect())
+        }
+    }
+
+    fn errored(custom_id: &str, kind: ApiErrorKind) -> MessageBatchResult {
+        MessageBatchResult {
+            custom_id: custom_id.to_string(),
+            result: BatchRequestResult::Errored {
+                error: ApiErrorResponse {
+                    type_: "error".to_string(),
+                    error: ApiErrorDetail {
+                        kind,
+                        message: "error".to_string(),
+                    },
+                },
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn resubmits_retryable_results_and_keeps_history() {
+        let requests = [request("req-1"), request("req-2"), request("req-3")];
+        let results = vec![
+            errored("req-1", ApiErrorKind::Overloaded),
+            MessageBatchResult {
+                custom_id: "req-2".to_string(),
+                result: BatchRequestResult::Expired,
+            },
+            errored("req-3", ApiErrorKind::InvalidRequest),
+        ];
+
+        let client = MockClient::default();
+        let report = ResubmitPolicy::new()
+            .with_max_rounds(2)
+            .resubmit(&client, &requests, results)
+            .await
+            .unwrap();
+        assert_eq!(report.rounds, 2);
+        assert_eq!(report.batch_ids, ["msgbatch_1", "msgbatch_2"]);
+
+        let attempts: Vec<usize> = report.items.iter().map(|i| i.attempts.len()).collect();
+        assert_eq!(attempts, [2, 3, 1]);
+        assert!(matches!(
+            report.items[0].result(),
+            BatchRequestResult::Canceled
+        ));
+        assert!(!report.items[2].was_resubmitted());
+        assert_eq!(report.failed().count(), 3);
+
+        let report = ResubmitPolicy::new()
+            .with_retry_expired(false)
+            .resubmit(&client, &requests, report.results())
+            .await
+            .unwrap();
+        assert_eq!(report.rounds, 0);
+    }
+}
@@ -0,0 +1,725 @@
+//! Durable storage of batch jobs
+//!
+//! A batch job is a named set of requests submitted as one or more message batches.
+//! A [`BatchJobStore`] records the requests, the batches they were submitted as, and
+//! the downloaded results, so that a
+//! [`BatchJobManager`](crate::batch::manager::BatchJobManager) can pick a job up again
+//! after the process restarts.
+//!
+//! [`FileJobStore`] keeps every job in its own directory. With the `sqlite` feature,
+//! [`SqliteJobStore`] keeps every job in one SQLite database.
+
+use crate::types::message_batches::{
+    MessageBatch, MessageBatchError, MessageBatchResult, MessageRequest, ProcessingStatus,
+    RequestCounts, is_valid_id,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Last known state of a batch of a job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchRecord {
+    /// Id of the batch
+    pub batch_id: String,
+    /// Processing status when the batch was last retrieved
+    pub processing_status: ProcessingStatus,
+    /// Request counts when the batch was last retrieved, adding up to the number of
+    /// requests in the batch
+    pub request_counts: RequestCounts,
+    /// Whether the results of the batch have been stored
+    #[serde(skip)]
+    pub downloaded: bool,
+}
+
+impl BatchRecord {
+    /// Record of a batch whose request counts are not known yet
+    pub fn new(batch_id: impl Into<String>) -> Self {
+        Self {
+            batch_id: batch_id.into(),
+            processing_status: ProcessingStatus::InProgress,
+            request_counts: RequestCounts::default(),
+            downloaded: false,
+        }
+    }
+
+    /// Whether the batch had ended when it was last retrieved
+    pub fn is_ended(&self) -> bool {
+        self.processing_status == ProcessingStatus::Ended
+    }
+}
+
+impl From<&MessageBatch> for BatchRecord {
+    fn from(batch: &MessageBatch) -> Self {
+        Self {
+            batch_id: batch.id.clone(),
+            processing_status: batch.processing_status,
+            request_counts: batch.request_counts,
+            downloaded: false,
+        }
+    }
+}
+
+/// Storage of batch jobs
+///
+/// Job ids, like `custom_id`s, are 1 to 64 letters, digits, hyphens and underscores.
+pub trait BatchJobStore: Send + Sync {
+    /// Record the requests of a new job
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if the job id is invalid or already used, or a
+    /// request is invalid (see [`MessageRequest::validate`]).
+    fn create_job(
+        &self,
+        job_id: &str,
+        requests: &[MessageRequest],
+    ) -> Result<(), MessageBatchError>;
+
+    /// Ids of every stored job
+    fn jobs(&self) -> Result<Vec<String>, MessageBatchError>;
+
+    /// Requests of a job, in the order they were recorded
+    fn requests(&self, job_id: &str) -> Result<Vec<MessageRequest>, MessageBatchError>;
+
+    /// Add a batch to a job, or update its record
+    ///
+    /// The `downloaded` flag of `batch` is ignored; it is set by
+    /// [`save_results`](Self::save_results).
+    fn save_batch(&self, job_id: &str, batch: &BatchRecord) -> Result<(), MessageBatchError>;
+
+    /// Batches of a job, in the order they were added
+    fn batches(&self, job_id: &str) -> Result<Vec<BatchRecord>, MessageBatchError>;
+
+    /// Store the results of a batch and mark it as downloaded, atomically
+    fn save_results(
+        &self,
+        job_id: &str,
+        batch_id: &str,
+        results: &[MessageBatchResult],
+    ) -> Result<(), MessageBatchError>;
+
+    /// Stored result of the request with `custom_id`
+    fn result(
+        &self,
+        job_id: &str,
+        custom_id: &str,
+    ) -> Result<Option<MessageBatchResult>, MessageBatchError>;
+}
+
+fn store_error(error: impl Display) -> MessageBatchError {
+    MessageBatchError::Store(error.to_string())
+}
+
+fn check_id(kind: &str, id: &str) -> Result<(), MessageBatchError> {
+    if is_valid_id(id) {
+        Ok(())
+    } else {
+        Err(MessageBatchError::Store(format!(
+            "invalid {} {:?}",
+            kind, id
+        )))
+    }
+}
+
+/// Store keeping every job in a directory
+///
+/// A job `job-1` is stored as:
+///
+/// ```text
+/// job-1/requests.jsonl
+/// job-1/batches.jsonl
+/// job-1/results/<batch id>.jsonl
+/// ```
+///
+/// Every file is written to a temporary file first and then renamed, so a crash never
+/// leaves a partially written file behind.
+///
+/// The first [`result`](BatchJobStore::result) lookup of a job reads all of its results
+/// once to index them by `custom_id`; later lookups read a single line.
+#[derive(Debug)]
+pub struct FileJobStore {
+    dir: PathBuf,
+    lock: Mutex<()>,
+    /// Location of every stored result, by job and `custom_id`
+    index: Mutex<HashMap<String, ResultIndex>>,
+}
+
+/// File and byte offset of the stored results of a job, by `custom_id`
+type ResultIndex = HashMap<String, (PathBuf, u64)>;
+
+impl FileJobStore {
+    /// Open a store in `dir`, creating the directory if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if the directory cannot be created.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, MessageBatchError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(store_error)?;
+        Ok(Self {
+            dir,
+            lock: Mutex::new(()),
+            index: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Directory of the store
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn job_dir(&self, job_id: &str) -> Result<PathBuf, MessageBatchError> {
+        check_id("job id", job_id)?;
+        let dir = self.dir.join(job_id);
+        if !dir.is_dir() {
+            return Err(MessageBatchError::Store(format!(
+                "job {} does not exist",
+                job_id
+            )));
+        }
+        Ok(dir)
+    }
+
+    fn results_dir(&self, job_id: &str) -> Result<PathBuf, MessageBatchError> {
+        Ok(self.job_dir(job_id)?.join("results"))
+    }
+
+    /// Index every results file of a job
+    fn index_results(&self, job_id: &str) -> Result<ResultIndex, MessageBatchError> {
+        let mut index = ResultIndex::new();
+        for entry in fs::read_dir(self.results_dir(job_id)?).map_err(store_error)? {
+            let path = entry.map_err(store_error)?.path();
+            if path.extension().is_none_or(|ext| ext != "jsonl") {
+                continue;
+            }
+            let mut reader = BufReader::new(fs::File::open(&path).map_err(store_error)?);
+            let mut line = String::new();
+            let mut offset = 0;
+            loop {
+                line.clear();
+                let read = reader.read_line(&mut line).map_err(store_error)?;
+                if read == 0 {
+                    break;
+                }
+                if !line.trim().is_empty() {
+                    let key: ResultKey = serde_json::from_str(&line).map_err(|e| {
+                        MessageBatchError::Store(format!("{}: {}", path.display(), e))
+                    })?;
+                    index.insert(key.custom_id, (path.clone(), offset));
+                }
+                offset += read as u64;
+            }
+        }
+        Ok(index)
+    }
+}
+
+/// The `custom_id` of a stored result, read without parsing the rest of it
+#[derive(Deserialize)]
+struct ResultKey {
+    custom_id: String,
+}
+
+/// Write `lines` to `path` as JSON Lines, through a temporary file
+fn write_jsonl<T: Serialize>(path: &Path, lines: &[T]) -> Result<(), MessageBatchError> {
+    write_atomic(path, |writer| {
+        for line in lines {
+            serde_json::to_writer(&mut *writer, line).map_err(store_error)?;
+            writer.write_all(b"\n").map_err(store_error)?;
+        }
+        Ok(())
+    })
+}
+
+fn write_atomic(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<fs::File>) -> Result<(), MessageBatchError>,
+) -> Result<(), MessageBatchError> {
+    let tmp = path.with_extension("tmp");
+    let mut writer = BufWriter::new(fs::File::create(&tmp).map_err(store_error)?);
+    write(&mut writer)?;
+    let file = writer.into_inner().map_err(store_error)?;
+    file.sync_all().map_err(store_error)?;
+    fs::rename(&tmp, path).map_err(store_error)
+}
+
+/// Read the JSON Lines file at `path`, stopping early when `visit` returns `true`
+fn read_jsonl<T: for<'de> Deserialize<'de>>(
+    path: &Path,
+    mut visit: impl FnMut(T) -> bool,
+) -> Result<(), MessageBatchError> {
+    let reader = BufReader::new(fs::File::open(path).map_err(store_error)?);
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(store_error)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value = serde_json::from_str(&line).map_err(|e| {
+            MessageBatchError::Store(format!("{}:{}: {}", path.display(), index + 1, e))
+        })?;
+        if visit(value) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+impl BatchJobStore for FileJobStore {
+    fn create_job(
+        &self,
+        job_id: &str,
+        requests: &[MessageRequest],
+    ) -> Result<(), MessageBatchError> {
+        check_id("job id", job_id)?;
+        for request in requests {
+            request.validate()?;
+        }
+        let _guard = self.lock.lock().unwrap();
+        let dir = self.dir.join(job_id);
+        if dir.exists() {
+            return Err(MessageBatchError::Store(format!(
+                "job {} already exists",
+                job_id
+            )));
+        }
+        // Build the job next to the store and move it in place once complete
+        let tmp = self.dir.join(format!(".{}.tmp", job_id));
+        if tmp.exists() {
+            fs::remove_dir_all(&tmp).map_err(store_error)?;
+        }
+        fs::create_dir_all(tmp.join("results")).map_err(store_error)?;
+        write_jsonl(&tmp.join("requests.jsonl"), requests)?;
+        write_jsonl::<BatchRecord>(&tmp.join("batches.jsonl"), &[])?;
+        fs::rename(&tmp, &dir).map_err(store_error)
+    }
+
+    fn jobs(&self) -> Result<Vec<String>, MessageBatchError> {
+        let mut jobs = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(store_error)? {
+            let entry = entry.map_err(store_error)?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.path().is_dir() && is_valid_id(&name) {
+                jobs.push(name);
+            }
+        }
+        jobs.sort();
+        Ok(jobs)
+    }
+
+    fn requests(&self, job_id: &str) -> Result<Vec<MessageRequest>, MessageBatchError> {
+        let path = self.job_dir(job_id)?.join("requests.jsonl");
+        let mut requests = Vec::new();
+        read_jsonl(&path, |request| {
+            requests.push(request);
+            false
+        })?;
+        Ok(requests)
+    }
+
+    fn save_batch(&self, job_id: &str, batch: &BatchRecord) -> Result<(), MessageBatchError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut batches = self.batches(job_id)?;
+        match batches.iter_mut().find(|b| b.batch_id == batch.batch_id) {
+            Some(record) => *record = batch.clone(),
+            None => batches.push(batch.clone()),
+        }
+        write_jsonl(&self.job_dir(job_id)?.join("batches.jsonl"), &batches)
+    }
+
+    fn batches(&self, job_id: &str) -> Result<Vec<BatchRecord>, MessageBatchError> {
+        let path = self.job_dir(job_id)?.join("batches.jsonl");
+        let results = self.results_dir(job_id)?;
+        let mut batches = Vec::new();
+        read_jsonl(&path, |mut batch: BatchRecord| {
+            batch.downloaded = results.join(format!("{}.jsonl", batch.batch_id)).is_file();
+            batches.push(batch);
+            false
+        })?;
+        Ok(batches)
+    }
+
+    fn save_results(
+        &self,
+        job_id: &str,
+        batch_id: &str,
+        results: &[MessageBatchResult],
+    ) -> Result<(), MessageBatchError> {
+        check_id("batch id", batch_id)?;
+        let path = self
+            .results_dir(job_id)?
+            .join(format!("{}.jsonl", batch_id));
+        write_jsonl(&path, results)?;
+        // Offsets of a rewritten file are stale, so the job is indexed again when needed
+        self.index.lock().unwrap().remove(job_id);
+        Ok(())
+    }
+
+    fn result(
+        &self,
+        job_id: &str,
+        custom_id: &str,
+    ) -> Result<Option<MessageBatchResult>, MessageBatchError> {
+        let location = {
+            let mut indexes = self.index.lock().unwrap();
+            let index = match indexes.get(job_id) {
+                Some(index) => index,
+                None => {
+                    let index = self.index_results(job_id)?;
+                    indexes.entry(job_id.to_string()).or_insert(index)
+                }
+            };
+            index.get(custom_id).cloned()
+        };
+        let Some((path, offset)) = location else {
+            return Ok(None);
+        };
+
+        let mut file = fs::File::open(&path).map_err(store_error)?;
+        file.seek(SeekFrom::Start(offset)).map_err(store_error)?;
+        let mut line = String::new();
+        BufReader::new(file)
+            .read_line(&mut line)
+            .map_err(store_error)?;
+        let result = serde_json::from_str(&line)
+            .map_err(|e| MessageBatchError::Store(format!("{}: {}", path.display(), e)))?;
+        Ok(Some(result))
+    }
+}
+
+/// Store keeping every job in a SQLite database
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteJobStore {
+    connection: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteJobStore {
+    const SCHEMA: &'static str = "
+        CREATE TABLE IF NOT EXISTS batch_jobs (
+            job_id TEXT PRIMARY KEY
+        );
+        CREATE TABLE IF NOT EXISTS batch_job_requests (
+            job_id TEXT NOT NULL,
+            custom_id TEXT NOT NULL,
+            request TEXT NOT NULL,
+            PRIMARY KEY (job_id, custom_id)
+        );
+        CREATE TABLE IF NOT EXISTS batch_job_batches (
+            job_id TEXT NOT NULL,
+            batch_id TEXT NOT NULL,
+            record TEXT NOT NULL,
+            downloaded INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (job_id, batch_id)
+        );
+        CREATE TABLE IF NOT EXISTS batch_job_results (
+            job_id TEXT NOT NULL,
+            custom_id TEXT NOT NULL,
+            result TEXT NOT NULL,
+            PRIMARY KEY (job_id, custom_id)
+        );
+    ";
+
+    /// Open the database at `path`, creating it if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if the database cannot be opened.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, MessageBatchError> {
+        Self::with_connection(rusqlite::Connection::open(path).map_err(store_error)?)
+    }
+
+    /// Open a database that lives in memory, for tests
+    pub fn in_memory() -> Result<Self, MessageBatchError> {
+        Self::with_connection(rusqlite::Connection::open_in_memory().map_err(store_error)?)
+    }
+
+    /// Use an open connection, creating the tables if needed
+    pub fn with_connection(connection: rusqlite::Connection) -> Result<Self, MessageBatchError> {
+        connection
+            .execute_batch(Self::SCHEMA)
+            .map_err(store_error)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn check_job(connection: &rusqlite::Connection, job_id: &str) -> Result<(), MessageBatchError> {
+        use rusqlite::OptionalExtension;
+
+        connection
+            .query_row(
+                "SELECT 1 FROM batch_jobs WHERE job_id = ?1",
+                [job_id],
+                |_| Ok(()),
+            )
+            .optional()
+            .map_err(store_error)?
+            .ok_or_else(|| MessageBatchError::Store(format!("job {} does not exist", job_id)))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn to_json<T: Serialize>(value: &T) -> Result<String, MessageBatchError> {
+    serde_json::to_string(value).map_err(store_error)
+}
+
+#[cfg(feature = "sqlite")]
+fn from_json<T: for<'de> Deserialize<'de>>(json: &str) -> Result<T, MessageBatchError> {
+    serde_json::from_str(json).map_err(store_error)
+}
+
+#[cfg(feature = "sqlite")]
+impl BatchJobStore for SqliteJobStore {
+    fn create_job(
+        &self,
+        job_id: &str,
+        requests: &[MessageRequest],
+    ) -> Result<(), MessageBatchError> {
+        check_id("job id", job_id)?;
+        let custom_ids = requests
+            .iter()
+            .map(MessageRequest::validate)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut connection = self.connection.lock().unwrap();
+        let tx = connection.transaction().map_err(store_error)?;
+        let created = tx
+            .execute(
+                "INSERT OR IGNORE INTO batch_jobs (job_id) VALUES (?1)",
+                [job_id],
+            )
+            .map_err(store_error)?;
+        if created == 0 {
+            return Err(MessageBatchError::Store(format!(
+                "job {} already exists",
+                job_id
+            )));
+        }
+        {
+            let mut insert = tx
+                .prepare(
+                    "INSERT INTO batch_job_requests (job_id, custom_id, request)
+                     VALUES (?1, ?2, ?3)",
+                )
+                .map_err(store_error)?;
+            for (request, custom_id) in requests.iter().zip(custom_ids) {
+                insert
+                    .execute((job_id, custom_id, to_json(request)?))
+                    .map_err(store_error)?;
+            }
+        }
+        tx.commit().map_err(store_error)
+    }
+
+    fn jobs(&self) -> Result<Vec<String>, MessageBatchError> {
+        let connection = self.connection.lock().unwrap();
+        let mut query = connection
+            .prepare("SELECT job_id FROM batch_jobs ORDER BY job_id")
+            .map_err(store_error)?;
+        let jobs = query
+            .query_map([], |row| row.get(0))
+            .map_err(store_error)?
+            .collect::<Result<_, _>>()
+            .map_err(store_error)?;
+        Ok(jobs)
+    }
+
+    fn requests(&self, job_id: &str) -> Result<Vec<MessageRequest>, MessageBatchError> {
+        let connection = self.connection.lock().unwrap();
+        Self::check_job(&connection, job_id)?;
+        let mut query = connection
+            .prepare("SELECT request FROM batch_job_requests WHERE job_id = ?1 ORDER BY rowid")
+            .map_err(store_error)?;
+        let rows = query
+            .query_map([job_id], |row| row.get::<_, String>(0))
+            .map_err(store_error)?;
+        rows.map(|json| from_json(&json.map_err(store_error)?))
+            .collect()
+    }
+
+    fn save_batch(&self, job_id: &str, batch: &BatchRecord) -> Result<(), MessageBatchError> {
+        let connection = self.connection.lock().unwrap();
+        Self::check_job(&connection, job_id)?;
+        connection
+            .execute(
+                "INSERT INTO batch_job_batches (job_id, batch_id, record) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (job_id, batch_id) DO UPDATE SET record = excluded.record",
+                (job_id, &batch.batch_id, to_json(batch)?),
+            )
+            .map_err(store_error)?;
+        Ok(())
+    }
+
+    fn batches(&self, job_id: &str) -> Result<Vec<BatchRecord>, MessageBatchError> {
+        let connection = self.connection.lock().unwrap();
+        Self::check_job(&connection, job_id)?;
+        let mut query = connection
+            .prepare(
+                "SELECT record, downloaded FROM batch_job_batches
+                 WHERE job_id = ?1 ORDER BY rowid",
+            )
+            .map_err(store_error)?;
+        let rows = query
+            .query_map([job_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))
+            })
+            .map_err(store_error)?;
+        rows.map(|row| {
+            let (json, downloaded) = row.map_err(store_error)?;
+            let mut record: BatchRecord = from_json(&json)?;
+            record.downloaded = downloaded;
+            Ok(record)
+        })
+        .collect()
+    }
+
+    fn save_results(
+        &self,
+        job_id: &str,
+        batch_id: &str,
+        results: &[MessageBatchResult],
+    ) -> Result<(), MessageBatchError> {
+        let mut connection = self.connection.lock().unwrap();
+        Self::check_job(&connection, job_id)?;
+        let tx = connection.transaction().map_err(store_error)?;
+        {
+            let mut insert = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO batch_job_results (job_id, custom_id, result)
+                     VALUES (?1, ?2, ?3)",
+                )
+                .map_err(store_error)?;
+            for result in results {
+                insert
+                    .execute((job_id, &result.custom_id, to_json(result)?))
+                    .map_err(store_error)?;
+            }
+        }
+        tx.execute(
+            "UPDATE batch_job_batches SET downloaded = 1 WHERE job_id = ?1 AND batch_id = ?2",
+            (job_id, batch_id),
+        )
+        .map_err(store_error)?;
+        tx.commit().map_err(store_error)
+    }
+
+    fn result(
+        &self,
+        job_id: &str,
+        custom_id: &str,
+    ) -> Result<Option<MessageBatchResult>, MessageBatchError> {
+        use rusqlite::OptionalExtension;
+
+        let connection = self.connection.lock().unwrap();
+        Self::check_job(&connection, job_id)?;
+        let json: Option<String> = connection
+            .query_row(
+                "SELECT result FROM batch_job_results WHERE job_id = ?1 AND custom_id = ?2",
+                (job_id, custom_id),
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(store_error)?;
+        json.map(|json| from_json(&json)).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::message::{CreateMessageParams, Message, RequiredMessageParams, Role};
+    use crate::types::message_batches::BatchRequestResult;
+
+    fn requests() -> Vec<MessageRequest> {
+        ["req-1", "req-2"]
+            .into_iter()
+            .map(|custom_id| {
+                let params = CreateMessageParams::new(RequiredMessageParams {
+                    model: "claude-3-5-haiku-latest".to_string(),
+                    messages: vec![Message::new_text(Role::User, "Hello!")],
+                    max_tokens: 100,
+                });
+                MessageRequest::new(params).with_custom_id(custom_id)
+            })
+            .collect()
+    }
+
+    fn exercise(store: &dyn BatchJobStore) {
+        store.create_job("job-1", &requests()).unwrap();
+        assert!(store.create_job("job-1", &requests()).is_err());
+        assert!(store.create_job("../escape", &requests()).is_err());
+        let mut unnamed = requests();
+        unnamed[1].custom_id = None;
+        assert!(matches!(
+            store.create_job("job-2", &unnamed),
+            Err(MessageBatchError::InvalidRequest(_))
+        ));
+        assert_eq!(store.jobs().unwrap(), ["job-1"]);
+        assert_eq!(store.requests("job-1").unwrap().len(), 2);
+        assert!(store.requests("job-2").is_err());
+
+        store
+            .save_batch("job-1", &BatchRecord::new("msgbatch_1"))
+            .unwrap();
+        store
+            .save_batch("job-1", &BatchRecord::new("msgbatch_2"))
+            .unwrap();
+        let mut ended = BatchRecord::new("msgbatch_1");
+        ended.processing_status = ProcessingStatus::Ended;
+        store.save_batch("job-1", &ended).unwrap();
+
+        let batches = store.batches("job-1").unwrap();
+        assert_eq!(batches.len(), 2);
+        assert!(batches[0].is_ended() && !batches[0].downloaded);
+        assert!(!batches[1].is_ended());
+
+        let result = MessageBatchResult {
+            custom_id: "req-1".to_string(),
+            result: BatchRequestResult::Expired,
+        };
+        store
+            .save_results("job-1", "msgbatch_1", &[result])
+            .unwrap();
+        assert!(store.batches("job-1").unwrap()[0].downloaded);
+        let stored = store.result("job-1", "req-1").unwrap().unwrap();
+        assert!(matches!(stored.result, BatchRequestResult::Expired));
+        assert!(store.result("job-1", "req-2").unwrap().is_none());
+
+        // Results saved after a lookup are found too
+        let result = MessageBatchResult {
+            custom_id: "req-2".to_string(),
+            result: BatchRequestResult::Canceled,
+        };
+        store
+            .save_results("job-1", "msgbatch_2", &[result])
+            .unwrap();
+        let stored = store.result("job-1", "req-2").unwrap().unwrap();
+        assert!(matches!(stored.result, BatchRequestResult::Canceled));
+        assert!(matches!(
+            store.result("job-1", "req-1").unwrap().unwrap().result,
+            BatchRequestResult::Expired
+        ));
+    }
+
+    #[test]
+    fn file_store_round_trips_jobs() {
+        let dir = std::env::temp_dir().join(format!("batch-job-store-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        exercise(&FileJobStore::open(&dir).unwrap());
+        // A reopened store sees the same jobs
+        let store = FileJobStore::open(&dir).unwrap();
+        assert!(store.batches("job-1").unwrap()[0].downloaded);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_store_round_trips_jobs() {
+        exercise(&SqliteJobStore::in_memory().unwrap());
+    }
+}
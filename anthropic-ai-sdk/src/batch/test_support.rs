@@ -0,0 +1,198 @@
+//! Helpers shared by the tests of the batch modules and the clients they wrap
+
+use crate::types::message::{
+    ContentBlock, CountMessageTokensParams, CountMessageTokensResponse, CreateMessageParams,
+    CreateMessageResponse, Message, MessageClient, MessageError, RequiredMessageParams, Role,
+    StopReason, StreamEvent, Usage,
+};
+use crate::types::message_batches::{
+    CancelMessageBatchParams, CreateMessageBatchParams, DeleteMessageBatchParams, DeleteResponse,
+    ListMessageBatchesParams, ListMessageBatchesResponse, MessageBatch, MessageBatchClient,
+    MessageBatchError, MessageBatchResult, MessageRequest, RequestCounts,
+    RetrieveMessageBatchParams, RetrieveMessageBatchResponse, RetrieveMessageBatchResultsParams,
+};
+use async_trait::async_trait;
+use futures_util::Stream;
+
+/// Batch `id` with the given processing status and request counts
+pub(crate) fn batch_json(id: &str, status: &str, counts: RequestCounts) -> MessageBatch {
+    let batch = serde_json::json!({
+        "id": id,
+        "type": "message_batch",
+        "created_at": "2024-09-24T18:37:24.100435Z",
+        "expires_at": "2024-09-25T18:37:24.100435Z",
+        "archived_at": null,
+        "cancel_initiated_at": null,
+        "ended_at": null,
+        "processing_status": status,
+        "request_counts": counts,
+        "results_url": null
+    });
+    serde_json::from_value(batch).unwrap()
+}
+
+/// A small request with `custom_id`
+pub(crate) fn request(custom_id: &str) -> MessageRequest {
+    let params = CreateMessageParams::new(RequiredMessageParams {
+        model: "claude-3-5-haiku-latest".to_string(),
+        messages: vec![Message::new_text(Role::User, "Hello!")],
+        max_tokens: 100,
+    });
+    MessageRequest::new(params).with_custom_id(custom_id)
+}
+
+/// Assert that two amounts are equal up to rounding
+pub(crate) fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "{} != {}",
+        actual,
+        expected
+    );
+}
+
+/// A response from `model` with the text "Hi"
+pub(crate) fn response(model: &str) -> CreateMessageResponse {
+    CreateMessageResponse {
+        content: vec![ContentBlock::text("Hi")],
+        id: "msg_1".to_string(),
+        model: model.to_string(),
+        role: Role::Assistant,
+        stop_reason: Some(StopReason::EndTurn),
+        stop_sequence: None,
+        type_: "message".to_string(),
+        usage: Usage::default(),
+    }
+}
+
+/// The calls of [`MessageClient`] a test mock answers
+///
+/// Every type implementing it is a [`MessageClient`]. By default messages are answered
+/// with [`response`], streams are empty and token counts panic.
+#[async_trait]
+pub(crate) trait MockMessages: Sync {
+    async fn create(
+        &self,
+        params: &CreateMessageParams,
+    ) -> Result<CreateMessageResponse, MessageError> {
+        Ok(response(&params.model))
+    }
+
+    async fn count(
+        &self,
+        _params: &CountMessageTokensParams,
+    ) -> Result<CountMessageTokensResponse, MessageError> {
+        unimplemented!()
+    }
+
+    /// Events streamed in answer to `body`
+    fn stream(&self, _body: &CreateMessageParams) -> Vec<StreamEvent> {
+        Vec::new()
+    }
+}
+
+/// A client answering with the defaults of [`MockMessages`]
+pub(crate) struct MockClient;
+
+impl MockMessages for MockClient {}
+
+#[async_trait]
+impl<T: MockMessages> MessageClient for T {
+    async fn create_message<'a>(
+        &'a self,
+        params: Option<&'a CreateMessageParams>,
+    ) -> Result<CreateMessageResponse, MessageError> {
+        self.create(params.unwrap()).await
+    }
+
+    async fn count_tokens<'a>(
+        &'a self,
+        params: Option<&'a CountMessageTokensParams>,
+    ) -> Result<CountMessageTokensResponse, MessageError> {
+        self.count(params.unwrap()).await
+    }
+
+    async fn create_message_streaming<'a>(
+        &'a self,
+        body: &'a CreateMessageParams,
+    ) -> Result<impl Stream<Item = Result<StreamEvent, MessageError>> + 'a, MessageError> {
+        Ok(futures_util::stream::iter(
+            self.stream(body).into_iter().map(Ok),
+        ))
+    }
+}
+
+/// The calls of [`MessageBatchClient`] a test mock answers
+///
+/// Every type implementing it is a [`MessageBatchClient`]. Calls the mock does not
+/// override panic.
+#[async_trait]
+pub(crate) trait MockBatches: Sync {
+    async fn create(
+        &self,
+        _params: &CreateMessageBatchParams,
+    ) -> Result<MessageBatch, MessageBatchError> {
+        unimplemented!()
+    }
+
+    async fn retrieve(&self, _batch_id: &str) -> Result<MessageBatch, MessageBatchError> {
+        unimplemented!()
+    }
+
+    async fn results(&self, _batch_id: &str) -> Result<Vec<MessageBatchResult>, MessageBatchError> {
+        unimplemented!()
+    }
+
+    async fn cancel(&self, _batch_id: &str) -> Result<MessageBatch, MessageBatchError> {
+        unimplemented!()
+    }
+}
+
+#[async_trait]
+impl<T: MockBatches> MessageBatchClient for T {
+    async fn create_message_batch<'a>(
+        &'a self,
+        params: &'a CreateMessageBatchParams,
+    ) -> Result<MessageBatch, MessageBatchError> {
+        self.create(params).await
+    }
+
+    async fn list_message_batches<'a>(
+        &'a self,
+        _params: Option<&'a ListMessageBatchesParams>,
+    ) -> Result<ListMessageBatchesResponse, MessageBatchError> {
+        unimplemented!()
+    }
+
+    async fn retrieve_message_batch<'a>(
+        &'a self,
+        params: &'a RetrieveMessageBatchParams,
+    ) -> Result<RetrieveMessageBatchResponse, MessageBatchError> {
+        self.retrieve(&params.message_batch_id).await
+    }
+
+    async fn retrieve_message_batch_results<'a>(
+        &'a self,
+        params: &'a RetrieveMessageBatchResultsParams,
+    ) -> Result<
+        impl Stream<Item = Result<MessageBatchResult, MessageBatchError>> + Send + 'a,
+        MessageBatchError,
+    > {
+        let results = self.results(&params.message_batch_id).await?;
+        Ok(futures_util::stream::iter(results.into_iter().map(Ok)))
+    }
+
+    async fn cancel_message_batch<'a>(
+        &'a self,
+        params: &'a CancelMessageBatchParams,
+    ) -> Result<MessageBatch, MessageBatchError> {
+        self.cancel(&params.message_batch_id).await
+    }
+
+    async fn delete_message_batch<'a>(
+        &'a self,
+        _params: &'a DeleteMessageBatchParams,
+    ) -> Result<DeleteResponse, MessageBatchError> {
+        unimplemented!()
+    }
+}
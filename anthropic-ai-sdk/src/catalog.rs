@@ -0,0 +1,490 @@
+//! Model catalog
+//!
+//! The Models API only reports a model's id, display name and creation date. This
+//! module provides [`ModelCatalog`], a built-in and versioned table of context windows,
+//! output limits, capabilities and prices, keyed by model id and alias. Validation,
+//! cost estimation and context trimming all read from the same catalog.
+//!
+//! The built-in data can be overridden from a JSON (or, with the `toml` feature, TOML)
+//! file, and merged with the results of `list_models` so that models released after
+//! this SDK still show up.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::catalog::ModelCatalog;
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::types::model::ModelError;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<ModelError>("your-api-key", "2023-06-01")?;
+//!
+//! let mut catalog = ModelCatalog::builtin().with_overrides_from_file("models.json")?;
+//! catalog.sync(&client).await?;
+//!
+//! if let Some(model) = catalog.get("claude-sonnet-4-5") {
+//!     println!("{}: {:?} token context window", model.display_name, model.context_window);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::types::message::{ContentBlock, CreateMessageParams, MessageContent};
+use crate::types::model::{ListModelsParams, Model, ModelClient, ModelError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use thiserror::Error;
+
+const BUILTIN_MODELS: &str = include_str!("../data/models.json");
+
+/// Error types for the model catalog
+#[derive(Debug, Error)]
+pub enum CatalogError {
+    #[error("Failed to read catalog file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse catalog: {0}")]
+    Parse(String),
+    #[error("Unsupported catalog file format: {0}")]
+    UnsupportedFormat(String),
+    #[error("Unknown model: {0}")]
+    UnknownModel(String),
+    #[error("Invalid request for {model}: {reason}")]
+    Invalid { model: String, reason: String },
+}
+
+/// Prices of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Base input tokens
+    pub input: f64,
+    /// Output tokens
+    pub output: f64,
+    /// Cache writes with a 5 minute TTL
+    pub cache_write_5m: f64,
+    /// Cache writes with a 1 hour TTL
+    pub cache_write_1h: f64,
+    /// Cache hits and refreshes
+    pub cache_read: f64,
+}
+
+/// Features supported by a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    /// Image input
+    pub vision: bool,
+    /// Extended thinking
+    pub thinking: bool,
+    /// Tool use
+    pub tools: bool,
+}
+
+/// Catalog entry for a single model
+///
+/// Limits, capabilities and pricing are `None` for models the catalog only knows
+/// from `list_models`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// Unique identifier for the model
+    pub id: String,
+    /// Display name of the model
+    #[serde(default)]
+    pub display_name: String,
+    /// Alternative names that resolve to this model
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Maximum number of input and output tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u32>,
+    /// Maximum value of `max_tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    /// Supported features
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<ModelCapabilities>,
+    /// Token prices
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<ModelPricing>,
+}
+
+impl ModelInfo {
+    /// Create an entry that only carries an id and display name
+    pub fn new(id: impl Into<String>, display_name: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            display_name: display_name.into(),
+            aliases: Vec::new(),
+            context_window: None,
+            max_output_tokens: None,
+            capabilities: None,
+            pricing: None,
+        }
+    }
+
+    /// Overwrites fields with the ones set in `other`
+    fn merge(&mut self, other: ModelInfo) {
+        if !other.display_name.is_empty() {
+            self.display_name = other.display_name;
+        }
+        for alias in other.aliases {
+            if !self.aliases.contains(&alias) {
+                self.aliases.push(alias);
+            }
+        }
+        self.context_window = other.context_window.or(self.context_window);
+        self.max_output_tokens = other.max_output_tokens.or(self.max_output_tokens);
+        self.capabilities = other.capabilities.or(self.capabilities);
+        self.pricing = other.pricing.or(self.pricing);
+    }
+}
+
+/// Serialized form of a catalog
+#[derive(Debug, Serialize, Deserialize)]
+struct CatalogFile {
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    models: Vec<ModelInfo>,
+}
+
+/// Catalog of model limits, capabilities and prices keyed by id and alias
+#[derive(Debug, Clone, Default)]
+pub struct ModelCatalog {
+    /// Version of the catalog data
+    version: String,
+    /// Entries keyed by canonical model id
+    models: BTreeMap<String, ModelInfo>,
+    /// Alias to canonical model id
+    aliases: HashMap<String, String>,
+}
+
+impl ModelCatalog {
+    /// Create a catalog with the data bundled with this SDK
+    pub fn builtin() -> Self {
+        Self::from_json_str(BUILTIN_MODELS).expect("built-in model catalog is valid")
+    }
+
+    /// Create an empty catalog
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Parse a catalog from JSON
+    ///
+    /// The expected shape is `{"version": "...", "models": [{"id": "...", ...}]}`.
+    pub fn from_json_str(json: &str) -> Result<Self, CatalogError> {
+        let file: CatalogFile =
+            serde_json::from_str(json).map_err(|e| CatalogError::Parse(e.to_string()))?;
+        Ok(Self::from_file(file))
+    }
+
+    /// Parse a catalog from TOML
+    ///
+    /// Uses the same shape as JSON, with models as a `[[models]]` array of tables.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(toml: &str) -> Result<Self, CatalogError> {
+        let file: CatalogFile =
+            toml::from_str(toml).map_err(|e| CatalogError::Parse(e.to_string()))?;
+        Ok(Self::from_file(file))
+    }
+
+    /// Load a catalog from a `.json` or `.toml` file
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, CatalogError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json_str(&contents),
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml_str(&contents),
+            other => Err(CatalogError::UnsupportedFormat(
+                other.unwrap_or_default().to_string(),
+            )),
+        }
+    }
+
+    /// Merge overrides from a `.json` or `.toml` file into this catalog
+    pub fn with_overrides_from_file(
+        mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, CatalogError> {
+        self.merge(Self::from_path(path)?);
+        Ok(self)
+    }
+
+    fn from_file(file: CatalogFile) -> Self {
+        let mut catalog = Self {
+            version: file.version.unwrap_or_default(),
+            ..Default::default()
+        };
+        for model in file.models {
+            catalog.upsert(model);
+        }
+        catalog
+    }
+
+    /// Version of the catalog data
+    ///
+    /// Reflects the last merged catalog that declared a version.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Resolves a model id or alias to the canonical model id
+    pub fn resolve(&self, model: &str) -> Option<&str> {
+        if let Some((id, _)) = self.models.get_key_value(model) {
+            return Some(id);
+        }
+        self.aliases.get(model).map(String::as_str)
+    }
+
+    /// Looks up a model by id or alias
+    pub fn get(&self, model: &str) -> Option<&ModelInfo> {
+        self.resolve(model).and_then(|id| self.models.get(id))
+    }
+
+    /// Whether the catalog knows a model id or alias
+    pub fn contains(&self, model: &str) -> bool {
+        self.resolve(model).is_some()
+    }
+
+    /// Iterates over all entries ordered by id
+    pub fn iter(&self) -> impl Iterator<Item = &ModelInfo> {
+        self.models.values()
+    }
+
+    /// Number of models in the catalog
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    /// Whether the catalog is empty
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    /// Inserts an entry, merging it with an existing entry of the same id or alias
+    pub fn upsert(&mut self, model: ModelInfo) {
+        let id = self.resolve(&model.id).unwrap_or(&model.id).to_string();
+        let entry = self
+            .models
+            .entry(id.clone())
+            .or_insert_with(|| ModelInfo::new(id.clone(), String::new()));
+        entry.merge(ModelInfo {
+            id: id.clone(),
+            ..model
+        });
+
+        for alias in &entry.aliases {
+            self.aliases.insert(alias.clone(), id.clone());
+        }
+    }
+
+    /// Merges another catalog into this one, field by field
+    ///
+    /// Fields set in `other` take precedence over the ones in this catalog.
+    pub fn merge(&mut self, other: ModelCatalog) {
+        if !other.version.is_empty() {
+            self.version = other.version;
+        }
+        for model in other.models.into_values() {
+            self.upsert(model);
+        }
+    }
+
+    /// Adds models returned by the Models API that are not in the catalog yet
+    ///
+    /// Returns the number of models that were added.
+    pub fn merge_models(&mut self, models: &[Model]) -> usize {
+        let mut added = 0;
+        for model in models {
+            if !self.contains(&model.id) {
+                self.upsert(ModelInfo::new(&model.id, &model.display_name));
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Lists all models available to the API key and merges them into the catalog
+    ///
+    /// Returns the number of models that were added.
+    pub async fn sync<C>(&mut self, client: &C) -> Result<usize, ModelError>
+    where
+        C: ModelClient + Sync,
+    {
+        let mut added = 0;
+        let mut params = ListModelsParams::new().limit(1000);
+        loop {
+            let page = client.list_models(Some(&params)).await?;
+            added += self.merge_models(&page.data);
+            match page.last_id {
+                Some(last_id) if page.has_more => params = params.after_id(last_id),
+                _ => return Ok(added),
+            }
+        }
+    }
+
+    /// Checks a request against the model's limits and capabilities
+    ///
+    /// Only checks what the catalog knows; requests for models without limits or
+    /// capabilities pass.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CatalogError::UnknownModel` if the model is not in the catalog, or
+    /// `CatalogError::Invalid` describing the first problem found.
+    pub fn validate(&self, params: &CreateMessageParams) -> Result<(), CatalogError> {
+        let model = self
+            .get(&params.model)
+            .ok_or_else(|| CatalogError::UnknownModel(params.model.clone()))?;
+        let invalid = |reason: String| CatalogError::Invalid {
+            model: params.model.clone(),
+            reason,
+        };
+
+        if let Some(max_output_tokens) = model.max_output_tokens {
+            if params.max_tokens > max_output_tokens {
+                return Err(invalid(format!(
+                    "max_tokens {} exceeds the limit of {}",
+                    params.max_tokens, max_output_tokens
+                )));
+            }
+        }
+
+        if let Some(capabilities) = model.capabilities {
+            if params.thinking.is_some() && !capabilities.thinking {
+                return Err(invalid("extended thinking is not supported".to_string()));
+            }
+            if params.tools.as_ref().is_some_and(|t| !t.is_empty()) && !capabilities.tools {
+                return Err(invalid("tool use is not supported".to_string()));
+            }
+            let has_images = params
+                .messages
+                .iter()
+                .any(|message| match &message.content {
+                    MessageContent::Blocks { content } => content
+                        .iter()
+                        .any(|block| matches!(block, ContentBlock::Image { .. })),
+                    MessageContent::Text { .. } => false,
+                });
+            if has_images && !capabilities.vision {
+                return Err(invalid("image input is not supported".to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_catalog_resolves_aliases() {
+        let catalog = ModelCatalog::builtin();
+        assert!(!catalog.version().is_empty());
+
+        let model = catalog.get("claude-sonnet-4-5").unwrap();
+        assert_eq!(model.id, "claude-sonnet-4-5-20250929");
+        assert_eq!(catalog.get(&model.id), Some(model));
+        assert!(model.pricing.is_some());
+        assert!(catalog.get("claude-unknown").is_none());
+    }
+
+    #[test]
+    fn overrides_merge_field_by_field() {
+        let mut catalog = ModelCatalog::builtin();
+        let overrides = ModelCatalog::from_json_str(
+            r#"{
+                "version": "custom",
+                "models": [
+                    {"id": "claude-3-7-sonnet-latest", "context_window": 100000},
+                    {"id": "claude-next", "display_name": "Claude Next", "aliases": ["next"]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        catalog.merge(overrides);
+
+        assert_eq!(catalog.version(), "custom");
+        let sonnet = catalog.get("claude-3-7-sonnet-20250219").unwrap();
+        assert_eq!(sonnet.context_window, Some(100_000));
+        assert_eq!(sonnet.max_output_tokens, Some(64_000));
+        assert_eq!(sonnet.display_name, "Claude Sonnet 3.7");
+        assert_eq!(catalog.resolve("next"), Some("claude-next"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn parses_toml_catalogs() {
+        let catalog = ModelCatalog::from_toml_str(
+            r#"
+            version = "toml"
+
+            [[models]]
+            id = "claude-custom"
+            context_window = 50000
+            pricing = { input = 1.0, output = 2.0, cache_write_5m = 1.25, cache_write_1h = 2.0, cache_read = 0.1 }
+            "#,
+        )
+        .unwrap();
+
+        let model = catalog.get("claude-custom").unwrap();
+        assert_eq!(model.context_window, Some(50_000));
+        assert_eq!(model.pricing.unwrap().output, 2.0);
+    }
+
+    #[test]
+    fn merge_models_adds_unknown_models() {
+        let mut catalog = ModelCatalog::builtin();
+        let before = catalog.len();
+        let models: Vec<Model> = serde_json::from_value(serde_json::json!([
+            {"type": "model", "id": "claude-sonnet-4-5-20250929", "display_name": "Claude Sonnet 4.5", "created_at": "2025-09-29T00:00:00Z"},
+            {"type": "model", "id": "claude-future-1", "display_name": "Claude Future", "created_at": "2026-01-01T00:00:00Z"}
+        ]))
+        .unwrap();
+
+        assert_eq!(catalog.merge_models(&models), 1);
+        assert_eq!(catalog.len(), before + 1);
+        let future = catalog.get("claude-future-1").unwrap();
+        assert_eq!(future.display_name, "Claude Future");
+        assert!(future.context_window.is_none());
+    }
+
+    #[test]
+    fn validate_checks_limits_and_capabilities() {
+        use crate::types::message::{Message, RequiredMessageParams, Role, Thinking, ThinkingType};
+
+        let catalog = ModelCatalog::builtin();
+        let params = |model: &str, max_tokens| {
+            CreateMessageParams::new(RequiredMessageParams {
+                model: model.to_string(),
+                messages: vec![Message::new_text(Role::User, "Hi")],
+                max_tokens,
+            })
+        };
+
+        assert!(
+            catalog
+                .validate(&params("claude-3-5-haiku-latest", 1024))
+                .is_ok()
+        );
+        assert!(matches!(
+            catalog.validate(&params("claude-3-5-haiku-latest", 10_000)),
+            Err(CatalogError::Invalid { .. })
+        ));
+        let thinking = params("claude-3-5-haiku-latest", 4096).with_thinking(Thinking {
+            budget_tokens: 1024,
+            type_: ThinkingType::Enabled,
+        });
+        assert!(matches!(
+            catalog.validate(&thinking),
+            Err(CatalogError::Invalid { .. })
+        ));
+        assert!(matches!(
+            catalog.validate(&params("claude-unknown", 1)),
+            Err(CatalogError::UnknownModel(_))
+        ));
+    }
+}
@@ -3,10 +3,21 @@
 //! This module provides the main client for interacting with the Anthropic API.
 //! It handles authentication, request construction, and response parsing.
 
+use crate::fallback::FallbackPolicy;
+use crate::stream::StreamTimeouts;
 use reqwest::Client as ReqwestClient;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 use std::error::Error as StdError;
+use std::time::Duration;
+
+/// Errors returned by the request helpers of [`AnthropicClient`]
+pub(crate) trait RequestError: StdError + From<String> {
+    /// Error for a request that could not be sent, or whose response could not be read
+    fn request_failed(message: String) -> Self {
+        Self::from(message)
+    }
+}
 
 /// Anthropic API client
 ///
@@ -49,6 +60,12 @@ pub struct AnthropicClient {
     api_version: String,
     /// The base URL for the Anthropic API
     api_base_url: String,
+    /// Timeouts applied to streaming responses
+    stream_timeouts: StreamTimeouts,
+    /// Maximum number of times a failed stream is resumed
+    stream_resume_attempts: u32,
+    /// Models that messages fall back to when the requested one fails
+    fallback_policy: Option<FallbackPolicy>,
 }
 
 /// Builder for AnthropicClient
@@ -59,6 +76,9 @@ pub struct AnthropicClientBuilder {
     api_version: String,
     api_base_url: String,
     client: Option<ReqwestClient>,
+    stream_timeouts: StreamTimeouts,
+    stream_resume_attempts: u32,
+    fallback_policy: Option<FallbackPolicy>,
 }
 
 impl AnthropicClientBuilder {
@@ -69,6 +89,9 @@ impl AnthropicClientBuilder {
             api_version: api_version.into(),
             api_base_url: AnthropicClient::DEFAULT_API_BASE_URL.to_string(),
             client: None,
+            stream_timeouts: StreamTimeouts::default(),
+            stream_resume_attempts: 0,
+            fallback_policy: None,
         }
     }
 
@@ -90,6 +113,36 @@ impl AnthropicClientBuilder {
         self
     }
 
+    /// Sets the maximum time to wait for the next event of a streaming response
+    ///
+    /// Ping events count as activity. By default a stream waits indefinitely.
+    pub fn with_stream_idle_timeout(mut self, idle: Duration) -> Self {
+        self.stream_timeouts.idle = Some(idle);
+        self
+    }
+
+    /// Sets the maximum time for a streaming response to complete
+    pub fn with_stream_deadline(mut self, deadline: Duration) -> Self {
+        self.stream_timeouts.deadline = Some(deadline);
+        self
+    }
+
+    /// Resumes streams that fail with a retryable error up to `max_attempts` times
+    ///
+    /// A resumed stream continues from the text received so far. Disabled by default.
+    pub fn with_stream_resume(mut self, max_attempts: u32) -> Self {
+        self.stream_resume_attempts = max_attempts;
+        self
+    }
+
+    /// Falls back to other models when a message request fails
+    ///
+    /// The policy applies to `create_message` and `create_message_streaming`.
+    pub fn with_fallback_policy(mut self, policy: FallbackPolicy) -> Self {
+        self.fallback_policy = Some(policy);
+        self
+    }
+
     /// Builds the AnthropicClient with the specified configuration
     pub fn build<E>(self) -> Result<AnthropicClient, E>
     where
@@ -110,6 +163,9 @@ impl AnthropicClientBuilder {
             api_key: self.api_key,
             api_version: self.api_version,
             api_base_url: self.api_base_url,
+            stream_timeouts: self.stream_timeouts,
+            stream_resume_attempts: self.stream_resume_attempts,
+            fallback_policy: self.fallback_policy,
         })
     }
 }
@@ -143,6 +199,18 @@ impl AnthropicClient {
         &self.api_base_url
     }
 
+    pub fn get_stream_timeouts(&self) -> &StreamTimeouts {
+        &self.stream_timeouts
+    }
+
+    pub fn get_stream_resume_attempts(&self) -> u32 {
+        self.stream_resume_attempts
+    }
+
+    pub fn get_fallback_policy(&self) -> Option<&FallbackPolicy> {
+        self.fallback_policy.as_ref()
+    }
+
     /// Creates a new AnthropicClient builder
     pub fn builder(
         api_key: impl Into<String>,
@@ -247,7 +315,7 @@ impl AnthropicClient {
         T: DeserializeOwned,
         Q: Serialize + ?Sized,
         B: Serialize + ?Sized,
-        E: StdError + From<String>,
+        E: RequestError,
     {
         let url = format!("{}{}", self.api_base_url, path);
 
@@ -269,13 +337,16 @@ impl AnthropicClient {
             request = request.json(b);
         }
 
-        let response = request.send().await.map_err(|e| E::from(e.to_string()))?;
+        let response = request
+            .send()
+            .await
+            .map_err(|e| E::request_failed(e.to_string()))?;
 
         let status = response.status();
         let body = response
             .text()
             .await
-            .map_err(|e| E::from(format!("Failed to get response body: {}", e)))?;
+            .map_err(|e| E::request_failed(format!("Failed to get response body: {}", e)))?;
 
         if !status.is_success() {
             return Err(E::from(body));
@@ -306,12 +377,46 @@ impl AnthropicClient {
     where
         T: DeserializeOwned,
         Q: Serialize + ?Sized,
-        E: StdError + From<String>,
+        E: RequestError,
     {
         self.send_request::<T, Q, (), E>(reqwest::Method::GET, path, query, None)
             .await
     }
 
+    /// Sends an authenticated GET request to a URL returned by the API, such as the
+    /// results URL of a message batch, without reading the response body
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The absolute URL to request
+    ///
+    /// # Returns
+    ///
+    /// Returns the response on success, or an error if the request fails to send or
+    /// the response indicates an error
+    pub(crate) async fn get_response<E>(&self, url: &str) -> Result<reqwest::Response, E>
+    where
+        E: RequestError,
+    {
+        let response = self
+            .client
+            .get(url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", &self.api_version)
+            .send()
+            .await
+            .map_err(|e| E::request_failed(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let error_body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Failed to get error response".to_string());
+            return Err(E::from(error_body));
+        }
+        Ok(response)
+    }
+
     /// Sends a POST request to the specified endpoint
     ///
     /// # Type Parameters
@@ -328,7 +433,7 @@ impl AnthropicClient {
     where
         T: DeserializeOwned,
         B: Serialize + ?Sized,
-        E: StdError + From<String>,
+        E: RequestError,
     {
         self.send_request::<T, (), B, E>(reqwest::Method::POST, path, None, body)
             .await
@@ -350,7 +455,7 @@ impl AnthropicClient {
     where
         T: DeserializeOwned,
         Q: Serialize + ?Sized,
-        E: StdError + From<String>,
+        E: RequestError,
     {
         self.send_request::<T, Q, (), E>(reqwest::Method::DELETE, path, query, None)
             .await
@@ -380,7 +485,7 @@ impl AnthropicClient {
         T: DeserializeOwned,
         Q: Serialize + ?Sized,
         B: Serialize + ?Sized,
-        E: StdError + From<String>,
+        E: RequestError,
     {
         let url = format!("{}{}", self.api_base_url, path);
 
@@ -403,13 +508,16 @@ impl AnthropicClient {
             request = request.json(b);
         }
 
-        let response = request.send().await.map_err(|e| E::from(e.to_string()))?;
+        let response = request
+            .send()
+            .await
+            .map_err(|e| E::request_failed(e.to_string()))?;
 
         let status = response.status();
         let body = response
             .text()
             .await
-            .map_err(|e| E::from(format!("Failed to get response body: {}", e)))?;
+            .map_err(|e| E::request_failed(format!("Failed to get response body: {}", e)))?;
 
         if !status.is_success() {
             return Err(E::from(body));
@@ -442,7 +550,7 @@ impl AnthropicClient {
     where
         T: DeserializeOwned,
         Q: Serialize + ?Sized,
-        E: StdError + From<String>,
+        E: RequestError,
     {
         self.send_request_with_beta::<T, Q, (), E>(
             reqwest::Method::GET,
@@ -473,7 +581,7 @@ impl AnthropicClient {
     ) -> Result<Vec<u8>, E>
     where
         Q: Serialize + ?Sized,
-        E: StdError + From<String>,
+        E: RequestError,
     {
         let url = format!("{}{}", self.api_base_url, path);
 
@@ -489,7 +597,10 @@ impl AnthropicClient {
             request = request.query(q);
         }
 
-        let response = request.send().await.map_err(|e| E::from(e.to_string()))?;
+        let response = request
+            .send()
+            .await
+            .map_err(|e| E::request_failed(e.to_string()))?;
 
         let status = response.status();
         
@@ -506,7 +617,7 @@ impl AnthropicClient {
             .bytes()
             .await
             .map(|b| b.to_vec())
-            .map_err(|e| E::from(format!("Failed to get response bytes: {}", e)))
+            .map_err(|e| E::request_failed(format!("Failed to get response bytes: {}", e)))
     }
 
     /// Downloads a file with a beta header
@@ -523,7 +634,7 @@ impl AnthropicClient {
         beta_header: &str,
     ) -> Result<Vec<u8>, E>
     where
-        E: StdError + From<String>,
+        E: RequestError,
     {
         self.send_request_with_beta_bytes::<(), E>(
             reqwest::Method::GET,
@@ -552,7 +663,7 @@ impl AnthropicClient {
     where
         T: DeserializeOwned,
         Q: Serialize + ?Sized,
-        E: StdError + From<String>,
+        E: RequestError,
     {
         self.send_request_with_beta::<T, Q, (), E>(
             reqwest::Method::DELETE,
@@ -583,7 +694,7 @@ impl AnthropicClient {
     ) -> Result<T, E>
     where
         T: DeserializeOwned,
-        E: StdError + From<String>,
+        E: RequestError,
     {
         let url = format!("{}{}", self.api_base_url, path);
 
@@ -602,13 +713,13 @@ impl AnthropicClient {
             .multipart(form)
             .send()
             .await
-            .map_err(|e| E::from(e.to_string()))?;
+            .map_err(|e| E::request_failed(e.to_string()))?;
 
         let status = response.status();
         let body = response
             .text()
             .await
-            .map_err(|e| E::from(format!("Failed to get response body: {}", e)))?;
+            .map_err(|e| E::request_failed(format!("Failed to get response body: {}", e)))?;
 
         if !status.is_success() {
             return Err(E::from(body));
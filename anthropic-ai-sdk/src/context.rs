@@ -0,0 +1,684 @@
+//! Context window management
+//!
+//! Long-running conversations eventually outgrow the model's context window. This
+//! module provides [`ContextManager`], which checks a [`CreateMessageParams`] against
+//! the input limit of the model and trims the history with a [`TrimStrategy`] until the
+//! request fits.
+//!
+//! Trimming always operates on whole turns: a turn starts with a user message that is
+//! not a tool result, so `tool_use` blocks are never separated from their
+//! `tool_result`. After trimming, the history starts with a user turn and roles
+//! alternate.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::context::{ContextManager, TrimStrategy};
+//! use anthropic_ai_sdk::types::message::{CreateMessageParams, MessageClient, MessageError};
+//!
+//! # async fn example(mut params: CreateMessageParams) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+//!
+//! let manager = ContextManager::new(200_000).with_strategy(TrimStrategy::ClearToolResults {
+//!     keep_recent: 3,
+//! });
+//! let report = manager.fit(&client, &mut params).await?;
+//! println!("Trimmed {} -> {} tokens", report.initial_tokens, report.final_tokens);
+//!
+//! let response = client.create_message(Some(&params)).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::catalog::ModelCatalog;
+use crate::tokens::TokenEstimator;
+use crate::types::message::{
+    ContentBlock, CountMessageTokensParams, CreateMessageParams, Message, MessageClient,
+    MessageContent, MessageError, RequiredMessageParams, Role,
+};
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Text that replaces the content of cleared tool results
+pub const CLEARED_TOOL_RESULT: &str = "[tool result cleared to save context]";
+
+/// Error types for context window management
+#[derive(Debug, Error)]
+pub enum ContextError {
+    #[error("Request needs {required} input tokens but the limit is {limit}")]
+    DoesNotFit { required: u32, limit: u32 },
+    #[error("Failed to summarize history: {0}")]
+    SummaryFailed(String),
+    #[error(transparent)]
+    Message(#[from] MessageError),
+}
+
+/// A custom history trimming strategy
+pub trait HistoryTrimmer: Send + Sync {
+    /// Removes or shrinks messages to free roughly `excess_tokens` tokens
+    ///
+    /// Returns `false` when nothing more can be trimmed. The manager normalizes the
+    /// history afterwards so that it starts with a user turn and roles alternate.
+    fn trim(&self, messages: &mut Vec<Message>, excess_tokens: u32) -> bool;
+}
+
+/// Strategy used to shrink a history that does not fit
+#[derive(Clone)]
+pub enum TrimStrategy {
+    /// Drop the oldest turns, keeping tool use and tool result pairs together
+    DropOldestTurns,
+    /// Replace the content of old tool results with a short placeholder
+    ///
+    /// Falls back to dropping the oldest turns when no tool results are left to clear.
+    ClearToolResults {
+        /// Number of most recent tool results to keep intact
+        keep_recent: usize,
+    },
+    /// Summarize older turns with a (cheaper) model call
+    ///
+    /// The summary is prepended to the oldest retained user turn. Falls back to
+    /// dropping the oldest turns if the summarized history still does not fit.
+    Summarize {
+        /// Model used to write the summary
+        model: String,
+        /// Maximum length of the summary
+        max_tokens: u32,
+        /// Number of most recent turns to keep verbatim
+        keep_recent_turns: usize,
+    },
+    /// A user-provided strategy
+    Custom(Arc<dyn HistoryTrimmer>),
+}
+
+impl fmt::Debug for TrimStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DropOldestTurns => write!(f, "DropOldestTurns"),
+            Self::ClearToolResults { keep_recent } => f
+                .debug_struct("ClearToolResults")
+                .field("keep_recent", keep_recent)
+                .finish(),
+            Self::Summarize {
+                model,
+                max_tokens,
+                keep_recent_turns,
+            } => f
+                .debug_struct("Summarize")
+                .field("model", model)
+                .field("max_tokens", max_tokens)
+                .field("keep_recent_turns", keep_recent_turns)
+                .finish(),
+            Self::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// Outcome of fitting a request into the context window
+#[derive(Debug, Clone, Default)]
+pub struct TrimReport {
+    /// Input tokens before trimming
+    pub initial_tokens: u32,
+    /// Input tokens after trimming
+    pub final_tokens: u32,
+    /// Input token limit the request was fitted into
+    pub limit: u32,
+    /// Number of messages removed from the history
+    pub removed_messages: usize,
+    /// Number of tool results whose content was cleared
+    pub cleared_tool_results: usize,
+    /// Number of messages replaced by a summary
+    pub summarized_messages: usize,
+}
+
+impl TrimReport {
+    /// Whether the history was modified
+    pub fn trimmed(&self) -> bool {
+        self.removed_messages > 0 || self.cleared_tool_results > 0 || self.summarized_messages > 0
+    }
+}
+
+/// Keeps requests within the model's context window
+#[derive(Debug, Clone)]
+pub struct ContextManager {
+    /// Total context window of the model (input and output tokens)
+    context_window: u32,
+    /// Strategy used when the request does not fit
+    strategy: TrimStrategy,
+    /// Estimator used for local counts and for sizing trim steps
+    estimator: TokenEstimator,
+    /// Whether to count tokens through the `count_tokens` endpoint
+    use_count_tokens: bool,
+}
+
+impl ContextManager {
+    /// Create a new manager for a model with the given context window
+    ///
+    /// Defaults to dropping the oldest turns and counting tokens locally.
+    pub fn new(context_window: u32) -> Self {
+        Self {
+            context_window,
+            strategy: TrimStrategy::DropOldestTurns,
+            estimator: TokenEstimator::default(),
+            use_count_tokens: false,
+        }
+    }
+
+    /// Create a new manager using a model's context window from the catalog
+    ///
+    /// Returns `None` if the catalog does not know the model's context window.
+    pub fn for_model(catalog: &ModelCatalog, model: &str) -> Option<Self> {
+        catalog.get(model)?.context_window.map(Self::new)
+    }
+
+    /// Set the trimming strategy
+    pub fn with_strategy(mut self, strategy: TrimStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Set the estimator used for local token counts
+    pub fn with_estimator(mut self, estimator: TokenEstimator) -> Self {
+        self.estimator = estimator;
+        self
+    }
+
+    /// Count tokens through the `count_tokens` endpoint instead of estimating locally
+    pub fn with_count_tokens(mut self, use_count_tokens: bool) -> Self {
+        self.use_count_tokens = use_count_tokens;
+        self
+    }
+
+    /// Input token limit for a request, leaving room for `max_tokens` of output
+    pub fn input_limit(&self, params: &CreateMessageParams) -> u32 {
+        self.context_window.saturating_sub(params.max_tokens)
+    }
+
+    /// Counts the input tokens of a request with the configured method
+    pub async fn count_tokens<C>(
+        &self,
+        client: &C,
+        params: &CreateMessageParams,
+    ) -> Result<u32, MessageError>
+    where
+        C: MessageClient + Sync,
+    {
+        if self.use_count_tokens {
+            let count_params = CountMessageTokensParams::from(params);
+            let response = client.count_tokens(Some(&count_params)).await?;
+            Ok(response.input_tokens)
+        } else {
+            Ok(self.estimator.estimate(params))
+        }
+    }
+
+    /// Trims the history of a request until it fits within the input limit
+    ///
+    /// `client` is used for `count_tokens` calls and for the summary request of
+    /// [`TrimStrategy::Summarize`]; with local counting and any other strategy no
+    /// request is sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ContextError::DoesNotFit` if the request is still too large after the
+    /// history has been trimmed down to the last user turn.
+    pub async fn fit<C>(
+        &self,
+        client: &C,
+        params: &mut CreateMessageParams,
+    ) -> Result<TrimReport, ContextError>
+    where
+        C: MessageClient + Sync,
+    {
+        let limit = self.input_limit(params);
+        let initial_tokens = self.count_tokens(client, params).await?;
+        let mut report = TrimReport {
+            initial_tokens,
+            final_tokens: initial_tokens,
+            limit,
+            ..Default::default()
+        };
+
+        let mut tokens = initial_tokens;
+        let mut summarized = false;
+        while tokens > limit {
+            let excess = tokens - limit;
+            let before = params.messages.len();
+
+            let progressed = match &self.strategy {
+                TrimStrategy::DropOldestTurns => self.drop_oldest_turns(params, excess),
+                TrimStrategy::ClearToolResults { keep_recent } => {
+                    let cleared = self.clear_tool_results(params, *keep_recent, excess);
+                    report.cleared_tool_results += cleared;
+                    cleared > 0 || self.drop_oldest_turns(params, excess)
+                }
+                TrimStrategy::Summarize {
+                    model,
+                    max_tokens,
+                    keep_recent_turns,
+                } if !summarized => {
+                    summarized = true;
+                    let replaced =
+                        summarize(client, params, model, *max_tokens, *keep_recent_turns).await?;
+                    report.summarized_messages += replaced;
+                    replaced > 0 || self.drop_oldest_turns(params, excess)
+                }
+                TrimStrategy::Summarize { .. } => self.drop_oldest_turns(params, excess),
+                TrimStrategy::Custom(trimmer) => trimmer.trim(&mut params.messages, excess),
+            };
+
+            normalize_history(&mut params.messages);
+            let after = params.messages.len();
+            if after < before {
+                report.removed_messages += before - after;
+            }
+
+            if !progressed {
+                return Err(ContextError::DoesNotFit {
+                    required: tokens,
+                    limit,
+                });
+            }
+            tokens = self.count_tokens(client, params).await?;
+        }
+
+        // Summaries replace messages rather than removing them
+        report.removed_messages = report
+            .removed_messages
+            .saturating_sub(report.summarized_messages);
+        report.final_tokens = tokens;
+        Ok(report)
+    }
+
+    /// Drops whole turns from the start of the history until roughly `excess` tokens
+    /// have been freed. The most recent turn is always kept.
+    fn drop_oldest_turns(&self, params: &mut CreateMessageParams, excess: u32) -> bool {
+        let starts = turn_starts(&params.messages);
+        if starts.len() < 2 {
+            return false;
+        }
+
+        let mut freed = 0;
+        let mut cut = 0;
+        // Anything before the first turn start is dropped along with it
+        for &end in &starts[1..] {
+            freed += params.messages[cut..end]
+                .iter()
+                .map(|m| self.estimator.estimate_message(m))
+                .sum::<u32>();
+            cut = end;
+            if freed >= excess {
+                break;
+            }
+        }
+
+        params.messages.drain(..cut);
+        true
+    }
+
+    /// Clears the oldest tool results until roughly `excess` tokens have been freed,
+    /// keeping the `keep_recent` most recent ones. Returns the number of cleared results.
+    fn clear_tool_results(
+        &self,
+        params: &mut CreateMessageParams,
+        keep_recent: usize,
+        excess: u32,
+    ) -> usize {
+        let total = params
+            .messages
+            .iter()
+            .flat_map(blocks)
+            .filter(|block| matches!(block, ContentBlock::ToolResult { .. }))
+            .count();
+        let mut clearable = total.saturating_sub(keep_recent);
+
+        let mut freed = 0;
+        let mut cleared = 0;
+        'outer: for message in params.messages.iter_mut() {
+            let MessageContent::Blocks { content } = &mut message.content else {
+                continue;
+            };
+            for block in content.iter_mut() {
+                if clearable == 0 || freed >= excess {
+                    break 'outer;
+                }
+                if let ContentBlock::ToolResult { content, .. } = block {
+                    clearable -= 1;
+                    if content == CLEARED_TOOL_RESULT {
+                        continue;
+                    }
+                    freed += self
+                        .estimator
+                        .estimate_text(content)
+                        .saturating_sub(self.estimator.estimate_text(CLEARED_TOOL_RESULT));
+                    *content = CLEARED_TOOL_RESULT.to_string();
+                    cleared += 1;
+                }
+            }
+        }
+        cleared
+    }
+}
+
+/// Replaces all but the most recent turns with a model-written summary
+///
+/// Returns the number of messages that were summarized.
+async fn summarize<C>(
+    client: &C,
+    params: &mut CreateMessageParams,
+    model: &str,
+    max_tokens: u32,
+    keep_recent_turns: usize,
+) -> Result<usize, ContextError>
+where
+    C: MessageClient + Sync,
+{
+    let starts = turn_starts(&params.messages);
+    let kept_turns = keep_recent_turns.max(1);
+    if starts.len() <= kept_turns {
+        return Ok(0);
+    }
+    let split = starts[starts.len() - kept_turns];
+
+    let transcript = params.messages[..split]
+        .iter()
+        .map(render_message)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let request = CreateMessageParams::new(RequiredMessageParams {
+        model: model.to_string(),
+        messages: vec![Message::new_text(Role::User, transcript)],
+        max_tokens,
+    })
+    .with_system(
+        "Summarize the following conversation so that it can replace the original \
+         messages. Keep facts, decisions, open questions and tool results that are \
+         still relevant. Reply with the summary only.",
+    );
+
+    let response = client.create_message(Some(&request)).await?;
+    let summary = response
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<String>();
+    if summary.trim().is_empty() {
+        return Err(ContextError::SummaryFailed(
+            "summary response contained no text".to_string(),
+        ));
+    }
+
+    params.messages.drain(..split);
+    let first = &mut params.messages[0];
+    let mut content = into_blocks(std::mem::replace(
+        &mut first.content,
+        MessageContent::Blocks { content: vec![] },
+    ));
+    content.insert(
+        0,
+        ContentBlock::text(format!("Summary of the earlier conversation:\n{}", summary)),
+    );
+    first.content = MessageContent::Blocks { content };
+    Ok(split)
+}
+
+/// Ensures the history starts with a user turn and that roles alternate
+///
+/// Leading messages that cannot start a conversation (assistant messages and orphaned
+/// tool results) are removed, and consecutive messages with the same role are merged.
+pub fn normalize_history(messages: &mut Vec<Message>) {
+    let first_turn = messages
+        .iter()
+        .position(is_turn_start)
+        .unwrap_or(messages.len());
+    messages.drain(..first_turn);
+
+    let mut normalized: Vec<Message> = Vec::with_capacity(messages.len());
+    for message in messages.drain(..) {
+        match normalized.last_mut() {
+            Some(last) if last.role == message.role => {
+                let mut content = into_blocks(std::mem::replace(
+                    &mut last.content,
+                    MessageContent::Blocks { content: vec![] },
+                ));
+                content.extend(into_blocks(message.content));
+                last.content = MessageContent::Blocks { content };
+            }
+            _ => normalized.push(message),
+        }
+    }
+    *messages = normalized;
+}
+
+/// Indices of the messages that start a turn
+fn turn_starts(messages: &[Message]) -> Vec<usize> {
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| is_turn_start(message))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// A turn starts with a user message that is not only tool results
+fn is_turn_start(message: &Message) -> bool {
+    if message.role != Role::User {
+        return false;
+    }
+    match &message.content {
+        MessageContent::Text { .. } => true,
+        MessageContent::Blocks { content } => !content
+            .iter()
+            .any(|block| matches!(block, ContentBlock::ToolResult { .. })),
+    }
+}
+
+fn blocks(message: &Message) -> &[ContentBlock] {
+    match &message.content {
+        MessageContent::Text { .. } => &[],
+        MessageContent::Blocks { content } => content,
+    }
+}
+
+fn into_blocks(content: MessageContent) -> Vec<ContentBlock> {
+    match content {
+        MessageContent::Text { content } => vec![ContentBlock::text(content)],
+        MessageContent::Blocks { content } => content,
+    }
+}
+
+fn render_message(message: &Message) -> String {
+    let speaker = match message.role {
+        Role::User => "User",
+        Role::Assistant => "Assistant",
+    };
+    let text = match &message.content {
+        MessageContent::Text { content } => content.clone(),
+        MessageContent::Blocks { content } => content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.clone()),
+                ContentBlock::ToolUse { name, input, .. } => {
+                    Some(format!("[called tool {} with {}]", name, input))
+                }
+                ContentBlock::ToolResult { content, .. } => {
+                    Some(format!("[tool result: {}]", content))
+                }
+                ContentBlock::Image { .. } => Some("[image]".to_string()),
+                ContentBlock::Document { title, .. } => Some(format!(
+                    "[document{}]",
+                    title
+                        .as_deref()
+                        .map(|t| format!(": {}", t))
+                        .unwrap_or_default()
+                )),
+                ContentBlock::Thinking { .. } | ContentBlock::RedactedThinking { .. } => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    format!("{}: {}", speaker, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_exchange(id: &str, result: &str) -> Vec<Message> {
+        vec![
+            Message::new_blocks(
+                Role::Assistant,
+                vec![ContentBlock::ToolUse {
+                    id: id.to_string(),
+                    name: "search".to_string(),
+                    input: serde_json::json!({"q": id}),
+                }],
+            ),
+            Message::new_blocks(
+                Role::User,
+                vec![ContentBlock::ToolResult {
+                    tool_use_id: id.to_string(),
+                    content: result.to_string(),
+                }],
+            ),
+        ]
+    }
+
+    fn history() -> CreateMessageParams {
+        let mut messages = vec![Message::new_text(Role::User, "first question")];
+        messages.extend(tool_exchange("a", &"long result ".repeat(200)));
+        messages.push(Message::new_text(Role::Assistant, "first answer"));
+        messages.push(Message::new_text(Role::User, "second question"));
+        messages.extend(tool_exchange("b", &"another result ".repeat(200)));
+        messages.push(Message::new_text(Role::Assistant, "second answer"));
+        messages.push(Message::new_text(Role::User, "third question"));
+
+        CreateMessageParams::new(RequiredMessageParams {
+            model: "claude-sonnet-4-5".to_string(),
+            messages,
+            max_tokens: 1024,
+        })
+    }
+
+    #[test]
+    fn dropping_turns_keeps_tool_pairs_together() {
+        let manager = ContextManager::new(0);
+        let mut params = history();
+
+        assert!(manager.drop_oldest_turns(&mut params, 1));
+        assert_eq!(params.messages.len(), 5);
+        assert!(is_turn_start(&params.messages[0]));
+        assert!(matches!(params.messages[1].role, Role::Assistant));
+
+        assert!(manager.drop_oldest_turns(&mut params, 1));
+        assert_eq!(params.messages.len(), 1);
+        assert!(!manager.drop_oldest_turns(&mut params, 1));
+    }
+
+    #[test]
+    fn clearing_tool_results_keeps_recent_ones() {
+        let manager = ContextManager::new(0);
+        let mut params = history();
+
+        assert_eq!(manager.clear_tool_results(&mut params, 1, u32::MAX), 1);
+        assert_eq!(manager.clear_tool_results(&mut params, 1, u32::MAX), 0);
+
+        let results: Vec<_> = params
+            .messages
+            .iter()
+            .flat_map(blocks)
+            .filter_map(|block| match block {
+                ContentBlock::ToolResult { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(results[0], CLEARED_TOOL_RESULT);
+        assert_ne!(results[1], CLEARED_TOOL_RESULT);
+    }
+
+    #[test]
+    fn normalize_starts_with_user_and_alternates() {
+        let mut messages = tool_exchange("a", "result");
+        messages.push(Message::new_text(Role::Assistant, "answer"));
+        messages.push(Message::new_text(Role::User, "question"));
+        messages.push(Message::new_text(Role::User, "follow up"));
+        messages.push(Message::new_text(Role::Assistant, "reply"));
+
+        normalize_history(&mut messages);
+
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(messages[0].role, Role::User));
+        assert_eq!(blocks(&messages[0]).len(), 2);
+        assert!(matches!(messages[1].role, Role::Assistant));
+    }
+
+    struct OfflineClient;
+
+    #[async_trait::async_trait]
+    impl MessageClient for OfflineClient {
+        async fn create_message<'a>(
+            &'a self,
+            _params: Option<&'a CreateMessageParams>,
+        ) -> Result<crate::types::message::CreateMessageResponse, MessageError> {
+            Err(MessageError::RequestFailed("offline".to_string()))
+        }
+
+        async fn count_tokens<'a>(
+            &'a self,
+            _params: Option<&'a CountMessageTokensParams>,
+        ) -> Result<crate::types::message::CountMessageTokensResponse, MessageError> {
+            Err(MessageError::RequestFailed("offline".to_string()))
+        }
+
+        async fn create_message_streaming<'a>(
+            &'a self,
+            _body: &'a CreateMessageParams,
+        ) -> Result<
+            impl futures_util::Stream<Item = Result<crate::types::message::StreamEvent, MessageError>>
+            + 'a,
+            MessageError,
+        > {
+            Ok(futures_util::stream::empty())
+        }
+    }
+
+    #[tokio::test]
+    async fn fit_trims_until_request_fits() {
+        let estimator = TokenEstimator::new();
+        let mut params = history();
+        let initial = estimator.estimate(&params);
+        let manager = ContextManager::new(initial - 100 + params.max_tokens)
+            .with_estimator(estimator)
+            .with_strategy(TrimStrategy::ClearToolResults { keep_recent: 1 });
+
+        let report = manager.fit(&OfflineClient, &mut params).await.unwrap();
+
+        assert_eq!(report.initial_tokens, initial);
+        assert!(report.final_tokens <= report.limit);
+        assert_eq!(report.cleared_tool_results, 1);
+        assert_eq!(report.removed_messages, 0);
+
+        let manager = ContextManager::new(params.max_tokens + 10);
+        let result = manager.fit(&OfflineClient, &mut params).await;
+        assert!(matches!(result, Err(ContextError::DoesNotFit { .. })));
+        assert_eq!(params.messages.len(), 1);
+    }
+
+    #[test]
+    fn for_model_uses_catalog_context_window() {
+        let catalog = ModelCatalog::builtin();
+        let manager = ContextManager::for_model(&catalog, "claude-sonnet-4-5").unwrap();
+        assert_eq!(manager.input_limit(&history()), 200_000 - 1024);
+        assert!(ContextManager::for_model(&catalog, "claude-unknown").is_none());
+    }
+
+    #[test]
+    fn input_limit_reserves_output_tokens() {
+        let manager = ContextManager::new(200_000);
+        assert_eq!(manager.input_limit(&history()), 198_976);
+    }
+}
@@ -0,0 +1,321 @@
+//! Cost calculation
+//!
+//! This module provides [`CostCalculator`], which turns the [`Usage`] reported for a
+//! response into a cost breakdown in USD: base input, output, cache writes by TTL,
+//! cache reads and server tool uses, with the batch discount applied to results of the
+//! Message Batches API.
+//!
+//! Prices come from a [`ModelCatalog`], so they can be overridden from a file or set
+//! per model on the calculator.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::cost::CostCalculator;
+//! use anthropic_ai_sdk::types::message::{CreateMessageParams, MessageClient, MessageError};
+//!
+//! # async fn example(params: CreateMessageParams) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+//! let calculator = CostCalculator::new();
+//!
+//! let response = client.create_message(Some(&params)).await?;
+//! let cost = calculator.response_cost(&response)?;
+//! println!("This response cost ${:.6}", cost.total());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::catalog::{ModelCatalog, ModelInfo, ModelPricing};
+use crate::types::message::{CreateMessageResponse, ServiceTier, Usage};
+use crate::types::message_batches::MessageBatchResult;
+use serde::Serialize;
+use std::ops::{Add, AddAssign};
+use thiserror::Error;
+
+/// Price of 1,000 web search requests in USD
+pub const WEB_SEARCH_PRICE_PER_1000: f64 = 10.0;
+
+/// Fraction of the token price charged for requests processed through the Batches API
+pub const BATCH_PRICE_FACTOR: f64 = 0.5;
+
+const TOKENS_PER_MILLION: f64 = 1_000_000.0;
+
+/// Error types for cost calculation
+#[derive(Debug, Error)]
+pub enum CostError {
+    #[error("Unknown model: {0}")]
+    UnknownModel(String),
+    #[error("No pricing available for model: {0}")]
+    MissingPricing(String),
+}
+
+/// Cost of a response in USD, broken down by line item
+///
+/// Line items are at full price; `batch_discount` is the amount subtracted for
+/// requests processed through the Batches API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CostBreakdown {
+    /// Base input tokens
+    pub input: f64,
+    /// Output tokens
+    pub output: f64,
+    /// Cache writes with a 5 minute TTL
+    pub cache_write_5m: f64,
+    /// Cache writes with a 1 hour TTL
+    pub cache_write_1h: f64,
+    /// Cache reads
+    pub cache_read: f64,
+    /// Server tool uses such as web search
+    pub server_tools: f64,
+    /// Amount subtracted by the batch discount
+    pub batch_discount: f64,
+}
+
+impl CostBreakdown {
+    /// Cost of all token line items before discounts
+    pub fn tokens(&self) -> f64 {
+        self.input + self.output + self.cache_write_5m + self.cache_write_1h + self.cache_read
+    }
+
+    /// Total cost after discounts
+    pub fn total(&self) -> f64 {
+        self.tokens() + self.server_tools - self.batch_discount
+    }
+}
+
+impl Add for CostBreakdown {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for CostBreakdown {
+    fn add_assign(&mut self, other: Self) {
+        self.input += other.input;
+        self.output += other.output;
+        self.cache_write_5m += other.cache_write_5m;
+        self.cache_write_1h += other.cache_write_1h;
+        self.cache_read += other.cache_read;
+        self.server_tools += other.server_tools;
+        self.batch_discount += other.batch_discount;
+    }
+}
+
+/// Maps model usage to a cost breakdown using a configurable price table
+#[derive(Debug, Clone)]
+pub struct CostCalculator {
+    /// Source of per-model token prices
+    catalog: ModelCatalog,
+    /// Price of 1,000 web search requests
+    web_search_price_per_1000: f64,
+    /// Fraction of the token price charged for batch requests
+    batch_price_factor: f64,
+}
+
+impl Default for CostCalculator {
+    fn default() -> Self {
+        Self::with_catalog(ModelCatalog::builtin())
+    }
+}
+
+impl CostCalculator {
+    /// Create a calculator with the built-in price table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a calculator that reads prices from the given catalog
+    pub fn with_catalog(catalog: ModelCatalog) -> Self {
+        Self {
+            catalog,
+            web_search_price_per_1000: WEB_SEARCH_PRICE_PER_1000,
+            batch_price_factor: BATCH_PRICE_FACTOR,
+        }
+    }
+
+    /// Set or override the prices of a model
+    pub fn with_pricing(mut self, model: impl Into<String>, pricing: ModelPricing) -> Self {
+        self.catalog.upsert(ModelInfo {
+            pricing: Some(pricing),
+            ..ModelInfo::new(model, String::new())
+        });
+        self
+    }
+
+    /// Set the price of 1,000 web search requests
+    pub fn with_web_search_price(mut self, price_per_1000: f64) -> Self {
+        self.web_search_price_per_1000 = price_per_1000;
+        self
+    }
+
+    /// Set the fraction of the token price charged for batch requests
+    pub fn with_batch_price_factor(mut self, factor: f64) -> Self {
+        self.batch_price_factor = factor.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The catalog prices are read from
+    pub fn catalog(&self) -> &ModelCatalog {
+        &self.catalog
+    }
+
+    /// Prices of a model
+    pub fn pricing(&self, model: &str) -> Result<ModelPricing, CostError> {
+        self.catalog
+            .get(model)
+            .ok_or_else(|| CostError::UnknownModel(model.to_string()))?
+            .pricing
+            .ok_or_else(|| CostError::MissingPricing(model.to_string()))
+    }
+
+    /// Calculates the cost of a model's usage
+    ///
+    /// The batch discount is applied when the usage reports the `batch` service tier.
+    pub fn cost(&self, model: &str, usage: &Usage) -> Result<CostBreakdown, CostError> {
+        let batch = usage.service_tier == Some(ServiceTier::Batch);
+        self.calculate(model, usage, batch)
+    }
+
+    /// Calculates the cost of a model's usage for a request processed as a batch
+    pub fn batch_cost(&self, model: &str, usage: &Usage) -> Result<CostBreakdown, CostError> {
+        self.calculate(model, usage, true)
+    }
+
+    /// Calculates the cost of a message response
+    pub fn response_cost(
+        &self,
+        response: &CreateMessageResponse,
+    ) -> Result<CostBreakdown, CostError> {
+        self.cost(&response.model, &response.usage)
+    }
+
+    /// Calculates the cost of a result from `retrieve_message_batch_results`
+    ///
+    /// Only succeeded requests are billed; other results cost nothing.
+    pub fn batch_result_cost(
+        &self,
+        result: &MessageBatchResult,
+    ) -> Result<CostBreakdown, CostError> {
+        match result.result.message() {
+            Some(message) => self.batch_cost(&message.model, &message.usage),
+            None => Ok(CostBreakdown::default()),
+        }
+    }
+
+    fn calculate(
+        &self,
+        model: &str,
+        usage: &Usage,
+        batch: bool,
+    ) -> Result<CostBreakdown, CostError> {
+        let pricing = self.pricing(model)?;
+        let per_token = |tokens: u32, price: f64| tokens as f64 * price / TOKENS_PER_MILLION;
+
+        let (cache_5m, cache_1h) = match usage.cache_creation {
+            Some(creation) => (
+                creation.ephemeral_5m_input_tokens,
+                creation.ephemeral_1h_input_tokens,
+            ),
+            None => (usage.cache_creation_input_tokens.unwrap_or(0), 0),
+        };
+        let web_searches = usage.server_tool_use.map_or(0, |u| u.web_search_requests);
+
+        let mut breakdown = CostBreakdown {
+            input: per_token(usage.input_tokens, pricing.input),
+            output: per_token(usage.output_tokens, pricing.output),
+            cache_write_5m: per_token(cache_5m, pricing.cache_write_5m),
+            cache_write_1h: per_token(cache_1h, pricing.cache_write_1h),
+            cache_read: per_token(
+                usage.cache_read_input_tokens.unwrap_or(0),
+                pricing.cache_read,
+            ),
+            server_tools: web_searches as f64 * self.web_search_price_per_1000 / 1000.0,
+            batch_discount: 0.0,
+        };
+        if batch {
+            breakdown.batch_discount = breakdown.tokens() * (1.0 - self.batch_price_factor);
+        }
+        Ok(breakdown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::test_support::assert_close;
+    use crate::types::message::{CacheCreation, ServerToolUsage};
+
+    #[test]
+    fn prices_every_line_item() {
+        let calculator = CostCalculator::new();
+        let usage = Usage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            cache_creation_input_tokens: Some(300_000),
+            cache_read_input_tokens: Some(2_000_000),
+            cache_creation: Some(CacheCreation {
+                ephemeral_5m_input_tokens: 200_000,
+                ephemeral_1h_input_tokens: 100_000,
+            }),
+            server_tool_use: Some(ServerToolUsage {
+                web_search_requests: 5,
+            }),
+            service_tier: Some(ServiceTier::Standard),
+        };
+
+        let cost = calculator.cost("claude-sonnet-4-5", &usage).unwrap();
+        assert_close(cost.input, 3.0);
+        assert_close(cost.output, 1.5);
+        assert_close(cost.cache_write_5m, 0.75);
+        assert_close(cost.cache_write_1h, 0.6);
+        assert_close(cost.cache_read, 0.6);
+        assert_close(cost.server_tools, 0.05);
+        assert_close(cost.batch_discount, 0.0);
+        assert_close(cost.total(), 6.5);
+    }
+
+    #[test]
+    fn batch_discount_halves_token_costs() {
+        let calculator = CostCalculator::new();
+        let usage = Usage {
+            input_tokens: 1_000_000,
+            output_tokens: 1_000_000,
+            service_tier: Some(ServiceTier::Batch),
+            ..Default::default()
+        };
+
+        let cost = calculator.cost("claude-haiku-4-5", &usage).unwrap();
+        assert_close(cost.tokens(), 6.0);
+        assert_close(cost.batch_discount, 3.0);
+        assert_close(cost.total(), 3.0);
+    }
+
+    #[test]
+    fn custom_prices_and_unknown_models() {
+        let calculator = CostCalculator::new().with_pricing(
+            "my-model",
+            ModelPricing {
+                input: 1.0,
+                output: 2.0,
+                cache_write_5m: 0.0,
+                cache_write_1h: 0.0,
+                cache_read: 0.0,
+            },
+        );
+        let usage = Usage {
+            input_tokens: 500_000,
+            output_tokens: 500_000,
+            ..Default::default()
+        };
+
+        assert_close(calculator.cost("my-model", &usage).unwrap().total(), 1.5);
+        assert!(matches!(
+            calculator.cost("other-model", &usage),
+            Err(CostError::UnknownModel(_))
+        ));
+    }
+}
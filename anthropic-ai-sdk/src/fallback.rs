@@ -0,0 +1,420 @@
+//! Model fallback
+//!
+//! This module provides [`FallbackPolicy`], which moves a request to the next model of
+//! an ordered list when the requested model is overloaded or no longer available,
+//! instead of failing. A policy can be set on the client with
+//! [`with_fallback_policy`](crate::client::AnthropicClientBuilder::with_fallback_policy),
+//! in which case `create_message` and `create_message_streaming` use it, or passed per
+//! request to [`create_message_with_fallback`](crate::client::AnthropicClient::create_message_with_fallback)
+//! and [`create_message_streaming_with_fallback`](crate::client::AnthropicClient::create_message_streaming_with_fallback),
+//! which also report the failed attempts.
+//!
+//! Streams fall back until their first event arrives; once an event has been yielded
+//! the stream stays on its model.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::fallback::FallbackPolicy;
+//! use anthropic_ai_sdk::types::message::{CreateMessageParams, MessageError};
+//!
+//! # async fn example(params: CreateMessageParams) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+//!
+//! let policy = FallbackPolicy::new(["claude-sonnet-4-5", "claude-haiku-4-5"]);
+//! let served = client.create_message_with_fallback(&params, &policy).await?;
+//!
+//! println!("Served by {} after {} failures", served.model, served.failures.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::catalog::ModelCatalog;
+use crate::types::error::ApiErrorKind;
+use crate::types::message::{ContentBlock, CreateMessageParams, MessageContent, MessageError};
+use std::sync::Arc;
+
+/// The smallest thinking budget the API accepts
+const MIN_THINKING_BUDGET: usize = 1024;
+
+/// Ordered list of models to fall back to, and the errors that trigger a fallback
+#[derive(Debug, Clone)]
+pub struct FallbackPolicy {
+    models: Vec<String>,
+    triggers: Vec<ApiErrorKind>,
+    strip_unsupported: bool,
+    catalog: Arc<ModelCatalog>,
+}
+
+impl FallbackPolicy {
+    /// Create a policy falling back to `models`, in order
+    ///
+    /// By default `overloaded_error` and `not_found_error` (returned for retired models)
+    /// trigger a fallback, and thinking and tools are stripped from requests to models
+    /// that the built-in catalog lists as not supporting them. Models that cannot
+    /// serve a request whose messages already use tools are skipped.
+    pub fn new(models: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            models: models.into_iter().map(Into::into).collect(),
+            triggers: vec![ApiErrorKind::Overloaded, ApiErrorKind::NotFound],
+            strip_unsupported: true,
+            catalog: Arc::new(ModelCatalog::builtin()),
+        }
+    }
+
+    /// Set the error kinds that trigger a fallback
+    pub fn with_triggers(mut self, triggers: impl IntoIterator<Item = ApiErrorKind>) -> Self {
+        self.triggers = triggers.into_iter().collect();
+        self
+    }
+
+    /// Add an error kind that triggers a fallback
+    pub fn with_trigger(mut self, trigger: ApiErrorKind) -> Self {
+        if !self.triggers.contains(&trigger) {
+            self.triggers.push(trigger);
+        }
+        self
+    }
+
+    /// Set whether thinking and tools are stripped from requests to fallback models
+    /// that do not support them
+    pub fn with_strip_unsupported(mut self, strip_unsupported: bool) -> Self {
+        self.strip_unsupported = strip_unsupported;
+        self
+    }
+
+    /// Use `catalog` to look up the capabilities of fallback models
+    pub fn with_catalog(mut self, catalog: ModelCatalog) -> Self {
+        self.catalog = Arc::new(catalog);
+        self
+    }
+
+    /// Models to fall back to, in order
+    pub fn models(&self) -> &[String] {
+        &self.models
+    }
+
+    /// Error kinds that trigger a fallback
+    pub fn triggers(&self) -> &[ApiErrorKind] {
+        &self.triggers
+    }
+
+    /// Whether `error` triggers a fallback
+    pub fn should_fall_back(&self, error: &MessageError) -> bool {
+        error
+            .kind()
+            .is_some_and(|kind| self.triggers.contains(kind))
+    }
+
+    /// Adapt `params` to be sent to `model`
+    ///
+    /// `max_tokens` is lowered to the model's output limit in the catalog. Thinking and
+    /// tools are removed if stripping is enabled and the catalog lists the model as not
+    /// supporting them. A kept thinking budget is lowered below the new `max_tokens`,
+    /// and thinking is removed if that leaves less than the minimum budget of 1024
+    /// tokens.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the model cannot serve the request: the messages contain tool
+    /// use or tool result blocks, which the API rejects without tool definitions, and
+    /// the model does not support tools.
+    pub fn params_for(
+        &self,
+        params: &CreateMessageParams,
+        model: &str,
+    ) -> Option<CreateMessageParams> {
+        let mut params = CreateMessageParams {
+            model: model.to_string(),
+            ..params.clone()
+        };
+        let Some(info) = self.catalog.get(model) else {
+            return Some(params);
+        };
+        if let Some(max_output_tokens) = info.max_output_tokens {
+            params.max_tokens = params.max_tokens.min(max_output_tokens);
+        }
+        if let Some(capabilities) = info.capabilities.filter(|_| self.strip_unsupported) {
+            if !capabilities.thinking {
+                params.thinking = None;
+            }
+            if !capabilities.tools {
+                if has_tool_blocks(&params) {
+                    return None;
+                }
+                params.tools = None;
+                params.tool_choice = None;
+            }
+        }
+        if let Some(thinking) = &mut params.thinking {
+            let max_budget = (params.max_tokens as usize).saturating_sub(1);
+            if max_budget < MIN_THINKING_BUDGET {
+                params.thinking = None;
+            } else {
+                thinking.budget_tokens = thinking.budget_tokens.min(max_budget);
+            }
+        }
+        Some(params)
+    }
+
+    /// Send `body` with `send`, moving on to the next model while it fails with a
+    /// triggering error
+    pub(crate) async fn run<T, F, Fut>(
+        &self,
+        body: &CreateMessageParams,
+        mut send: F,
+    ) -> Result<FallbackResponse<T>, MessageError>
+    where
+        F: FnMut(CreateMessageParams) -> Fut,
+        Fut: Future<Output = Result<T, MessageError>>,
+    {
+        let mut fallbacks = self
+            .models
+            .iter()
+            .filter(|model| **model != body.model)
+            .filter_map(|model| Some((model, self.params_for(body, model)?)));
+        let mut failures = Vec::new();
+        let mut model = body.model.clone();
+        let mut params = body.clone();
+
+        loop {
+            let error = match send(params).await {
+                Ok(response) => {
+                    return Ok(FallbackResponse {
+                        response,
+                        model,
+                        failures,
+                    });
+                }
+                Err(error) => error,
+            };
+            if !self.should_fall_back(&error) {
+                return Err(error);
+            }
+            let Some((next, next_params)) = fallbacks.next() else {
+                return Err(error);
+            };
+            tracing::warn!("Falling back from {} to {}: {}", model, next, error);
+            failures.push(FallbackFailure { model, error });
+            model = next.clone();
+            params = next_params;
+        }
+    }
+}
+
+/// Whether any message contains tool use or tool result blocks
+fn has_tool_blocks(params: &CreateMessageParams) -> bool {
+    params
+        .messages
+        .iter()
+        .any(|message| match &message.content {
+            MessageContent::Blocks { content } => content.iter().any(|block| {
+                matches!(
+                    block,
+                    ContentBlock::ToolUse { .. } | ContentBlock::ToolResult { .. }
+                )
+            }),
+            MessageContent::Text { .. } => false,
+        })
+}
+
+/// Model that failed before the request fell back to the next one
+#[derive(Debug, Clone)]
+pub struct FallbackFailure {
+    /// Model the request was sent to
+    pub model: String,
+    /// Error the model failed with
+    pub error: MessageError,
+}
+
+/// Response of a request sent with a fallback policy
+#[derive(Debug, Clone)]
+pub struct FallbackResponse<T> {
+    /// The response
+    pub response: T,
+    /// Model that served the response
+    pub model: String,
+    /// Models that failed before, in order
+    pub failures: Vec<FallbackFailure>,
+}
+
+impl<T> FallbackResponse<T> {
+    /// Whether the response was served by a fallback model
+    pub fn fell_back(&self) -> bool {
+        !self.failures.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::error::ApiErrorDetail;
+    use crate::types::message::{Message, Role, Thinking, ThinkingType, Tool};
+
+    fn api_error(kind: ApiErrorKind) -> MessageError {
+        MessageError::Api(ApiErrorDetail {
+            kind,
+            message: "error".to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn falls_back_in_order_on_triggering_errors() {
+        let policy = FallbackPolicy::new(["claude-primary", "claude-a", "claude-b"]);
+        let body = CreateMessageParams {
+            model: "claude-primary".to_string(),
+            ..Default::default()
+        };
+
+        let mut sent = Vec::new();
+        let served = policy
+            .run(&body, |params| {
+                sent.push(params.model.clone());
+                let result = match params.model.as_str() {
+                    "claude-primary" => Err(api_error(ApiErrorKind::Overloaded)),
+                    "claude-a" => Err(api_error(ApiErrorKind::NotFound)),
+                    _ => Ok(params.model),
+                };
+                async move { result }
+            })
+            .await
+            .unwrap();
+        assert_eq!(sent, ["claude-primary", "claude-a", "claude-b"]);
+        assert_eq!(served.model, "claude-b");
+        assert_eq!(served.response, "claude-b");
+        assert_eq!(served.failures.len(), 2);
+        assert!(served.fell_back());
+
+        let error = policy
+            .run(&body, |_| async {
+                Err::<(), _>(api_error(ApiErrorKind::InvalidRequest))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), Some(&ApiErrorKind::InvalidRequest));
+
+        let error = policy
+            .run(&body, |_| async {
+                Err::<(), _>(api_error(ApiErrorKind::Overloaded))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), Some(&ApiErrorKind::Overloaded));
+    }
+
+    #[test]
+    fn strips_features_the_fallback_does_not_support() {
+        let mut catalog = ModelCatalog::empty();
+        let mut info = crate::catalog::ModelInfo::new("claude-basic", "Claude Basic");
+        info.max_output_tokens = Some(8_192);
+        info.capabilities = Some(crate::catalog::ModelCapabilities {
+            vision: true,
+            thinking: false,
+            tools: false,
+        });
+        catalog.upsert(info);
+
+        let body = CreateMessageParams {
+            max_tokens: 64_000,
+            tools: Some(vec![Tool {
+                name: "get_weather".to_string(),
+                description: None,
+                input_schema: serde_json::json!({"type": "object"}),
+            }]),
+            ..Default::default()
+        }
+        .with_thinking(Thinking {
+            budget_tokens: 1024,
+            type_: ThinkingType::Enabled,
+        });
+        let policy = FallbackPolicy::new(["claude-basic"]).with_catalog(catalog);
+        let params = policy.params_for(&body, "claude-basic").unwrap();
+        assert_eq!(params.model, "claude-basic");
+        assert_eq!(params.max_tokens, 8_192);
+        assert!(params.thinking.is_none());
+        assert!(params.tools.is_none());
+
+        // Tool blocks in the history need tool definitions
+        let mut with_history = body.clone();
+        with_history.messages = vec![
+            Message::new_text(Role::User, "What is the weather?"),
+            Message::new_blocks(
+                Role::Assistant,
+                vec![ContentBlock::ToolUse {
+                    id: "toolu_1".to_string(),
+                    name: "get_weather".to_string(),
+                    input: serde_json::json!({}),
+                }],
+            ),
+        ];
+        assert!(policy.params_for(&with_history, "claude-basic").is_none());
+
+        let policy = policy.with_strip_unsupported(false);
+        let params = policy.params_for(&body, "claude-basic").unwrap();
+        assert!(params.thinking.is_some());
+        assert_eq!(params.max_tokens, 8_192);
+    }
+
+    #[test]
+    fn lowers_the_thinking_budget_below_max_tokens() {
+        let body = CreateMessageParams {
+            model: "claude-sonnet-4-5".to_string(),
+            max_tokens: 64_000,
+            ..Default::default()
+        }
+        .with_thinking(Thinking {
+            budget_tokens: 40_000,
+            type_: ThinkingType::Enabled,
+        });
+        let policy = FallbackPolicy::new(["claude-opus-4-1"]);
+        let params = policy.params_for(&body, "claude-opus-4-1").unwrap();
+        assert_eq!(params.max_tokens, 32_000);
+        assert_eq!(params.thinking.unwrap().budget_tokens, 31_999);
+
+        let mut catalog = ModelCatalog::empty();
+        let mut info = crate::catalog::ModelInfo::new("claude-small", "Claude Small");
+        info.max_output_tokens = Some(1_024);
+        catalog.upsert(info);
+        let policy = FallbackPolicy::new(["claude-small"]).with_catalog(catalog);
+        let params = policy.params_for(&body, "claude-small").unwrap();
+        assert_eq!(params.max_tokens, 1_024);
+        assert!(params.thinking.is_none());
+    }
+
+    #[tokio::test]
+    async fn skips_fallbacks_that_cannot_serve_the_request() {
+        let mut catalog = ModelCatalog::empty();
+        let mut info = crate::catalog::ModelInfo::new("claude-basic", "Claude Basic");
+        info.capabilities = Some(crate::catalog::ModelCapabilities {
+            vision: true,
+            thinking: true,
+            tools: false,
+        });
+        catalog.upsert(info);
+
+        let body = CreateMessageParams {
+            model: "claude-primary".to_string(),
+            messages: vec![Message::new_blocks(
+                Role::User,
+                vec![ContentBlock::ToolResult {
+                    tool_use_id: "toolu_1".to_string(),
+                    content: "Sunny".to_string(),
+                }],
+            )],
+            ..Default::default()
+        };
+        let policy = FallbackPolicy::new(["claude-primary", "claude-basic"]).with_catalog(catalog);
+
+        let mut sent = Vec::new();
+        let error = policy
+            .run(&body, |params| {
+                sent.push(params.model);
+                async { Err::<(), _>(api_error(ApiErrorKind::Overloaded)) }
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(sent, ["claude-primary"]);
+        assert_eq!(error.kind(), Some(&ApiErrorKind::Overloaded));
+    }
+}
@@ -0,0 +1,437 @@
+//! Image preprocessing helpers
+//!
+//! Claude rescales any image whose long edge exceeds 1568 pixels (or that is larger
+//! than roughly 1.15 megapixels) before it is processed, so sending larger images only
+//! adds upload time and latency. This module provides the size and token arithmetic
+//! used across the SDK and, with the `image` feature enabled, helpers that downscale,
+//! re-encode and strip metadata from images before they are attached to a message.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[cfg(feature = "image")]
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use anthropic_ai_sdk::image::{ImageOptions, prepare_image};
+//! use anthropic_ai_sdk::types::message::{ContentBlock, Message, Role};
+//!
+//! let bytes = std::fs::read("screenshot.png")?;
+//! let prepared = prepare_image(&bytes, &ImageOptions::default())?;
+//! println!(
+//!     "{}x{} image, ~{} input tokens",
+//!     prepared.width,
+//!     prepared.height,
+//!     prepared.estimated_tokens()
+//! );
+//!
+//! let message = Message::new_blocks(
+//!     Role::User,
+//!     vec![prepared.into_content_block(), ContentBlock::text("What is shown here?")],
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+/// Recommended maximum length of the long edge of an image, in pixels
+pub const MAX_LONG_EDGE: u32 = 1568;
+
+/// Recommended maximum number of pixels in an image
+pub const MAX_PIXELS: u32 = 1_150_000;
+
+/// Number of pixels that roughly correspond to one input token
+pub const PIXELS_PER_TOKEN: u32 = 750;
+
+/// Estimates the number of input tokens an image of the given size will use
+///
+/// Uses the documented approximation `width * height / 750`, rounded up.
+pub fn estimate_image_tokens(width: u32, height: u32) -> u32 {
+    let pixels = u64::from(width) * u64::from(height);
+    pixels.div_ceil(u64::from(PIXELS_PER_TOKEN)) as u32
+}
+
+/// Computes the size an image should be scaled to so that it fits within the limits
+///
+/// The aspect ratio is preserved. Images that already fit are returned unchanged;
+/// images are never scaled up.
+///
+/// # Arguments
+///
+/// * `width` - Original width in pixels
+/// * `height` - Original height in pixels
+/// * `max_long_edge` - Maximum length of the longer side
+/// * `max_pixels` - Maximum total number of pixels
+pub fn fit_within(width: u32, height: u32, max_long_edge: u32, max_pixels: u32) -> (u32, u32) {
+    if width == 0 || height == 0 {
+        return (width, height);
+    }
+
+    let long_edge = width.max(height) as f64;
+    let pixels = width as f64 * height as f64;
+
+    let edge_scale = max_long_edge as f64 / long_edge;
+    let pixel_scale = (max_pixels as f64 / pixels).sqrt();
+    let scale = edge_scale.min(pixel_scale);
+
+    if scale >= 1.0 {
+        return (width, height);
+    }
+
+    let scaled_width = ((width as f64 * scale).floor() as u32).max(1);
+    let scaled_height = ((height as f64 * scale).floor() as u32).max(1);
+    (scaled_width, scaled_height)
+}
+
+/// Reads the pixel dimensions of an image from its header
+///
+/// Supports PNG, JPEG, GIF and WebP without decoding the image, so it is available
+/// without the `image` feature. Returns `None` if the format is not recognised or the
+/// header is truncated.
+pub fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+        return Some((width, height));
+    }
+
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        let width = u16::from_le_bytes(bytes.get(6..8)?.try_into().ok()?);
+        let height = u16::from_le_bytes(bytes.get(8..10)?.try_into().ok()?);
+        return Some((width.into(), height.into()));
+    }
+
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12)? == b"WEBP" {
+        return webp_dimensions(bytes);
+    }
+
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        return jpeg_dimensions(bytes);
+    }
+
+    None
+}
+
+fn webp_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let le24 = |at: usize| -> Option<u32> {
+        let b = bytes.get(at..at + 3)?;
+        Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16)
+    };
+
+    match bytes.get(12..16)? {
+        b"VP8 " => {
+            let width = u16::from_le_bytes(bytes.get(26..28)?.try_into().ok()?) & 0x3FFF;
+            let height = u16::from_le_bytes(bytes.get(28..30)?.try_into().ok()?) & 0x3FFF;
+            Some((width.into(), height.into()))
+        }
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+        _ => None,
+    }
+}
+
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut i = 2;
+    loop {
+        // Skip to the next marker, ignoring fill bytes
+        while *bytes.get(i)? != 0xFF {
+            i += 1;
+        }
+        while *bytes.get(i)? == 0xFF {
+            i += 1;
+        }
+        let marker = *bytes.get(i)?;
+        i += 1;
+
+        // Standalone markers carry no length
+        if matches!(marker, 0x01 | 0xD0..=0xD9) {
+            continue;
+        }
+
+        let length = usize::from(u16::from_be_bytes(bytes.get(i..i + 2)?.try_into().ok()?));
+        let is_start_of_frame =
+            matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_start_of_frame {
+            let height = u16::from_be_bytes(bytes.get(i + 3..i + 5)?.try_into().ok()?);
+            let width = u16::from_be_bytes(bytes.get(i + 5..i + 7)?.try_into().ok()?);
+            return Some((width.into(), height.into()));
+        }
+        i += length;
+    }
+}
+
+#[cfg(feature = "image")]
+pub use self::prepare::{ImageError, ImageOptions, PreparedImage, prepare_image};
+
+#[cfg(feature = "image")]
+mod prepare {
+    use super::{MAX_LONG_EDGE, MAX_PIXELS, estimate_image_tokens, fit_within};
+    use crate::types::message::ContentBlock;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::imageops::FilterType;
+    use image::{DynamicImage, GenericImageView, ImageDecoder, ImageFormat, ImageReader};
+    use std::io::Cursor;
+    use thiserror::Error;
+
+    /// Error types for image preprocessing
+    #[derive(Debug, Error)]
+    pub enum ImageError {
+        #[error("Unsupported image format")]
+        UnsupportedFormat,
+        #[error("Failed to decode image: {0}")]
+        Decode(String),
+        #[error("Failed to encode image: {0}")]
+        Encode(String),
+    }
+
+    /// Options controlling how images are prepared for upload
+    #[derive(Debug, Clone)]
+    pub struct ImageOptions {
+        /// Maximum length of the long edge, in pixels
+        pub max_long_edge: u32,
+        /// Maximum total number of pixels
+        pub max_pixels: u32,
+        /// Quality used when re-encoding as JPEG (1-100)
+        pub jpeg_quality: u8,
+    }
+
+    impl Default for ImageOptions {
+        fn default() -> Self {
+            Self {
+                max_long_edge: MAX_LONG_EDGE,
+                max_pixels: MAX_PIXELS,
+                jpeg_quality: 85,
+            }
+        }
+    }
+
+    impl ImageOptions {
+        /// Create a new ImageOptions with the recommended limits
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Set the maximum length of the long edge
+        pub fn max_long_edge(mut self, max_long_edge: u32) -> Self {
+            self.max_long_edge = max_long_edge.max(1);
+            self
+        }
+
+        /// Set the maximum total number of pixels
+        pub fn max_pixels(mut self, max_pixels: u32) -> Self {
+            self.max_pixels = max_pixels.max(1);
+            self
+        }
+
+        /// Set the JPEG quality (1-100)
+        pub fn jpeg_quality(mut self, jpeg_quality: u8) -> Self {
+            self.jpeg_quality = jpeg_quality.clamp(1, 100);
+            self
+        }
+    }
+
+    /// An image that has been resized and re-encoded for upload
+    #[derive(Debug, Clone)]
+    pub struct PreparedImage {
+        /// Media type of the encoded image (e.g. "image/jpeg")
+        pub media_type: String,
+        /// Encoded image bytes, without any metadata
+        pub data: Vec<u8>,
+        /// Width after resizing
+        pub width: u32,
+        /// Height after resizing
+        pub height: u32,
+        /// Width of the original image
+        pub original_width: u32,
+        /// Height of the original image
+        pub original_height: u32,
+    }
+
+    impl PreparedImage {
+        /// Estimated number of input tokens this image will use
+        pub fn estimated_tokens(&self) -> u32 {
+            estimate_image_tokens(self.width, self.height)
+        }
+
+        /// Whether the image was scaled down
+        pub fn was_resized(&self) -> bool {
+            self.width != self.original_width || self.height != self.original_height
+        }
+
+        /// Base64-encoded image data
+        pub fn to_base64(&self) -> String {
+            STANDARD.encode(&self.data)
+        }
+
+        /// Convert into an image content block
+        pub fn into_content_block(self) -> ContentBlock {
+            let data = self.to_base64();
+            ContentBlock::image("base64", self.media_type, data)
+        }
+    }
+
+    /// Decodes, downscales and re-encodes an image
+    ///
+    /// EXIF orientation is applied before resizing and all metadata is dropped by
+    /// re-encoding. Images with an alpha channel, and images that were PNG or GIF to
+    /// begin with (typically screenshots and diagrams), are encoded as PNG; everything
+    /// else is encoded as JPEG.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ImageError` if the format is not recognised or the image cannot be
+    /// decoded or encoded.
+    pub fn prepare_image(
+        bytes: &[u8],
+        options: &ImageOptions,
+    ) -> Result<PreparedImage, ImageError> {
+        let reader = ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(|e| ImageError::Decode(e.to_string()))?;
+        let source_format = reader.format().ok_or(ImageError::UnsupportedFormat)?;
+
+        let mut decoder = reader
+            .into_decoder()
+            .map_err(|e| ImageError::Decode(e.to_string()))?;
+        let orientation = decoder
+            .orientation()
+            .map_err(|e| ImageError::Decode(e.to_string()))?;
+        let mut image =
+            DynamicImage::from_decoder(decoder).map_err(|e| ImageError::Decode(e.to_string()))?;
+        image.apply_orientation(orientation);
+
+        let (original_width, original_height) = image.dimensions();
+        let (width, height) = fit_within(
+            original_width,
+            original_height,
+            options.max_long_edge,
+            options.max_pixels,
+        );
+        if (width, height) != (original_width, original_height) {
+            image = image.resize_exact(width, height, FilterType::Lanczos3);
+        }
+
+        let lossless = matches!(source_format, ImageFormat::Png | ImageFormat::Gif);
+        let mut data = Vec::new();
+        let media_type = if image.color().has_alpha() || lossless {
+            image
+                .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+                .map_err(|e| ImageError::Encode(e.to_string()))?;
+            "image/png"
+        } else {
+            let rgb = DynamicImage::ImageRgb8(image.to_rgb8());
+            JpegEncoder::new_with_quality(&mut data, options.jpeg_quality)
+                .encode_image(&rgb)
+                .map_err(|e| ImageError::Encode(e.to_string()))?;
+            "image/jpeg"
+        };
+
+        Ok(PreparedImage {
+            media_type: media_type.to_string(),
+            data,
+            width,
+            height,
+            original_width,
+            original_height,
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+        fn encode(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            image
+                .write_to(&mut Cursor::new(&mut bytes), format)
+                .unwrap();
+            bytes
+        }
+
+        #[test]
+        fn downscales_large_photos_to_jpeg() {
+            let photo =
+                DynamicImage::ImageRgb8(RgbImage::from_pixel(2400, 1200, Rgb([10, 20, 30])));
+            let prepared =
+                prepare_image(&encode(photo, ImageFormat::Jpeg), &ImageOptions::new()).unwrap();
+
+            assert_eq!(prepared.media_type, "image/jpeg");
+            assert!(prepared.was_resized());
+            assert!(prepared.width.max(prepared.height) <= MAX_LONG_EDGE);
+            assert!(prepared.width * prepared.height <= MAX_PIXELS);
+            assert_eq!(
+                (prepared.original_width, prepared.original_height),
+                (2400, 1200)
+            );
+        }
+
+        #[test]
+        fn keeps_transparency_as_png() {
+            let icon = DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 32, Rgba([0, 0, 0, 0])));
+            let prepared =
+                prepare_image(&encode(icon, ImageFormat::Png), &ImageOptions::new()).unwrap();
+
+            assert_eq!(prepared.media_type, "image/png");
+            assert!(!prepared.was_resized());
+            assert_eq!(prepared.estimated_tokens(), 3);
+        }
+
+        #[test]
+        fn rejects_unknown_data() {
+            let result = prepare_image(b"not an image", &ImageOptions::new());
+            assert!(matches!(result, Err(ImageError::UnsupportedFormat)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_rounds_up() {
+        assert_eq!(estimate_image_tokens(1000, 1000), 1334);
+        assert_eq!(estimate_image_tokens(750, 1), 1);
+        assert_eq!(estimate_image_tokens(751, 1), 2);
+    }
+
+    #[test]
+    fn reads_dimensions_from_headers() {
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        assert_eq!(dimensions(&png), Some((640, 480)));
+
+        let gif = b"GIF89a\x20\x03\x58\x02";
+        assert_eq!(dimensions(gif), Some((800, 600)));
+
+        let jpeg = [
+            0xFF, 0xD8, // SOI
+            0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, // APP0 with two bytes of payload
+            0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0xE0, 0x02, 0x80, // SOF0 480x640
+        ];
+        assert_eq!(dimensions(&jpeg), Some((640, 480)));
+
+        assert_eq!(dimensions(b"plain text"), None);
+        assert_eq!(dimensions(&[0xFF, 0xD8, 0xFF]), None);
+    }
+
+    #[test]
+    fn fit_within_preserves_small_images() {
+        assert_eq!(fit_within(800, 600, MAX_LONG_EDGE, MAX_PIXELS), (800, 600));
+    }
+
+    #[test]
+    fn fit_within_limits_long_edge_and_pixels() {
+        let (width, height) = fit_within(4000, 1000, MAX_LONG_EDGE, MAX_PIXELS);
+        assert_eq!(width, MAX_LONG_EDGE);
+        assert_eq!(height, 392);
+
+        let (width, height) = fit_within(2000, 2000, MAX_LONG_EDGE, MAX_PIXELS);
+        assert!(width * height <= MAX_PIXELS);
+        assert_eq!(width, height);
+    }
+}
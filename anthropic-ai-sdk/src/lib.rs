@@ -1,7 +1,18 @@
 pub mod admin_client;
+pub mod batch;
+pub mod catalog;
 pub mod client;
+pub mod context;
+pub mod cost;
+pub mod fallback;
 pub mod files;
+pub mod image;
 pub mod message_batches;
 pub mod messages;
 pub mod models;
+pub mod partial_json;
+pub mod sse;
+pub mod stream;
+pub mod tokens;
 pub mod types;
+pub mod usage;
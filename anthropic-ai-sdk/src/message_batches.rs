@@ -5,13 +5,15 @@
 
 use crate::client::AnthropicClient;
 use crate::types::message_batches::{
-    CancelMessageBatchParams, CancelResponse, CreateMessageBatchParams, DeleteMessageBatchParams,
-    DeleteResponse, ListMessageBatchesParams, ListMessageBatchesResponse, MessageBatch,
-    MessageBatchClient, MessageBatchError, RetrieveMessageBatchParams,
+    CancelMessageBatchParams, CreateMessageBatchParams, DeleteMessageBatchParams, DeleteResponse,
+    ListMessageBatchesParams, ListMessageBatchesResponse, MessageBatch, MessageBatchClient,
+    MessageBatchError, MessageBatchResult, RetrieveMessageBatchParams,
     RetrieveMessageBatchResponse, RetrieveMessageBatchResultsParams,
-    RetrieveMessageBatchResultsResponse,
 };
 use async_trait::async_trait;
+use futures_util::{Stream, TryStreamExt};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+use tokio_util::io::StreamReader;
 
 #[async_trait]
 impl MessageBatchClient for AnthropicClient {
@@ -32,6 +34,7 @@ impl MessageBatchClient for AnthropicClient {
     /// # Errors
     ///
     /// Returns a `MessageBatchError` if:
+    /// - A request sets `stream`
     /// - The request fails to send
     /// - The API returns an error response
     /// - The response cannot be parsed
@@ -40,9 +43,11 @@ impl MessageBatchClient for AnthropicClient {
     ///
     /// ```no_run
     /// use anthropic_ai_sdk::client::AnthropicClient;
+    /// use anthropic_ai_sdk::types::message::{
+    ///     CreateMessageParams, Message, RequiredMessageParams, Role,
+    /// };
     /// use anthropic_ai_sdk::types::message_batches::{
-    ///     CreateMessageBatchParams, Message, MessageBatchClient, MessageBatchError, MessageRequest,
-    ///     MessageRequestParams,
+    ///     CreateMessageBatchParams, MessageBatchClient, MessageBatchError, MessageRequest,
     /// };
     ///
     /// use tokio;
@@ -53,9 +58,12 @@ impl MessageBatchClient for AnthropicClient {
     ///     "2023-06-01",
     /// )?;
     ///
-    /// let messages = vec![Message::new("user", "Hello!")];
-    /// let request_params = MessageRequestParams::new("claude-3-haiku", messages, 100)
-    ///     .with_system("You are a helpful assistant");
+    /// let request_params = CreateMessageParams::new(RequiredMessageParams {
+    ///     model: "claude-3-5-haiku-latest".to_string(),
+    ///     messages: vec![Message::new_text(Role::User, "Hello!")],
+    ///     max_tokens: 100,
+    /// })
+    /// .with_system("You are a helpful assistant");
     /// let request = MessageRequest::new(request_params).with_custom_id("req1");
     /// let batch_params = CreateMessageBatchParams::new(vec![request]);
     /// let response = client.create_message_batch(&batch_params).await?;
@@ -68,6 +76,7 @@ impl MessageBatchClient for AnthropicClient {
         &'a self,
         body: &'a CreateMessageBatchParams,
     ) -> Result<MessageBatch, MessageBatchError> {
+        body.validate()?;
         self.post("/messages/batches", Some(body)).await
     }
 
@@ -151,41 +160,72 @@ impl MessageBatchClient for AnthropicClient {
 
     /// Retrieve message batch results
     ///
-    /// Retrieve message batch results by ID
+    /// Streams the results of an ended message batch from its `results_url`. The
+    /// results file is JSONL and is parsed line by line as it downloads, so large
+    /// batches are never held in memory at once. Results are not in request order; match
+    /// them to requests by `custom_id`.
     ///
     /// # Returns
     ///
-    /// Returns a message batch results
+    /// Returns a stream of results, one per request of the batch
     ///
     /// # Errors
     ///
     /// Returns a `MessageBatchError` if:
     /// - The request fails to send
     /// - The API returns an error response
-    /// - The response cannot be parsed
+    /// - The batch has not ended yet
+    ///
+    /// The stream yields an error if the download fails or a line cannot be parsed.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// use anthropic_ai_sdk::client::AnthropicClient;
-    /// use anthropic_ai_sdk::types::message_batches::{MessageBatch, MessageBatchClient, MessageBatchError, RetrieveMessageBatchResultsParams};
+    /// use anthropic_ai_sdk::types::message_batches::{
+    ///     BatchRequestResult, MessageBatchClient, MessageBatchError,
+    ///     RetrieveMessageBatchResultsParams,
+    /// };
+    /// use futures_util::StreamExt;
     ///
     /// async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// let client = AnthropicClient::new::<MessageBatchError>("your-api-key", "2023-06-01")?;
-    /// let results = client.retrieve_message_batch_results(&RetrieveMessageBatchResultsParams::new("msgbatch_batch_id")).await?;
-    /// println!("Results: {:?}", results);
+    /// let params = RetrieveMessageBatchResultsParams::new("msgbatch_batch_id");
+    /// let mut results = Box::pin(client.retrieve_message_batch_results(&params).await?);
+    /// while let Some(result) = results.next().await {
+    ///     let result = result?;
+    ///     match result.result {
+    ///         BatchRequestResult::Succeeded { message } => {
+    ///             println!("{}: {:?}", result.custom_id, message.content)
+    ///         }
+    ///         BatchRequestResult::Errored { error } => {
+    ///             println!("{}: {}", result.custom_id, error.error)
+    ///         }
+    ///         BatchRequestResult::Canceled | BatchRequestResult::Expired => {}
+    ///     }
+    /// }
     /// # Ok(())
     /// # }
     /// ```
     async fn retrieve_message_batch_results<'a>(
         &'a self,
         params: &'a RetrieveMessageBatchResultsParams,
-    ) -> Result<RetrieveMessageBatchResultsResponse, MessageBatchError> {
-        self.get::<RetrieveMessageBatchResultsResponse, RetrieveMessageBatchResultsParams, MessageBatchError>(
-            &format!("/messages/batches/{}/results", params.message_batch_id),
-            None,
-        )
-        .await
+    ) -> Result<
+        impl Stream<Item = Result<MessageBatchResult, MessageBatchError>> + Send + 'a,
+        MessageBatchError,
+    > {
+        let batch = self
+            .retrieve_message_batch(&RetrieveMessageBatchParams::new(
+                params.message_batch_id.as_str(),
+            ))
+            .await?;
+        let results_url = batch
+            .results_url
+            .ok_or(MessageBatchError::ResultsNotAvailable(batch.id))?;
+
+        let response = self.get_response::<MessageBatchError>(&results_url).await?;
+        let bytes = response.bytes_stream().map_err(std::io::Error::other);
+        Ok(parse_results(StreamReader::new(bytes)))
     }
 
     /// Cancel a message batch
@@ -194,7 +234,7 @@ impl MessageBatchClient for AnthropicClient {
     ///
     /// # Returns
     ///
-    /// Returns a cancel response
+    /// Returns the batch, which is `canceling` until its in-flight requests finish
     ///
     /// # Errors
     ///
@@ -211,18 +251,18 @@ impl MessageBatchClient for AnthropicClient {
     ///
     /// async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// let client = AnthropicClient::new::<MessageBatchError>("your-api-key", "2023-06-01")?;
-    /// let response = client.cancel_message_batch(&CancelMessageBatchParams::new("batch_id")).await?;
-    /// println!("Response: {:?}", response);
+    /// let batch = client.cancel_message_batch(&CancelMessageBatchParams::new("batch_id")).await?;
+    /// println!("Status: {:?}", batch.processing_status);
     /// # Ok(())
     /// # }
     /// ```
     async fn cancel_message_batch<'a>(
         &'a self,
         params: &'a CancelMessageBatchParams,
-    ) -> Result<CancelResponse, MessageBatchError> {
-        self.post::<CancelResponse, CancelMessageBatchParams, MessageBatchError>(
+    ) -> Result<MessageBatch, MessageBatchError> {
+        self.post::<MessageBatch, (), MessageBatchError>(
             &format!("/messages/batches/{}/cancel", params.message_batch_id),
-            Some(params),
+            None,
         )
         .await
     }
@@ -266,3 +306,281 @@ impl MessageBatchClient for AnthropicClient {
         .await
     }
 }
+
+/// Parse a message batch results file
+///
+/// Reads JSONL results, such as a results file saved to disk, line by line. Blank lines
+/// are skipped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anthropic_ai_sdk::message_batches::parse_results;
+/// use futures_util::StreamExt;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = tokio::fs::File::open("results.jsonl").await?;
+/// let mut results = Box::pin(parse_results(file));
+/// while let Some(result) = results.next().await {
+///     println!("{}", result?.custom_id);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn parse_results<R>(
+    reader: R,
+) -> impl Stream<Item = Result<MessageBatchResult, MessageBatchError>>
+where
+    R: AsyncRead,
+{
+    let lines = Box::pin(BufReader::new(reader)).lines();
+    futures_util::stream::unfold((lines, 0usize), |(mut lines, mut number)| async move {
+        loop {
+            number += 1;
+            let line = match lines.next_line().await {
+                Ok(Some(line)) if line.trim().is_empty() => continue,
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(e) => return Some((Err(e.into()), (lines, number))),
+            };
+            let result = serde_json::from_str(&line).map_err(|source| MessageBatchError::Parse {
+                line: number,
+                source,
+            });
+            return Some((result, (lines, number)));
+        }
+    })
+}
+
+/// Write message batch results as JSONL
+///
+/// Results are written as they arrive, so a large batch is never held in memory.
+///
+/// # Returns
+///
+/// Returns the number of results written.
+///
+/// # Errors
+///
+/// Returns the first error of `results`, or a `MessageBatchError` if writing fails.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anthropic_ai_sdk::client::AnthropicClient;
+/// use anthropic_ai_sdk::message_batches::write_results_jsonl;
+/// use anthropic_ai_sdk::types::message_batches::{
+///     MessageBatchClient, MessageBatchError, RetrieveMessageBatchResultsParams,
+/// };
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = AnthropicClient::new::<MessageBatchError>("your-api-key", "2023-06-01")?;
+/// let params = RetrieveMessageBatchResultsParams::new("msgbatch_batch_id");
+/// let results = client.retrieve_message_batch_results(&params).await?;
+///
+/// let file = tokio::fs::File::create("results.jsonl").await?;
+/// let written = write_results_jsonl(results, file).await?;
+/// println!("Wrote {} results", written);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn write_results_jsonl<S, W>(results: S, writer: W) -> Result<usize, MessageBatchError>
+where
+    S: Stream<Item = Result<MessageBatchResult, MessageBatchError>>,
+    W: AsyncWrite,
+{
+    let mut results = Box::pin(results);
+    let mut writer = Box::pin(BufWriter::new(writer));
+    let mut written = 0;
+    while let Some(result) = results.try_next().await? {
+        let mut line = serde_json::to_vec(&result).map_err(MessageBatchError::Serialize)?;
+        line.push(b'\n');
+        writer.write_all(&line).await?;
+        written += 1;
+    }
+    writer.flush().await?;
+    Ok(written)
+}
+
+/// Columns written by [`write_results_csv`]
+#[cfg(feature = "csv")]
+pub const CSV_COLUMNS: [&str; 8] = [
+    "custom_id",
+    "status",
+    "text",
+    "stop_reason",
+    "input_tokens",
+    "output_tokens",
+    "error_type",
+    "error_message",
+];
+
+/// Write message batch results as CSV, one row per result
+///
+/// The columns are [`CSV_COLUMNS`]: the text and stop reason are set for succeeded
+/// results, and the error type and message for errored ones. Results are written as
+/// they arrive, so a large batch is never held in memory.
+///
+/// # Returns
+///
+/// Returns the number of results written, not counting the header.
+///
+/// # Errors
+///
+/// Returns the first error of `results`, or a `MessageBatchError` if writing fails.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anthropic_ai_sdk::message_batches::{parse_results, write_results_csv};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let results = parse_results(tokio::fs::File::open("results.jsonl").await?);
+/// let file = tokio::fs::File::create("results.csv").await?;
+/// write_results_csv(results, file).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "csv")]
+pub async fn write_results_csv<S, W>(results: S, writer: W) -> Result<usize, MessageBatchError>
+where
+    S: Stream<Item = Result<MessageBatchResult, MessageBatchError>>,
+    W: AsyncWrite,
+{
+    let mut results = Box::pin(results);
+    let mut writer = Box::pin(BufWriter::new(writer));
+    let mut line = Vec::new();
+    write_csv_record(&mut line, CSV_COLUMNS)?;
+    writer.write_all(&line).await?;
+
+    let mut written = 0;
+    while let Some(result) = results.try_next().await? {
+        line.clear();
+        write_csv_record(&mut line, csv_row(&result))?;
+        writer.write_all(&line).await?;
+        written += 1;
+    }
+    writer.flush().await?;
+    Ok(written)
+}
+
+/// Encode one CSV record into `line`
+#[cfg(feature = "csv")]
+fn write_csv_record<I>(line: &mut Vec<u8>, record: I) -> Result<(), MessageBatchError>
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let mut writer = csv::Writer::from_writer(line);
+    writer.write_record(record).map_err(std::io::Error::from)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+fn csv_row(result: &MessageBatchResult) -> [String; 8] {
+    let message = result.result.message();
+    let error = result.result.error();
+    [
+        result.custom_id.clone(),
+        result.result.status().to_string(),
+        message.map(|m| m.text()).unwrap_or_default(),
+        message
+            .and_then(|m| m.stop_reason)
+            .map(|reason| reason.as_str().to_string())
+            .unwrap_or_default(),
+        message
+            .map(|m| m.usage.input_tokens.to_string())
+            .unwrap_or_default(),
+        message
+            .map(|m| m.usage.output_tokens.to_string())
+            .unwrap_or_default(),
+        error.map(|e| e.kind.to_string()).unwrap_or_default(),
+        error.map(|e| e.message.clone()).unwrap_or_default(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::error::ApiErrorKind;
+    use crate::types::message_batches::BatchRequestResult;
+    use futures_util::StreamExt;
+
+    const RESULTS: &str = r#"{"custom_id":"a","result":{"type":"succeeded","message":{"id":"msg_1","type":"message","role":"assistant","model":"claude-3-5-haiku-20241022","content":[{"type":"text","text":"Hi"}],"stop_reason":"end_turn","stop_sequence":null,"usage":{"input_tokens":10,"output_tokens":2}}}}
+{"custom_id":"b","result":{"type":"errored","error":{"type":"error","error":{"type":"invalid_request_error","message":"Bad request"}}}}
+
+{"custom_id":"c","result":{"type":"canceled"}}
+{"custom_id":"d","result":{"type":"expired"}}
+not json
+"#;
+
+    #[tokio::test]
+    async fn parses_results_of_every_type() {
+        let results: Vec<_> = parse_results(RESULTS.as_bytes()).collect().await;
+        assert_eq!(results.len(), 5);
+
+        let succeeded = results[0].as_ref().unwrap();
+        assert_eq!(succeeded.custom_id, "a");
+        assert_eq!(succeeded.result.message().unwrap().usage.output_tokens, 2);
+
+        let errored = &results[1].as_ref().unwrap().result;
+        assert_eq!(errored.error().unwrap().kind, ApiErrorKind::InvalidRequest);
+        assert!(matches!(
+            results[2].as_ref().unwrap().result,
+            BatchRequestResult::Canceled
+        ));
+        assert!(matches!(
+            results[3].as_ref().unwrap().result,
+            BatchRequestResult::Expired
+        ));
+
+        assert!(matches!(
+            results[4],
+            Err(MessageBatchError::Parse { line: 6, .. })
+        ));
+    }
+
+    fn valid_results() -> impl Stream<Item = Result<MessageBatchResult, MessageBatchError>> {
+        parse_results(RESULTS.as_bytes()).take(4)
+    }
+
+    #[tokio::test]
+    async fn writes_results_as_jsonl() {
+        let mut out = Vec::new();
+        assert_eq!(
+            write_results_jsonl(valid_results(), &mut out)
+                .await
+                .unwrap(),
+            4
+        );
+        let parsed: Vec<_> = parse_results(out.as_slice()).collect().await;
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(parsed[3].as_ref().unwrap().custom_id, "d");
+
+        let failing = parse_results(RESULTS.as_bytes());
+        assert!(write_results_jsonl(failing, Vec::new()).await.is_err());
+    }
+
+    #[cfg(feature = "csv")]
+    #[tokio::test]
+    async fn writes_results_as_csv() {
+        let mut out = Vec::new();
+        assert_eq!(
+            write_results_csv(valid_results(), &mut out).await.unwrap(),
+            4
+        );
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "custom_id,status,text,stop_reason,input_tokens,output_tokens,error_type,error_message",
+                "a,succeeded,Hi,end_turn,10,2,,",
+                "b,errored,,,,,invalid_request_error,Bad request",
+                "c,canceled,,,,,,",
+                "d,expired,,,,,,",
+            ]
+        );
+    }
+}
@@ -5,16 +5,28 @@
 
 use eventsource_stream::Eventsource;
 use futures_util::Stream;
+use futures_util::future::Either;
+use futures_util::stream::BoxStream;
 use reqwest::header::HeaderValue;
+use std::borrow::Borrow;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::Instant;
 
 use crate::client::AnthropicClient;
+use crate::fallback::{FallbackPolicy, FallbackResponse};
+use crate::stream::{StreamSplicer, Watchdog, text_prefill};
 use crate::types::message::{
-    CountMessageTokensParams, CountMessageTokensResponse, CreateMessageParams,
-    CreateMessageResponse, MessageClient, MessageError, StreamEvent,
+    ContentBlock, ContinuationOptions, CountMessageTokensParams, CountMessageTokensResponse,
+    CreateMessageParams, CreateMessageResponse, Message, MessageClient, MessageContent,
+    MessageError, Role, StopReason, StreamEvent, StreamUsage, Usage,
 };
 use async_trait::async_trait;
 use futures_util::StreamExt;
 
+/// Delay before the first attempt to resume a stream, doubled for every further attempt
+const RESUME_BACKOFF: Duration = Duration::from_millis(500);
+
 #[async_trait]
 impl MessageClient for AnthropicClient {
     /// Creates a message using the specified model
@@ -31,7 +43,9 @@ impl MessageClient for AnthropicClient {
     /// # Returns
     ///
     /// Returns the model's response on success, including the generated message
-    /// and any additional metadata.
+    /// and any additional metadata. If the client has a
+    /// [fallback policy](crate::client::AnthropicClientBuilder::with_fallback_policy),
+    /// `model` in the response is the model that served it.
     ///
     /// # Errors
     ///
@@ -67,13 +81,21 @@ impl MessageClient for AnthropicClient {
         &'a self,
         body: Option<&'a CreateMessageParams>,
     ) -> Result<CreateMessageResponse, MessageError> {
-        self.post("/messages", body).await
+        match (body, self.get_fallback_policy()) {
+            (Some(body), Some(policy)) => self
+                .create_message_with_fallback(body, policy)
+                .await
+                .map(|served| served.response),
+            _ => self.post("/messages", body).await,
+        }
     }
 
     /// Counts the number of tokens in a message
     ///
     /// Returns a count of the tokens that would be used by a message with the
     /// given parameters. This can be used to ensure messages stay within token limits.
+    /// Convert a `CreateMessageParams` with `From` to count exactly what
+    /// `create_message` would send, including the system prompt, tools and thinking.
     ///
     /// # Arguments
     ///
@@ -94,9 +116,22 @@ impl MessageClient for AnthropicClient {
     ///
     /// ```no_run
     /// use anthropic_ai_sdk::client::AnthropicClient;
-    /// use anthropic_ai_sdk::types::message::{MessageClient, MessageError};
+    /// use anthropic_ai_sdk::types::message::{
+    ///     CountMessageTokensParams, CreateMessageParams, MessageClient, MessageError,
+    /// };
     /// use tokio;
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = AnthropicClient::new::<MessageError>(
+    ///     "your-api-key",
+    ///     "2023-06-01",
+    /// )?;
+    ///
+    /// let params = CreateMessageParams::default();
+    /// let count = client
+    ///     .count_tokens(Some(&CountMessageTokensParams::from(&params)))
+    ///     .await?;
+    ///
+    /// println!("Input tokens: {}", count.input_tokens);
     /// # Ok(())
     /// # }
     /// ```
@@ -116,23 +151,281 @@ impl MessageClient for AnthropicClient {
     ///
     /// # Returns
     ///
-    /// Returns a stream of events on success.
+    /// Returns a stream of events on success. `error` events sent by the API are
+    /// yielded as [`MessageError::Api`] errors, and the idle timeout and deadline set on
+    /// the client end the stream with [`MessageError::IdleTimeout`] or
+    /// [`MessageError::DeadlineExceeded`]. Dropping the stream aborts the request.
+    ///
+    /// When stream resumption is enabled with
+    /// [`with_stream_resume`](crate::client::AnthropicClientBuilder::with_stream_resume),
+    /// a stream that fails with a retryable error is continued by a new request that
+    /// prefills the text received so far, and the continuation is spliced into the same
+    /// stream. Usage in the final `message_delta` only covers the last request. A stream
+    /// whose content includes tool use or thinking blocks is not resumed, because those
+    /// cannot be prefilled.
+    ///
+    /// If the client has a
+    /// [fallback policy](crate::client::AnthropicClientBuilder::with_fallback_policy),
+    /// a request that fails before its first event arrives is sent to the next model.
     ///
     /// # Errors
     ///
     /// Returns a `MessageError` if:
     /// - The request fails to send
+    /// - The API rejects the request
+    /// - No response arrives within the idle timeout or deadline
     async fn create_message_streaming<'a>(
         &'a self,
         body: &'a CreateMessageParams,
     ) -> Result<impl Stream<Item = Result<StreamEvent, MessageError>> + 'a, MessageError> {
-        // Ensure that stream parameter is set to true
-        if body.stream.is_none() || !body.stream.unwrap() {
-            return Err(MessageError::ApiError(
-                "Stream parameter must be set to true for streaming".to_string(),
-            ));
+        message_stream(self, body).await
+    }
+}
+
+impl AnthropicClient {
+    /// Creates a message with streaming enabled, returning a stream that owns its data
+    ///
+    /// Behaves like [`MessageClient::create_message_streaming`], but the stream holds a
+    /// clone of the client and the parameters instead of borrowing them, so it can be
+    /// moved to another task or [broadcast](crate::stream::MessageStreamExt::broadcast)
+    /// to several consumers.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageError` if:
+    /// - The request fails to send
+    /// - The API rejects the request
+    /// - No response arrives within the idle timeout or deadline
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anthropic_ai_sdk::client::AnthropicClient;
+    /// use anthropic_ai_sdk::stream::MessageStreamExt;
+    /// use anthropic_ai_sdk::types::message::{CreateMessageParams, MessageError};
+    ///
+    /// # async fn example(params: CreateMessageParams) -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+    ///
+    /// let stream = client.create_message_streaming_owned(params).await?;
+    /// let broadcast = stream.broadcast(64);
+    /// let ui = broadcast.subscribe_from_start();
+    /// let storage = broadcast.subscribe_from_start();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_message_streaming_owned(
+        &self,
+        body: CreateMessageParams,
+    ) -> Result<impl Stream<Item = Result<StreamEvent, MessageError>> + Send + 'static, MessageError>
+    {
+        message_stream(self.clone(), body).await
+    }
+
+    /// Creates a message, falling back to other models when the requested one fails
+    ///
+    /// The request is sent to `body.model` first, then to the models of `policy` in
+    /// order while it fails with one of the policy's trigger errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - Parameters for creating the message
+    /// * `policy` - Models to fall back to and the errors that trigger a fallback
+    ///
+    /// # Returns
+    ///
+    /// Returns the response together with the model that served it and the errors of
+    /// the models that failed before.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageError` if a request fails with an error that does not trigger a
+    /// fallback, or if the last model fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anthropic_ai_sdk::client::AnthropicClient;
+    /// use anthropic_ai_sdk::fallback::FallbackPolicy;
+    /// use anthropic_ai_sdk::types::error::ApiErrorKind;
+    /// use anthropic_ai_sdk::types::message::{CreateMessageParams, MessageError};
+    ///
+    /// # async fn example(params: CreateMessageParams) -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+    ///
+    /// let policy = FallbackPolicy::new(["claude-sonnet-4-5", "claude-haiku-4-5"])
+    ///     .with_trigger(ApiErrorKind::RateLimit);
+    /// let served = client.create_message_with_fallback(&params, &policy).await?;
+    ///
+    /// if served.fell_back() {
+    ///     println!("Served by {}", served.model);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_message_with_fallback(
+        &self,
+        body: &CreateMessageParams,
+        policy: &FallbackPolicy,
+    ) -> Result<FallbackResponse<CreateMessageResponse>, MessageError> {
+        policy
+            .run(body, |params| async move {
+                self.post("/messages", Some(&params)).await
+            })
+            .await
+    }
+
+    /// Creates a message with streaming enabled, falling back to other models when the
+    /// requested one fails before the first event arrives
+    ///
+    /// Like [`create_message_streaming_owned`](Self::create_message_streaming_owned),
+    /// the stream owns its data. Once the first event has arrived, the stream stays on
+    /// its model; later errors are yielded by the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageError` if:
+    /// - `body.stream` is not set to true
+    /// - A request fails with an error that does not trigger a fallback
+    /// - The last model fails
+    pub async fn create_message_streaming_with_fallback(
+        &self,
+        body: CreateMessageParams,
+        policy: &FallbackPolicy,
+    ) -> Result<
+        FallbackResponse<impl Stream<Item = Result<StreamEvent, MessageError>> + Send + 'static>,
+        MessageError,
+    > {
+        message_stream_with(self.clone(), body, Some(policy.clone())).await
+    }
+
+    /// Creates a message, continuing it while it stops at `max_tokens`
+    ///
+    /// When a response stops with [`StopReason::MaxTokens`], its text is sent back as a
+    /// prefilled assistant turn and the continuation is appended to the response, until
+    /// the message completes or the limits in `options` are reached. Each request asks
+    /// for at most `body.max_tokens` tokens, lowered to stay within
+    /// [`max_total_tokens`](ContinuationOptions::max_total_tokens).
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - Parameters for creating the message
+    /// * `options` - Maximum number of requests and output tokens
+    ///
+    /// # Returns
+    ///
+    /// Returns the combined response. Text continuing the last text block is merged
+    /// into it, and `usage` is the sum over all requests. The stop reason is the one of
+    /// the last request, so it is still `max_tokens` if a limit was reached. A response
+    /// that contains tool use or thinking blocks is not continued, because those cannot
+    /// be prefilled.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageError` if any of the requests fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anthropic_ai_sdk::client::AnthropicClient;
+    /// use anthropic_ai_sdk::types::message::{
+    ///     ContinuationOptions, CreateMessageParams, MessageError,
+    /// };
+    ///
+    /// # async fn example(params: CreateMessageParams) -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+    ///
+    /// let options = ContinuationOptions::new()
+    ///     .with_max_rounds(5)
+    ///     .with_max_total_tokens(16_000);
+    /// let response = client
+    ///     .create_message_until_complete(&params, options)
+    ///     .await?;
+    ///
+    /// println!("Output tokens: {}", response.usage.output_tokens);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_message_until_complete(
+        &self,
+        body: &CreateMessageParams,
+        options: ContinuationOptions,
+    ) -> Result<CreateMessageResponse, MessageError> {
+        let first = first_params(body, &options);
+        let mut response = self.create_message(Some(&first)).await?;
+        let mut rounds = 1;
+
+        while response.stop_reason == Some(StopReason::MaxTokens) {
+            let Some(max_tokens) =
+                options.next_max_tokens(body.max_tokens, rounds, response.usage.output_tokens)
+            else {
+                break;
+            };
+            let Some(prefill) = text_prefill(&response.content) else {
+                break;
+            };
+
+            let params = continuation_params(body, prefill, max_tokens);
+            let continuation = self.create_message(Some(&params)).await?;
+            append_continuation(&mut response, continuation);
+            rounds += 1;
         }
 
+        Ok(response)
+    }
+
+    /// Creates a message with streaming enabled, continuing it while it stops at
+    /// `max_tokens`
+    ///
+    /// The streaming equivalent of
+    /// [`create_message_until_complete`](Self::create_message_until_complete). The
+    /// events of every continuation are spliced into one stream: the `message_delta`
+    /// and `message_stop` of intermediate requests and the `message_start` of
+    /// continuations are dropped, text continuing the last text block is streamed as
+    /// deltas of that block, and the final `message_delta` reports the usage summed over
+    /// all requests. Like [`create_message_streaming_owned`](Self::create_message_streaming_owned),
+    /// the stream owns its data.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageError` if:
+    /// - `body.stream` is not set to true
+    /// - The first request fails to send or the API rejects it
+    ///
+    /// Failed continuation requests are yielded as errors by the stream.
+    pub async fn create_message_streaming_until_complete(
+        &self,
+        body: CreateMessageParams,
+        options: ContinuationOptions,
+    ) -> Result<impl Stream<Item = Result<StreamEvent, MessageError>> + Send + 'static, MessageError>
+    {
+        let first = first_params(&body, &options);
+        let events = message_stream(self.clone(), first).await?.boxed();
+
+        let continued = ContinuedStream {
+            client: self.clone(),
+            body,
+            options,
+            events: Some(events),
+            splicer: StreamSplicer::default(),
+            pending: VecDeque::new(),
+            held_stop: None,
+            round_usage: Usage::default(),
+            usage: Usage::default(),
+            rounds: 1,
+        };
+        Ok(Box::pin(futures_util::stream::unfold(
+            continued,
+            ContinuedStream::next,
+        )))
+    }
+
+    /// Sends a streaming request and maps its SSE events to `StreamEvent`s
+    async fn open_message_stream(
+        &self,
+        body: &CreateMessageParams,
+        started: Instant,
+    ) -> Result<BoxStream<'static, Result<StreamEvent, MessageError>>, MessageError> {
         let url = format!("{}/messages", self.get_api_base_url());
 
         let client = &self.get_client();
@@ -148,16 +441,20 @@ impl MessageClient for AnthropicClient {
             )
             .json(body);
 
-        let response = request
-            .send()
-            .await
-            .map_err(|e| MessageError::RequestFailed(e.to_string()))?;
+        let timeouts = *self.get_stream_timeouts();
+        let response = match timeouts.first_expiry(started) {
+            Some((limit, error)) => tokio::time::timeout(limit, request.send())
+                .await
+                .map_err(|_| error)?,
+            None => request.send().await,
+        }
+        .map_err(|e| MessageError::RequestFailed(e.to_string()))?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.map_err(|e| {
                 MessageError::RequestFailed(format!("Failed to read error response: {}", e))
             })?;
-            return Err(MessageError::ApiError(error_text));
+            return Err(MessageError::from(error_text));
         }
 
         // Get the bytes stream and convert it to EventSource stream
@@ -165,7 +462,7 @@ impl MessageClient for AnthropicClient {
         let event_stream = bytes_stream.eventsource();
 
         // Map SSE events to our StreamEvent type
-        Ok(event_stream.map(|event_result| {
+        let events = event_stream.map(|event_result| {
             event_result
                 .map_err(|e| MessageError::RequestFailed(e.to_string()))
                 .and_then(|event| {
@@ -176,6 +473,517 @@ impl MessageClient for AnthropicClient {
                         ))
                     })
                 })
-        }))
+                .and_then(|event| match event {
+                    StreamEvent::Error { error } => Err(error.into()),
+                    event => Ok(event),
+                })
+        });
+
+        Ok(Watchdog::started_at(events, timeouts, started).boxed())
+    }
+
+    /// Opens a message stream and waits for its first event, so that a request that
+    /// fails with an error triggering a fallback can be sent to the next model
+    async fn open_message_stream_with_fallback(
+        &self,
+        body: &CreateMessageParams,
+        started: Instant,
+        policy: &FallbackPolicy,
+    ) -> Result<BoxStream<'static, Result<StreamEvent, MessageError>>, MessageError> {
+        let mut events = self.open_message_stream(body, started).await?;
+        match events.next().await {
+            Some(Err(error)) if policy.should_fall_back(&error) => Err(error),
+            Some(first) => Ok(futures_util::stream::once(async { first })
+                .chain(events)
+                .boxed()),
+            None => Ok(events),
+        }
+    }
+}
+
+/// Opens a message stream, falling back to other models and resuming it after failures
+/// as configured on the client
+async fn message_stream<C, B>(
+    client: C,
+    body: B,
+) -> Result<impl Stream<Item = Result<StreamEvent, MessageError>> + Send, MessageError>
+where
+    C: Borrow<AnthropicClient> + Send + Sync,
+    B: Borrow<CreateMessageParams> + Send + Sync,
+{
+    let policy = client.borrow().get_fallback_policy().cloned();
+    let served = message_stream_with(client, body, policy).await?;
+    Ok(served.response)
+}
+
+/// Opens a message stream, falling back to other models according to `policy`
+async fn message_stream_with<C, B>(
+    client: C,
+    body: B,
+    policy: Option<FallbackPolicy>,
+) -> Result<
+    FallbackResponse<impl Stream<Item = Result<StreamEvent, MessageError>> + Send>,
+    MessageError,
+>
+where
+    C: Borrow<AnthropicClient> + Send + Sync,
+    B: Borrow<CreateMessageParams> + Send + Sync,
+{
+    // Ensure that stream parameter is set to true
+    if body.borrow().stream != Some(true) {
+        return Err(MessageError::ApiError(
+            "Stream parameter must be set to true for streaming".to_string(),
+        ));
+    }
+
+    let started = Instant::now();
+    let (events, served) = match &policy {
+        Some(policy) => {
+            let opener = client.borrow();
+            let served = policy
+                .run(body.borrow(), |params| async move {
+                    let events = opener
+                        .open_message_stream_with_fallback(&params, started, policy)
+                        .await?;
+                    Ok((events, params))
+                })
+                .await?;
+            let (events, params) = served.response;
+            let served = FallbackResponse {
+                response: (params.model != body.borrow().model).then_some(params),
+                model: served.model,
+                failures: served.failures,
+            };
+            (events, served)
+        }
+        None => {
+            let events = client
+                .borrow()
+                .open_message_stream(body.borrow(), started)
+                .await?;
+            let served = FallbackResponse {
+                response: None,
+                model: body.borrow().model.clone(),
+                failures: Vec::new(),
+            };
+            (events, served)
+        }
+    };
+
+    let attempts = client.borrow().get_stream_resume_attempts();
+    if attempts == 0 {
+        return Ok(FallbackResponse {
+            response: Either::Left(events),
+            model: served.model,
+            failures: served.failures,
+        });
+    }
+
+    let resume = ResumableStream {
+        client,
+        body,
+        fallback_params: served.response,
+        events: Some(events),
+        splicer: StreamSplicer::default(),
+        started,
+        attempts_left: attempts,
+        resumes: 0,
+    };
+    let resumable = futures_util::stream::unfold(resume, ResumableStream::next);
+    Ok(FallbackResponse {
+        response: Either::Right(Box::pin(resumable)),
+        model: served.model,
+        failures: served.failures,
+    })
+}
+
+/// Stream that resumes after a retryable failure by prefilling the text received so far
+struct ResumableStream<C, B> {
+    client: C,
+    body: B,
+    /// Parameters sent to the fallback model that served the stream
+    fallback_params: Option<CreateMessageParams>,
+    events: Option<BoxStream<'static, Result<StreamEvent, MessageError>>>,
+    splicer: StreamSplicer,
+    started: Instant,
+    attempts_left: u32,
+    resumes: u32,
+}
+
+impl<C, B> ResumableStream<C, B>
+where
+    C: Borrow<AnthropicClient>,
+    B: Borrow<CreateMessageParams>,
+{
+    async fn next(mut self) -> Option<(Result<StreamEvent, MessageError>, Self)> {
+        loop {
+            let events = self.events.as_mut()?;
+            let error = match events.next().await {
+                Some(Ok(event)) => match self.splicer.push(event) {
+                    Ok(Some(event)) => return Some((Ok(event), self)),
+                    Ok(None) => continue,
+                    Err(error) => error,
+                },
+                Some(Err(error)) => error,
+                None if self.splicer.is_finished() => return None,
+                None => MessageError::RequestFailed("Stream ended before message_stop".into()),
+            };
+
+            // Drop the failed request before sending the next one
+            self.events = None;
+            if let Err(error) = self.resume(error).await {
+                return Some((Err(error), self));
+            }
+        }
+    }
+
+    /// Sends a continuation request, or returns the error if the stream cannot resume
+    async fn resume(&mut self, mut error: MessageError) -> Result<(), MessageError> {
+        loop {
+            let resumable = self.attempts_left > 0
+                && error.is_retryable()
+                && !matches!(error, MessageError::DeadlineExceeded(_));
+            // Tool use and thinking blocks cannot be prefilled
+            let prefill = match self.splicer.prefill() {
+                Some(prefill) if resumable => prefill,
+                _ => return Err(error),
+            };
+
+            self.attempts_left -= 1;
+            self.resumes += 1;
+            tracing::warn!(
+                "Resuming stream after error (attempt {}): {}",
+                self.resumes,
+                error
+            );
+            tokio::time::sleep(RESUME_BACKOFF * 2u32.pow(self.resumes.min(6) - 1)).await;
+
+            let body = match &self.fallback_params {
+                Some(params) => params,
+                None => self.body.borrow(),
+            };
+            let Some(params) = resume_params(body, prefill, self.splicer.output_tokens()) else {
+                return Err(error);
+            };
+            match self
+                .client
+                .borrow()
+                .open_message_stream(&params, self.started)
+                .await
+            {
+                Ok(events) => {
+                    self.events = Some(events);
+                    self.splicer.resume();
+                    return Ok(());
+                }
+                Err(e) => error = e,
+            }
+        }
+    }
+}
+
+/// Parameters of the first request, with `max_tokens` lowered to the token cap
+fn first_params(body: &CreateMessageParams, options: &ContinuationOptions) -> CreateMessageParams {
+    let mut params = body.clone();
+    if let Some(max_tokens) = options.next_max_tokens(body.max_tokens, 0, 0) {
+        params.max_tokens = max_tokens;
+    }
+    params
+}
+
+/// Parameters of a request continuing `body` after the assistant generated `prefill`
+fn continuation_params(
+    body: &CreateMessageParams,
+    prefill: String,
+    max_tokens: u32,
+) -> CreateMessageParams {
+    let mut params = prefilled_params(body, prefill);
+    params.max_tokens = max_tokens;
+    params
+}
+
+/// Parameters of a request resuming `body` after a broken stream generated `prefill`
+/// in `streamed` output tokens
+///
+/// Returns `None` if the stream used up `max_tokens`.
+fn resume_params(
+    body: &CreateMessageParams,
+    prefill: String,
+    streamed: u32,
+) -> Option<CreateMessageParams> {
+    let max_tokens = body.max_tokens.checked_sub(streamed).filter(|&t| t > 0)?;
+    Some(continuation_params(body, prefill, max_tokens))
+}
+
+/// Parameters of `body` with the generated `prefill` as the assistant turn
+///
+/// The API rejects consecutive assistant turns, so when `body` already ends with an
+/// assistant prefill the generated text, which does not repeat it, is appended to it.
+fn prefilled_params(body: &CreateMessageParams, prefill: String) -> CreateMessageParams {
+    let mut params = body.clone();
+    if prefill.is_empty() {
+        return params;
+    }
+    match params.messages.last_mut() {
+        Some(Message {
+            role: Role::Assistant,
+            content,
+        }) => match content {
+            MessageContent::Text { content } => content.push_str(&prefill),
+            MessageContent::Blocks { content } => match content.last_mut() {
+                Some(ContentBlock::Text { text }) => text.push_str(&prefill),
+                _ => content.push(ContentBlock::text(prefill)),
+            },
+        },
+        _ => params
+            .messages
+            .push(Message::new_text(Role::Assistant, prefill)),
+    }
+    params
+}
+
+/// Append the response to a continuation request to the response it continues
+fn append_continuation(response: &mut CreateMessageResponse, continuation: CreateMessageResponse) {
+    let mut blocks = continuation.content.into_iter();
+    if let Some(ContentBlock::Text { text }) = response.content.last_mut() {
+        if let Some(ContentBlock::Text { text: more }) = blocks.as_slice().first() {
+            // The prefill was sent without its trailing whitespace
+            let more = if text.ends_with(char::is_whitespace) {
+                more.trim_start()
+            } else {
+                more
+            };
+            text.push_str(more);
+            blocks.next();
+        }
+    }
+    response.content.extend(blocks);
+    response.stop_reason = continuation.stop_reason;
+    response.stop_sequence = continuation.stop_sequence;
+    response.usage += &continuation.usage;
+}
+
+/// Stream that continues a message with further requests while it stops at `max_tokens`
+struct ContinuedStream {
+    client: AnthropicClient,
+    body: CreateMessageParams,
+    options: ContinuationOptions,
+    events: Option<BoxStream<'static, Result<StreamEvent, MessageError>>>,
+    splicer: StreamSplicer,
+    /// Events ready to be yielded
+    pending: VecDeque<StreamEvent>,
+    /// `content_block_stop` held back until it is known whether the block continues
+    held_stop: Option<StreamEvent>,
+    /// Usage of the current request
+    round_usage: Usage,
+    /// Usage of the finished requests
+    usage: Usage,
+    rounds: u32,
+}
+
+impl ContinuedStream {
+    async fn next(mut self) -> Option<(Result<StreamEvent, MessageError>, Self)> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some((Ok(event), self));
+            }
+            let events = self.events.as_mut()?;
+            let result = match events.next().await {
+                Some(Ok(event)) => self.handle(event).await,
+                Some(Err(error)) => Err(error),
+                None => {
+                    self.events = None;
+                    self.flush_held()
+                }
+            };
+            if let Err(error) = result {
+                self.events = None;
+                return Some((Err(error), self));
+            }
+        }
+    }
+
+    async fn handle(&mut self, event: StreamEvent) -> Result<(), MessageError> {
+        match event {
+            StreamEvent::MessageStart { ref message } => {
+                self.round_usage = message.usage.clone();
+                self.forward(event)
+            }
+            StreamEvent::ContentBlockStop { .. } => {
+                self.flush_held()?;
+                self.held_stop = Some(event);
+                Ok(())
+            }
+            StreamEvent::MessageDelta { delta, usage } => {
+                if let Some(usage) = &usage {
+                    self.round_usage.apply_delta(usage);
+                }
+                let round_usage = std::mem::take(&mut self.round_usage);
+                self.usage += &round_usage;
+
+                if delta.stop_reason == Some(StopReason::MaxTokens) {
+                    if let Some(params) = self.continuation() {
+                        // Drop the finished request before sending the next one
+                        self.events = None;
+                        self.held_stop = None;
+                        let events = message_stream(self.client.clone(), params).await?;
+                        self.events = Some(events.boxed());
+                        self.splicer.resume();
+                        return Ok(());
+                    }
+                }
+
+                self.flush_held()?;
+                self.forward(StreamEvent::MessageDelta {
+                    delta,
+                    usage: Some(StreamUsage::from(&self.usage)),
+                })
+            }
+            event => {
+                self.flush_held()?;
+                self.forward(event)
+            }
+        }
+    }
+
+    /// Parameters of the next request, or `None` if the message cannot be continued
+    fn continuation(&mut self) -> Option<CreateMessageParams> {
+        let max_tokens = self.options.next_max_tokens(
+            self.body.max_tokens,
+            self.rounds,
+            self.usage.output_tokens,
+        )?;
+        // The last text block is still open because its stop was held back
+        let prefill = self.splicer.prefill()?;
+        self.rounds += 1;
+        Some(continuation_params(&self.body, prefill, max_tokens))
+    }
+
+    fn flush_held(&mut self) -> Result<(), MessageError> {
+        match self.held_stop.take() {
+            Some(event) => self.forward(event),
+            None => Ok(()),
+        }
+    }
+
+    fn forward(&mut self, event: StreamEvent) -> Result<(), MessageError> {
+        if let Some(event) = self.splicer.push(event)? {
+            self.pending.push_back(event);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(
+        content: Vec<ContentBlock>,
+        stop_reason: StopReason,
+        output: u32,
+    ) -> CreateMessageResponse {
+        CreateMessageResponse {
+            content,
+            id: "msg_1".to_string(),
+            model: "claude-sonnet-4-5".to_string(),
+            role: Role::Assistant,
+            stop_reason: Some(stop_reason),
+            stop_sequence: None,
+            type_: "message".to_string(),
+            usage: Usage {
+                input_tokens: 10,
+                output_tokens: output,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn continuation_is_appended_to_last_text_block() {
+        let mut first = response(
+            vec![ContentBlock::text("Once upon a ")],
+            StopReason::MaxTokens,
+            100,
+        );
+        let prefill = text_prefill(&first.content).unwrap();
+        assert_eq!(prefill, "Once upon a");
+
+        let params = continuation_params(&CreateMessageParams::default(), prefill, 50);
+        assert_eq!(params.max_tokens, 50);
+        assert_eq!(params.messages.last().unwrap().role, Role::Assistant);
+
+        append_continuation(
+            &mut first,
+            response(vec![ContentBlock::text(" time.")], StopReason::EndTurn, 20),
+        );
+        assert_eq!(first.content, vec![ContentBlock::text("Once upon a time.")]);
+        assert_eq!(first.stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(first.usage.input_tokens, 20);
+        assert_eq!(first.usage.output_tokens, 120);
+    }
+
+    fn prefilled_body(prefill: MessageContent) -> CreateMessageParams {
+        CreateMessageParams {
+            messages: vec![
+                Message::new_text(Role::User, "Tell me a story"),
+                Message {
+                    role: Role::Assistant,
+                    content: prefill,
+                },
+            ],
+            max_tokens: 100,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn continuation_extends_a_prefilled_body() {
+        let body = prefilled_body(MessageContent::Text {
+            content: "Once".to_string(),
+        });
+        let params = continuation_params(&body, " upon a".to_string(), 50);
+        assert_eq!(params.messages.len(), 2);
+        assert_eq!(params.messages[1].role, Role::Assistant);
+        assert_eq!(
+            params.messages[1].content,
+            MessageContent::Text {
+                content: "Once upon a".to_string()
+            }
+        );
+
+        let params = continuation_params(&body, String::new(), 50);
+        assert_eq!(params.messages[1].content, body.messages[1].content);
+    }
+
+    #[test]
+    fn resume_extends_a_prefilled_body() {
+        let body = prefilled_body(MessageContent::Blocks {
+            content: vec![ContentBlock::text("Once")],
+        });
+        let params = resume_params(&body, " upon a".to_string(), 40).unwrap();
+        assert_eq!(params.max_tokens, 60);
+        assert_eq!(params.messages.len(), 2);
+        assert_eq!(
+            params.messages[1].content,
+            MessageContent::Blocks {
+                content: vec![ContentBlock::text("Once upon a")]
+            }
+        );
+        assert!(resume_params(&body, " upon a".to_string(), 100).is_none());
+    }
+
+    #[test]
+    fn first_request_respects_token_cap() {
+        let body = CreateMessageParams {
+            max_tokens: 4096,
+            ..Default::default()
+        };
+        let options = ContinuationOptions::new().with_max_total_tokens(1000);
+        assert_eq!(first_params(&body, &options).max_tokens, 1000);
+        assert_eq!(
+            first_params(&body, &ContinuationOptions::new()).max_tokens,
+            4096
+        );
     }
 }
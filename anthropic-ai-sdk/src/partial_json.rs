@@ -0,0 +1,438 @@
+//! Partial JSON parsing
+//!
+//! Tool inputs are streamed as fragments of JSON in `input_json_delta` events. This
+//! module turns an incomplete JSON document into a best-effort [`serde_json::Value`], so
+//! tool arguments and structured output can be displayed while they are generated.
+//!
+//! Incomplete input is closed as follows:
+//!
+//! - open strings are closed, dropping a trailing incomplete escape sequence
+//! - open arrays and objects are closed
+//! - object keys without a value yet are dropped
+//! - numbers are kept if what was received so far is a valid number
+//! - incomplete `true`, `false` and `null` literals are dropped
+//!
+//! Documents nested more than 128 levels deep are rejected.
+//!
+//! # Example
+//!
+//! ```
+//! use anthropic_ai_sdk::partial_json::{PartialJson, parse_partial};
+//! use serde_json::json;
+//!
+//! assert_eq!(
+//!     parse_partial(r#"{"city": "San Fra"#),
+//!     Some(json!({"city": "San Fra"}))
+//! );
+//!
+//! let mut input = PartialJson::new();
+//! input.push(r#"{"items": [1, 2"#);
+//! assert_eq!(input.value(), Some(json!({"items": [1, 2]})));
+//! input.push(r#", 3], "done": tr"#);
+//! assert_eq!(input.value(), Some(json!({"items": [1, 2, 3]})));
+//! ```
+
+use serde_json::{Map, Number, Value};
+
+/// How deeply arrays and objects may be nested
+const MAX_DEPTH: usize = 128;
+
+/// Parse a possibly incomplete JSON document
+///
+/// Returns `None` if nothing has been received yet, or if the input is not the prefix
+/// of a valid JSON document.
+pub fn parse_partial(input: &str) -> Option<Value> {
+    let mut parser = Parser {
+        input: input.as_bytes(),
+        position: 0,
+        depth: 0,
+    };
+    let (value, complete) = parser.value().ok()??;
+    parser.skip_whitespace();
+    if complete && parser.position < parser.input.len() {
+        // Trailing data after a complete document
+        return None;
+    }
+    Some(value)
+}
+
+/// Buffer of JSON fragments that can be parsed at any time
+///
+/// Each parse reads the whole buffer, so calling [`push`](Self::push) for every
+/// fragment of a long document costs time quadratic in its length. Parse less often,
+/// for example on a timer, when inputs are large.
+#[derive(Debug, Clone, Default)]
+pub struct PartialJson {
+    buffer: String,
+}
+
+impl PartialJson {
+    /// Create an empty buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a fragment and return the best-effort value of the buffer
+    ///
+    /// The whole buffer is parsed again. Use [`extend`](Self::extend) to append without
+    /// parsing.
+    pub fn push(&mut self, fragment: &str) -> Option<Value> {
+        self.extend(fragment);
+        self.value()
+    }
+
+    /// Append a fragment without parsing the buffer
+    pub fn extend(&mut self, fragment: &str) {
+        self.buffer.push_str(fragment);
+    }
+
+    /// Best-effort value of the fragments received so far
+    pub fn value(&self) -> Option<Value> {
+        parse_partial(&self.buffer)
+    }
+
+    /// The fragments received so far
+    pub fn as_str(&self) -> &str {
+        &self.buffer
+    }
+}
+
+/// The input is not the prefix of a valid JSON document
+struct Invalid;
+
+/// A parsed value and whether it was complete
+type Parsed = Option<(Value, bool)>;
+
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+    /// Arrays and objects open around the current position
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    /// Parse a value, returning `None` if the input ends before anything usable
+    fn value(&mut self) -> Result<Parsed, Invalid> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(None),
+            Some(open @ (b'{' | b'[')) => {
+                if self.depth == MAX_DEPTH {
+                    return Err(Invalid);
+                }
+                self.depth += 1;
+                let parsed = if open == b'{' {
+                    self.object()
+                } else {
+                    self.array()
+                };
+                self.depth -= 1;
+                parsed.map(Some)
+            }
+            Some(b'"') => {
+                let (s, complete) = self.string()?;
+                Ok(Some((Value::String(s), complete)))
+            }
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(Invalid),
+        }
+    }
+
+    fn object(&mut self) -> Result<(Value, bool), Invalid> {
+        self.position += 1;
+        let mut map = Map::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Ok((Value::Object(map), false)),
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok((Value::Object(map), true));
+                }
+                Some(b'"') => {}
+                Some(_) => return Err(Invalid),
+            }
+
+            let (key, complete) = self.string()?;
+            if !complete {
+                return Ok((Value::Object(map), false));
+            }
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Ok((Value::Object(map), false)),
+                Some(b':') => self.position += 1,
+                Some(_) => return Err(Invalid),
+            }
+            let Some((value, complete)) = self.value()? else {
+                return Ok((Value::Object(map), false));
+            };
+            map.insert(key, value);
+            if !complete {
+                return Ok((Value::Object(map), false));
+            }
+
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Ok((Value::Object(map), false)),
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok((Value::Object(map), true));
+                }
+                Some(_) => return Err(Invalid),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<(Value, bool), Invalid> {
+        self.position += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(b']') {
+                self.position += 1;
+                return Ok((Value::Array(items), true));
+            }
+            let Some((value, complete)) = self.value()? else {
+                return Ok((Value::Array(items), false));
+            };
+            items.push(value);
+            if !complete {
+                return Ok((Value::Array(items), false));
+            }
+
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Ok((Value::Array(items), false)),
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok((Value::Array(items), true));
+                }
+                Some(_) => return Err(Invalid),
+            }
+        }
+    }
+
+    /// Parse a string starting at the opening quote
+    fn string(&mut self) -> Result<(String, bool), Invalid> {
+        self.position += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let Some(byte) = self.peek() else {
+                return Ok((into_string(bytes), false));
+            };
+            self.position += 1;
+            match byte {
+                b'"' => return Ok((into_string(bytes), true)),
+                b'\\' => {
+                    let Some(escape) = self.peek() else {
+                        return Ok((into_string(bytes), false));
+                    };
+                    self.position += 1;
+                    let unescaped = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => match self.unicode_escape()? {
+                            Some(c) => c,
+                            None => return Ok((into_string(bytes), false)),
+                        },
+                        _ => return Err(Invalid),
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(unescaped.encode_utf8(&mut buffer).as_bytes());
+                }
+                _ => bytes.push(byte),
+            }
+        }
+    }
+
+    /// Parse the hex digits of a `\u` escape, including a following low surrogate
+    ///
+    /// Returns `None` if the input ends inside the escape.
+    fn unicode_escape(&mut self) -> Result<Option<char>, Invalid> {
+        let Some(high) = self.hex4()? else {
+            return Ok(None);
+        };
+        if !(0xD800..0xDC00).contains(&high) {
+            return Ok(Some(
+                char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER),
+            ));
+        }
+
+        // A high surrogate must be followed by `\uXXXX` with a low surrogate
+        for expected in [b'\\', b'u'] {
+            match self.peek() {
+                None => return Ok(None),
+                Some(byte) if byte == expected => self.position += 1,
+                Some(_) => return Ok(Some(char::REPLACEMENT_CHARACTER)),
+            }
+        }
+        let Some(low) = self.hex4()? else {
+            return Ok(None);
+        };
+        let code = 0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+        Ok(Some(
+            char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER),
+        ))
+    }
+
+    fn hex4(&mut self) -> Result<Option<u32>, Invalid> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let Some(byte) = self.peek() else {
+                return Ok(None);
+            };
+            let digit = (byte as char).to_digit(16).ok_or(Invalid)?;
+            code = code * 16 + digit;
+            self.position += 1;
+        }
+        Ok(Some(code))
+    }
+
+    fn literal(&mut self, literal: &str, value: Value) -> Result<Parsed, Invalid> {
+        let rest = &self.input[self.position..];
+        let literal = literal.as_bytes();
+        if rest.starts_with(literal) {
+            self.position += literal.len();
+            Ok(Some((value, true)))
+        } else if literal.starts_with(rest) {
+            self.position = self.input.len();
+            Ok(None)
+        } else {
+            Err(Invalid)
+        }
+    }
+
+    fn number(&mut self) -> Result<Parsed, Invalid> {
+        let start = self.position;
+        while matches!(
+            self.peek(),
+            Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        ) {
+            self.position += 1;
+        }
+        let complete = self.position < self.input.len();
+        let text = std::str::from_utf8(&self.input[start..self.position]).map_err(|_| Invalid)?;
+
+        match serde_json::from_str::<Number>(text) {
+            Ok(number) => Ok(Some((Value::Number(number), complete))),
+            Err(_) if complete => Err(Invalid),
+            Err(_) => {
+                // Drop a trailing sign, decimal point or exponent that has no digits yet
+                let trimmed = text.trim_end_matches(['-', '+', '.', 'e', 'E']);
+                Ok(serde_json::from_str::<Number>(trimmed)
+                    .ok()
+                    .map(|number| (Value::Number(number), false)))
+            }
+        }
+    }
+}
+
+/// Convert the bytes of a string, dropping an incomplete trailing UTF-8 sequence
+fn into_string(bytes: Vec<u8>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut bytes = e.into_bytes();
+            bytes.truncate(valid);
+            String::from_utf8(bytes).unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn closes_open_containers_and_strings() {
+        assert_eq!(parse_partial(""), None);
+        assert_eq!(parse_partial("{"), Some(json!({})));
+        assert_eq!(parse_partial(r#"{"na"#), Some(json!({})));
+        assert_eq!(parse_partial(r#"{"name""#), Some(json!({})));
+        assert_eq!(parse_partial(r#"{"name": "#), Some(json!({})));
+        assert_eq!(
+            parse_partial(r#"{"name": "Al"#),
+            Some(json!({"name": "Al"}))
+        );
+        assert_eq!(
+            parse_partial(r#"{"tags": ["a", "b"#),
+            Some(json!({"tags": ["a", "b"]}))
+        );
+        assert_eq!(
+            parse_partial(r#"{"a": {"b": [1, {"c": "d"}], "e": nu"#),
+            Some(json!({"a": {"b": [1, {"c": "d"}]}}))
+        );
+    }
+
+    #[test]
+    fn handles_numbers_and_escapes() {
+        assert_eq!(parse_partial(r#"{"n": 12"#), Some(json!({"n": 12})));
+        assert_eq!(parse_partial(r#"{"n": 1.5e"#), Some(json!({"n": 1.5})));
+        assert_eq!(parse_partial(r#"{"n": -"#), Some(json!({})));
+        assert_eq!(parse_partial(r#"["line\"#), Some(json!(["line"])));
+        assert_eq!(parse_partial(r#"["line\n"#), Some(json!(["line\n"])));
+        assert_eq!(parse_partial(r#"["\u00"#), Some(json!([""])));
+        assert_eq!(parse_partial(r#"["é😀"]"#), Some(json!(["é😀"])));
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert_eq!(parse_partial(r#"{"a" 1"#), None);
+        assert_eq!(parse_partial("[1, x"), None);
+        assert_eq!(parse_partial(r#"{"a": 1} trailing"#), None);
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let nested = "[".repeat(MAX_DEPTH);
+        assert!(parse_partial(&nested).is_some());
+        assert_eq!(parse_partial(&"[".repeat(MAX_DEPTH + 1)), None);
+        assert_eq!(parse_partial(&"[".repeat(100_000)), None);
+    }
+
+    #[test]
+    fn buffer_accumulates_fragments() {
+        let mut input = PartialJson::new();
+        let fragments = [
+            r#"{"loc"#,
+            r#"ation": "Par"#,
+            r#"is", "unit": "c"#,
+            r#"elsius"}"#,
+        ];
+        let values: Vec<_> = fragments.iter().map(|f| input.push(f)).collect();
+        assert_eq!(values[0], Some(json!({})));
+        assert_eq!(values[1], Some(json!({"location": "Par"})));
+        assert_eq!(values[2], Some(json!({"location": "Paris", "unit": "c"})));
+        assert_eq!(
+            values[3],
+            Some(json!({"location": "Paris", "unit": "celsius"}))
+        );
+        assert_eq!(
+            input.as_str(),
+            r#"{"location": "Paris", "unit": "celsius"}"#
+        );
+    }
+}
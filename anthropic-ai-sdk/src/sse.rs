@@ -0,0 +1,267 @@
+//! Server-Sent Events encoding
+//!
+//! This module re-emits the events of a message stream as Server-Sent Events in the
+//! same wire format the API produces, so web backends can proxy a stream to browsers
+//! that already understand it:
+//!
+//! ```text
+//! event: content_block_delta
+//! data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}
+//!
+//! ```
+//!
+//! Stream errors are sent as an `error` event before the stream ends, and `ping` events
+//! are sent while the upstream stream is idle to keep intermediaries from closing the
+//! connection.
+//!
+//! With the `axum` feature, [`sse_response`] turns a message stream into an
+//! [`axum::response::Sse`] response. When the browser disconnects, axum drops the
+//! response stream, which drops the upstream stream and aborts the request to the API.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::sse::encode_stream;
+//! use anthropic_ai_sdk::types::message::{CreateMessageParams, MessageError};
+//! use futures_util::StreamExt;
+//! use std::time::Duration;
+//!
+//! # async fn example(params: CreateMessageParams) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+//!
+//! let stream = client.create_message_streaming_owned(params).await?;
+//! let mut frames = encode_stream(stream, Some(Duration::from_secs(15)));
+//! while let Some(Ok(frame)) = frames.next().await {
+//!     // Write the frame to the response body
+//!     print!("{}", frame);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::types::error::ApiErrorResponse;
+use crate::types::message::{MessageError, StreamEvent};
+use futures_util::Stream;
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::{Instant, Sleep, sleep};
+
+/// Interval between `ping` events used by [`sse_response`]
+pub const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Name of the SSE event of a stream event, as sent by the API
+pub fn event_name(event: &StreamEvent) -> &'static str {
+    match event {
+        StreamEvent::MessageStart { .. } => "message_start",
+        StreamEvent::ContentBlockStart { .. } => "content_block_start",
+        StreamEvent::ContentBlockDelta { .. } => "content_block_delta",
+        StreamEvent::ContentBlockStop { .. } => "content_block_stop",
+        StreamEvent::MessageDelta { .. } => "message_delta",
+        StreamEvent::MessageStop => "message_stop",
+        StreamEvent::Ping => "ping",
+        StreamEvent::Error { .. } => "error",
+    }
+}
+
+/// JSON payload of a stream event
+pub fn event_data(event: &StreamEvent) -> String {
+    serde_json::to_string(event).expect("stream events serialize to JSON")
+}
+
+/// JSON payload of the `error` event reporting a stream error
+///
+/// API errors keep their type; other errors are reported as `api_error`.
+pub fn error_data(error: &MessageError) -> String {
+    let response = ApiErrorResponse {
+        type_: "error".to_string(),
+        error: error.error_detail(),
+    };
+    serde_json::to_string(&response).expect("API errors serialize to JSON")
+}
+
+/// Encode a stream event as an SSE frame
+pub fn encode_event(event: &StreamEvent) -> String {
+    frame(event_name(event), &event_data(event))
+}
+
+/// Encode a stream error as an SSE `error` frame
+pub fn encode_error(error: &MessageError) -> String {
+    frame("error", &error_data(error))
+}
+
+fn frame(event: &str, data: &str) -> String {
+    format!("event: {}\ndata: {}\n\n", event, data)
+}
+
+/// Encode a message stream as SSE frames
+///
+/// When `keep_alive` is set, a `ping` frame is emitted whenever the stream has been
+/// idle for that long. The stream ends after the first error, which is encoded as an
+/// `error` frame. Dropping the returned stream drops `stream`.
+pub fn encode_stream<S>(stream: S, keep_alive: Option<Duration>) -> SseEncoder<S>
+where
+    S: Stream<Item = Result<StreamEvent, MessageError>>,
+{
+    SseEncoder {
+        inner: Some(Box::pin(stream)),
+        keep_alive: keep_alive.map(|interval| (interval, Box::pin(sleep(interval)))),
+    }
+}
+
+/// Stream of SSE frames created by [`encode_stream`]
+pub struct SseEncoder<S> {
+    inner: Option<Pin<Box<S>>>,
+    keep_alive: Option<(Duration, Pin<Box<Sleep>>)>,
+}
+
+impl<S> Stream for SseEncoder<S>
+where
+    S: Stream<Item = Result<StreamEvent, MessageError>>,
+{
+    type Item = Result<String, Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let Some(inner) = this.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        let frame = match inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(Ok(event))) => encode_event(&event),
+            Poll::Ready(Some(Err(error))) => {
+                this.inner = None;
+                encode_error(&error)
+            }
+            Poll::Ready(None) => {
+                this.inner = None;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => {
+                let ping_due = match this.keep_alive.as_mut() {
+                    Some((_, timer)) => timer.as_mut().poll(cx).is_ready(),
+                    None => false,
+                };
+                if !ping_due {
+                    return Poll::Pending;
+                }
+                encode_event(&StreamEvent::Ping)
+            }
+        };
+
+        if let Some((interval, timer)) = this.keep_alive.as_mut() {
+            timer.as_mut().reset(Instant::now() + *interval);
+        }
+        Poll::Ready(Some(Ok(frame)))
+    }
+}
+
+/// Convert a message stream into an axum SSE response
+///
+/// `ping` events are sent every [`DEFAULT_KEEP_ALIVE`] while the stream is idle; call
+/// [`Sse::keep_alive`](axum::response::Sse::keep_alive) on the result to change the
+/// interval. A stream error is sent as an `error` event and ends the response.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anthropic_ai_sdk::client::AnthropicClient;
+/// use anthropic_ai_sdk::sse::sse_response;
+/// use anthropic_ai_sdk::types::message::CreateMessageParams;
+/// use axum::response::{IntoResponse, Response};
+///
+/// async fn chat(client: AnthropicClient, params: CreateMessageParams) -> Response {
+///     match client.create_message_streaming_owned(params).await {
+///         Ok(stream) => sse_response(stream).into_response(),
+///         Err(e) => (axum::http::StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+///     }
+/// }
+/// ```
+#[cfg(feature = "axum")]
+pub fn sse_response<S>(
+    stream: S,
+) -> axum::response::Sse<impl Stream<Item = Result<axum::response::sse::Event, Infallible>>>
+where
+    S: Stream<Item = Result<StreamEvent, MessageError>> + Send + 'static,
+{
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use futures_util::StreamExt;
+
+    let events = stream.scan(false, |failed, item| {
+        if *failed {
+            return std::future::ready(None);
+        }
+        let event = match item {
+            Ok(event) => Event::default()
+                .event(event_name(&event))
+                .data(event_data(&event)),
+            Err(error) => {
+                *failed = true;
+                Event::default().event("error").data(error_data(&error))
+            }
+        };
+        std::future::ready(Some(Ok(event)))
+    });
+
+    Sse::new(events).keep_alive(
+        KeepAlive::new().interval(DEFAULT_KEEP_ALIVE).event(
+            Event::default()
+                .event("ping")
+                .data(event_data(&StreamEvent::Ping)),
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    #[test]
+    fn encodes_events_in_api_wire_format() {
+        let event: StreamEvent = serde_json::from_str(
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            encode_event(&event),
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n"
+        );
+        assert_eq!(
+            encode_event(&StreamEvent::Ping),
+            "event: ping\ndata: {\"type\":\"ping\"}\n\n"
+        );
+
+        let error = MessageError::from(
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+                .to_string(),
+        );
+        assert_eq!(
+            encode_error(&error),
+            "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn encoder_sends_pings_and_stops_after_errors() {
+        let items = vec![
+            Ok(StreamEvent::MessageStop),
+            Err(MessageError::IdleTimeout(Duration::from_secs(1))),
+            Ok(StreamEvent::MessageStop),
+        ];
+        let frames: Vec<_> = encode_stream(futures_util::stream::iter(items), None)
+            .collect()
+            .await;
+        assert_eq!(frames.len(), 2);
+        assert!(frames[1].as_ref().unwrap().starts_with("event: error\n"));
+
+        let idle = futures_util::stream::pending::<Result<StreamEvent, MessageError>>();
+        let mut frames = encode_stream(idle, Some(Duration::from_millis(10)));
+        assert_eq!(
+            frames.next().await.unwrap().unwrap(),
+            encode_event(&StreamEvent::Ping)
+        );
+    }
+}
@@ -0,0 +1,1144 @@
+//! Streaming helpers
+//!
+//! This module provides adapters for the stream returned by
+//! [`MessageClient::create_message_streaming`](crate::types::message::MessageClient::create_message_streaming):
+//!
+//! - [`MessageStreamExt::text_stream`] yields only the text of the response
+//! - [`MessageStreamExt::callbacks`] dispatches text, thinking, tool use and message stop
+//!   events to callbacks
+//! - [`MessageStreamExt::into_async_read`] exposes the text as an [`AsyncRead`] so it can be
+//!   piped into `tokio::io::copy` or an HTTP body
+//! - [`MessageStreamExt::with_timeouts`] ends a stream that stalls or runs past its deadline
+//! - [`MessageStreamExt::broadcast`] fans the events out to several consumers, such as a
+//!   UI, a persistence layer and metrics
+//!
+//! [`MessageAccumulator`] rebuilds the complete message from the events of a stream, and
+//! exposes the best-effort input of tool use blocks while it is streaming.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::stream::MessageStreamExt;
+//! use anthropic_ai_sdk::types::message::{CreateMessageParams, MessageClient, MessageError};
+//!
+//! # async fn example(params: CreateMessageParams) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+//!
+//! let stream = client.create_message_streaming(&params).await?;
+//! let message = stream
+//!     .callbacks()
+//!     .on_text(|text| print!("{}", text))
+//!     .on_tool_use(|tool_use| println!("\n[calling {}]", tool_use.name))
+//!     .run()
+//!     .await?;
+//! println!("\nStopped because of {:?}", message.stop_reason);
+//!
+//! // Or pipe the text somewhere
+//! let stream = client.create_message_streaming(&params).await?;
+//! let mut reader = stream.into_async_read();
+//! tokio::io::copy(&mut reader, &mut tokio::io::stdout()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::partial_json::parse_partial;
+use crate::tokens::TokenEstimator;
+use crate::types::message::{
+    ContentBlock, ContentBlockDelta, CreateMessageResponse, MessageError, StreamEvent,
+};
+use futures_util::{Stream, StreamExt, future};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::AsyncRead;
+use tokio::sync::mpsc;
+use tokio::time::{Instant, Sleep, sleep_until};
+use tokio_util::bytes::Bytes;
+use tokio_util::io::StreamReader;
+
+/// Completed tool use block of a streamed response
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolUseBlock {
+    /// Identifier of the tool use
+    pub id: String,
+    /// Name of the tool
+    pub name: String,
+    /// Input of the tool call
+    pub input: serde_json::Value,
+}
+
+/// Rebuilds a complete message from streaming events
+#[derive(Debug, Default)]
+pub struct MessageAccumulator {
+    message: Option<CreateMessageResponse>,
+    /// Partial JSON of tool use blocks that have not stopped yet
+    partial_json: HashMap<usize, String>,
+    finished: bool,
+}
+
+impl MessageAccumulator {
+    /// Create an empty accumulator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply an event to the message
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageError` if the event is an `error` event, arrives before
+    /// `message_start`, references an unknown content block or completes a tool use
+    /// block whose input is not valid JSON.
+    pub fn push(&mut self, event: &StreamEvent) -> Result<(), MessageError> {
+        match event {
+            StreamEvent::MessageStart { message } => {
+                self.message = Some(CreateMessageResponse {
+                    content: message.content.clone(),
+                    id: message.id.clone(),
+                    model: message.model.clone(),
+                    role: message.role,
+                    stop_reason: message.stop_reason,
+                    stop_sequence: message.stop_sequence.clone(),
+                    type_: message.type_.clone(),
+                    usage: message.usage.clone(),
+                });
+            }
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => {
+                let content = &mut self.message_mut()?.content;
+                if *index < content.len() {
+                    content[*index] = content_block.clone();
+                } else {
+                    content.push(content_block.clone());
+                }
+            }
+            StreamEvent::ContentBlockDelta { index, delta } => {
+                if let ContentBlockDelta::InputJsonDelta { partial_json } = delta {
+                    self.partial_json
+                        .entry(*index)
+                        .or_default()
+                        .push_str(partial_json);
+                    return Ok(());
+                }
+                let block = self.block_mut(*index)?;
+                match (block, delta) {
+                    (ContentBlock::Text { text }, ContentBlockDelta::TextDelta { text: delta }) => {
+                        text.push_str(delta)
+                    }
+                    (
+                        ContentBlock::Thinking { thinking, .. },
+                        ContentBlockDelta::ThinkingDelta { thinking: delta },
+                    ) => thinking.push_str(delta),
+                    (
+                        ContentBlock::Thinking { signature, .. },
+                        ContentBlockDelta::SignatureDelta { signature: delta },
+                    ) => signature.push_str(delta),
+                    (_, delta) => {
+                        return Err(MessageError::ApiError(format!(
+                            "Unexpected delta for content block {}: {:?}",
+                            index, delta
+                        )));
+                    }
+                }
+            }
+            StreamEvent::ContentBlockStop { index } => {
+                if let Some(json) = self.partial_json.remove(index) {
+                    if let ContentBlock::ToolUse { input, .. } = self.block_mut(*index)? {
+                        *input = parse_tool_input(&json)?;
+                    }
+                }
+            }
+            StreamEvent::MessageDelta { delta, usage } => {
+                let message = self.message_mut()?;
+                message.stop_reason = delta.stop_reason;
+                message.stop_sequence = delta.stop_sequence.clone();
+                if let Some(usage) = usage {
+                    message.usage.apply_delta(usage);
+                }
+            }
+            StreamEvent::MessageStop => self.finished = true,
+            StreamEvent::Ping => {}
+            StreamEvent::Error { error } => return Err(error.clone().into()),
+        }
+        Ok(())
+    }
+
+    /// The message built so far, if `message_start` has been received
+    pub fn message(&self) -> Option<&CreateMessageResponse> {
+        self.message.as_ref()
+    }
+
+    /// Whether `message_stop` has been received
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Best-effort input of a tool use block whose input is still streaming
+    ///
+    /// Returns `None` once the block has stopped; its complete input is then part of
+    /// the message.
+    pub fn partial_tool_input(&self, index: usize) -> Option<serde_json::Value> {
+        self.partial_json
+            .get(&index)
+            .and_then(|json| parse_partial(json))
+    }
+
+    /// Consume the accumulator and return the message built so far
+    pub fn into_message(self) -> Option<CreateMessageResponse> {
+        self.message
+    }
+
+    fn message_mut(&mut self) -> Result<&mut CreateMessageResponse, MessageError> {
+        self.message
+            .as_mut()
+            .ok_or_else(|| MessageError::ApiError("Received event before message_start".into()))
+    }
+
+    fn block_mut(&mut self, index: usize) -> Result<&mut ContentBlock, MessageError> {
+        self.message_mut()?
+            .content
+            .get_mut(index)
+            .ok_or_else(|| MessageError::ApiError(format!("Unknown content block {}", index)))
+    }
+}
+
+fn parse_tool_input(json: &str) -> Result<serde_json::Value, MessageError> {
+    if json.trim().is_empty() {
+        return Ok(serde_json::Value::Object(Default::default()));
+    }
+    serde_json::from_str(json)
+        .map_err(|e| MessageError::ApiError(format!("Invalid tool input JSON: {}", e)))
+}
+
+type TextCallback<'a> = Box<dyn FnMut(&str) + Send + 'a>;
+type ToolUseCallback<'a> = Box<dyn FnMut(&ToolUseBlock) + Send + 'a>;
+type MessageStopCallback<'a> = Box<dyn FnMut(&CreateMessageResponse) + Send + 'a>;
+
+/// Builder that dispatches the events of a message stream to callbacks
+///
+/// Created with [`MessageStreamExt::callbacks`]. Nothing happens until
+/// [`run`](StreamCallbacks::run) is awaited.
+pub struct StreamCallbacks<'a, S> {
+    stream: S,
+    on_text: Option<TextCallback<'a>>,
+    on_thinking: Option<TextCallback<'a>>,
+    on_tool_use: Option<ToolUseCallback<'a>>,
+    on_tool_input: Option<ToolUseCallback<'a>>,
+    on_message_stop: Option<MessageStopCallback<'a>>,
+}
+
+impl<'a, S> StreamCallbacks<'a, S>
+where
+    S: Stream<Item = Result<StreamEvent, MessageError>>,
+{
+    fn new(stream: S) -> Self {
+        Self {
+            stream,
+            on_text: None,
+            on_thinking: None,
+            on_tool_use: None,
+            on_tool_input: None,
+            on_message_stop: None,
+        }
+    }
+
+    /// Call `callback` with every chunk of text
+    pub fn on_text(mut self, callback: impl FnMut(&str) + Send + 'a) -> Self {
+        self.on_text = Some(Box::new(callback));
+        self
+    }
+
+    /// Call `callback` with every chunk of extended thinking
+    pub fn on_thinking(mut self, callback: impl FnMut(&str) + Send + 'a) -> Self {
+        self.on_thinking = Some(Box::new(callback));
+        self
+    }
+
+    /// Call `callback` with every tool use block once its input is complete
+    pub fn on_tool_use(mut self, callback: impl FnMut(&ToolUseBlock) + Send + 'a) -> Self {
+        self.on_tool_use = Some(Box::new(callback));
+        self
+    }
+
+    /// Call `callback` with the best-effort input of a tool use block after every
+    /// fragment of its input
+    ///
+    /// See [`partial_json`](crate::partial_json) for how incomplete input is parsed.
+    pub fn on_tool_input(mut self, callback: impl FnMut(&ToolUseBlock) + Send + 'a) -> Self {
+        self.on_tool_input = Some(Box::new(callback));
+        self
+    }
+
+    /// Call `callback` with the complete message when `message_stop` is received
+    pub fn on_message_stop(
+        mut self,
+        callback: impl FnMut(&CreateMessageResponse) + Send + 'a,
+    ) -> Self {
+        self.on_message_stop = Some(Box::new(callback));
+        self
+    }
+
+    /// Consume the stream, calling the callbacks as events arrive
+    ///
+    /// # Returns
+    ///
+    /// Returns the complete message.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageError` if the stream fails, contains an `error` event or ends
+    /// before `message_start`.
+    pub async fn run(self) -> Result<CreateMessageResponse, MessageError> {
+        let Self {
+            stream,
+            mut on_text,
+            mut on_thinking,
+            mut on_tool_use,
+            mut on_tool_input,
+            mut on_message_stop,
+        } = self;
+        let mut stream = std::pin::pin!(stream);
+        let mut accumulator = MessageAccumulator::new();
+
+        while let Some(event) = stream.next().await {
+            let event = event?;
+            accumulator.push(&event)?;
+            match &event {
+                StreamEvent::ContentBlockDelta { index, delta } => match delta {
+                    ContentBlockDelta::TextDelta { text } => {
+                        if let Some(callback) = on_text.as_mut() {
+                            callback(text);
+                        }
+                    }
+                    ContentBlockDelta::ThinkingDelta { thinking } => {
+                        if let Some(callback) = on_thinking.as_mut() {
+                            callback(thinking);
+                        }
+                    }
+                    ContentBlockDelta::InputJsonDelta { .. } => {
+                        let Some(callback) = on_tool_input.as_mut() else {
+                            continue;
+                        };
+                        let block = accumulator.message().and_then(|m| m.content.get(*index));
+                        let input = accumulator.partial_tool_input(*index);
+                        if let (Some(ContentBlock::ToolUse { id, name, .. }), Some(input)) =
+                            (block, input)
+                        {
+                            callback(&ToolUseBlock {
+                                id: id.clone(),
+                                name: name.clone(),
+                                input,
+                            });
+                        }
+                    }
+                    _ => {}
+                },
+                StreamEvent::ContentBlockStop { index } => {
+                    let Some(callback) = on_tool_use.as_mut() else {
+                        continue;
+                    };
+                    let block = accumulator.message().and_then(|m| m.content.get(*index));
+                    if let Some(ContentBlock::ToolUse { id, name, input }) = block {
+                        callback(&ToolUseBlock {
+                            id: id.clone(),
+                            name: name.clone(),
+                            input: input.clone(),
+                        });
+                    }
+                }
+                StreamEvent::MessageStop => {
+                    if let (Some(callback), Some(message)) =
+                        (on_message_stop.as_mut(), accumulator.message())
+                    {
+                        callback(message);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        accumulator
+            .into_message()
+            .ok_or_else(|| MessageError::ApiError("Stream ended before message_start".into()))
+    }
+}
+
+/// Text of `content` to prefill a continuation with, or `None` if it contains content
+/// other than text
+///
+/// Trailing whitespace is trimmed because the API rejects prefills that end with it.
+pub(crate) fn text_prefill(content: &[ContentBlock]) -> Option<String> {
+    let mut text = String::new();
+    for block in content {
+        match block {
+            ContentBlock::Text { text: block } => text.push_str(block),
+            _ => return None,
+        }
+    }
+    Some(text.trim_end().to_string())
+}
+
+/// Splices the events of continuation requests into a single stream
+///
+/// The events of the first request pass through unchanged. After
+/// [`resume`](StreamSplicer::resume), the `message_start` of the continuation is
+/// dropped and its content block indices are shifted so that it continues the message
+/// already streamed: text that continues an unfinished text block is appended to it.
+#[derive(Debug, Default)]
+pub(crate) struct StreamSplicer {
+    accumulator: MessageAccumulator,
+    /// Content block that has started but not stopped
+    open_block: Option<usize>,
+    /// Index of the first content block of the current continuation
+    base: usize,
+    /// Whether the first block of the continuation is merged into the open block
+    merge_first: bool,
+    /// Whether leading whitespace of the continuation must be dropped, because the
+    /// prefill had trailing whitespace that was already streamed
+    strip_leading_whitespace: bool,
+    continuing: bool,
+}
+
+impl StreamSplicer {
+    /// Map an event of the current request to the spliced stream
+    ///
+    /// Returns `None` for events that must not be forwarded.
+    pub(crate) fn push(&mut self, event: StreamEvent) -> Result<Option<StreamEvent>, MessageError> {
+        let event = match event {
+            StreamEvent::MessageStart { .. } if self.continuing => return Ok(None),
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } if self.continuing => {
+                if index == 0 && self.merge_first {
+                    match content_block {
+                        ContentBlock::Text { text } if !text.is_empty() => {
+                            StreamEvent::ContentBlockDelta {
+                                index: self.base,
+                                delta: ContentBlockDelta::TextDelta { text },
+                            }
+                        }
+                        _ => return Ok(None),
+                    }
+                } else {
+                    StreamEvent::ContentBlockStart {
+                        index: self.base + index,
+                        content_block,
+                    }
+                }
+            }
+            StreamEvent::ContentBlockDelta { index, delta } if self.continuing => {
+                StreamEvent::ContentBlockDelta {
+                    index: self.base + index,
+                    delta,
+                }
+            }
+            StreamEvent::ContentBlockStop { index } if self.continuing => {
+                StreamEvent::ContentBlockStop {
+                    index: self.base + index,
+                }
+            }
+            event => event,
+        };
+
+        let event = match event {
+            StreamEvent::ContentBlockDelta {
+                index,
+                delta: ContentBlockDelta::TextDelta { text },
+            } if self.strip_leading_whitespace => {
+                let text = text.trim_start();
+                if text.is_empty() {
+                    return Ok(None);
+                }
+                self.strip_leading_whitespace = false;
+                StreamEvent::ContentBlockDelta {
+                    index,
+                    delta: ContentBlockDelta::TextDelta {
+                        text: text.to_string(),
+                    },
+                }
+            }
+            event => event,
+        };
+
+        match &event {
+            StreamEvent::ContentBlockStart { index, .. } => self.open_block = Some(*index),
+            StreamEvent::ContentBlockStop { .. } => {
+                self.open_block = None;
+                self.strip_leading_whitespace = false;
+            }
+            _ => {}
+        }
+        self.accumulator.push(&event)?;
+        Ok(Some(event))
+    }
+
+    /// The message streamed so far
+    pub(crate) fn message(&self) -> Option<&CreateMessageResponse> {
+        self.accumulator.message()
+    }
+
+    /// Whether `message_stop` has been streamed
+    pub(crate) fn is_finished(&self) -> bool {
+        self.accumulator.is_finished()
+    }
+
+    /// Text to prefill the continuation with, or `None` if the message cannot be
+    /// continued because it contains content other than text
+    pub(crate) fn prefill(&self) -> Option<String> {
+        match self.message() {
+            Some(message) => text_prefill(&message.content),
+            None => Some(String::new()),
+        }
+    }
+
+    /// Output tokens streamed so far
+    ///
+    /// The API reports output usage at the end of a response, so until then the tokens
+    /// are estimated from the text streamed.
+    pub(crate) fn output_tokens(&self) -> u32 {
+        let Some(message) = self.message() else {
+            return 0;
+        };
+        let estimator = TokenEstimator::new();
+        let estimated = message
+            .content
+            .iter()
+            .map(|block| match block {
+                ContentBlock::Text { text } => estimator.estimate_text(text),
+                _ => 0,
+            })
+            .sum();
+        message.usage.output_tokens.max(estimated)
+    }
+
+    /// Prepare for the events of a continuation request
+    pub(crate) fn resume(&mut self) {
+        let Some(message) = self.accumulator.message() else {
+            // Nothing was forwarded, so the new request replaces the old one
+            return;
+        };
+        let blocks = message.content.len();
+        let trailing_whitespace = matches!(
+            message.content.last(),
+            Some(ContentBlock::Text { text }) if text.ends_with(char::is_whitespace)
+        );
+
+        self.continuing = true;
+        self.merge_first = self.open_block.is_some_and(|open| open + 1 == blocks);
+        self.base = if self.merge_first { blocks - 1 } else { blocks };
+        self.strip_leading_whitespace = self.merge_first && trailing_whitespace;
+    }
+}
+
+/// Timeouts applied to a message stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamTimeouts {
+    /// Maximum time between two events, pings included
+    pub idle: Option<Duration>,
+    /// Maximum time for the whole stream
+    pub deadline: Option<Duration>,
+}
+
+impl StreamTimeouts {
+    /// Create timeouts that never expire
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum time between two events
+    pub fn with_idle_timeout(mut self, idle: Duration) -> Self {
+        self.idle = Some(idle);
+        self
+    }
+
+    /// Set the maximum time for the whole stream
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Time left before the first timeout of a stream started at `started` expires,
+    /// with the error reported when it does
+    pub(crate) fn first_expiry(&self, started: Instant) -> Option<(Duration, MessageError)> {
+        let remaining = self
+            .deadline
+            .map(|deadline| (deadline.saturating_sub(started.elapsed()), deadline));
+        match (self.idle, remaining) {
+            (Some(idle), Some((remaining, deadline))) if remaining < idle => {
+                Some((remaining, MessageError::DeadlineExceeded(deadline)))
+            }
+            (Some(idle), _) => Some((idle, MessageError::IdleTimeout(idle))),
+            (None, Some((remaining, deadline))) => {
+                Some((remaining, MessageError::DeadlineExceeded(deadline)))
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+/// Stream adapter that enforces [`StreamTimeouts`]
+///
+/// Every event, pings included, resets the idle timer. When a timeout expires the
+/// adapter yields [`MessageError::IdleTimeout`] or [`MessageError::DeadlineExceeded`]
+/// and drops the underlying stream, which aborts the HTTP request.
+pub struct Watchdog<S> {
+    inner: Option<Pin<Box<S>>>,
+    timeouts: StreamTimeouts,
+    idle: Option<Pin<Box<Sleep>>>,
+    deadline: Option<Pin<Box<Sleep>>>,
+    last_event: Instant,
+    pings: u64,
+}
+
+impl<S> Watchdog<S> {
+    /// Enforce timeouts on a stream, starting the clocks now
+    pub fn new(stream: S, timeouts: StreamTimeouts) -> Self {
+        Self::started_at(stream, timeouts, Instant::now())
+    }
+
+    /// Enforce timeouts on a stream whose request was sent at `started`
+    pub(crate) fn started_at(stream: S, timeouts: StreamTimeouts, started: Instant) -> Self {
+        Self {
+            inner: Some(Box::pin(stream)),
+            timeouts,
+            idle: timeouts
+                .idle
+                .map(|idle| Box::pin(sleep_until(Instant::now() + idle))),
+            deadline: timeouts
+                .deadline
+                .map(|deadline| Box::pin(sleep_until(started + deadline))),
+            last_event: Instant::now(),
+            pings: 0,
+        }
+    }
+
+    /// Number of ping events received so far
+    pub fn pings(&self) -> u64 {
+        self.pings
+    }
+
+    /// Time elapsed since the last event
+    pub fn since_last_event(&self) -> Duration {
+        self.last_event.elapsed()
+    }
+
+    fn expire(&mut self, error: MessageError) -> Poll<Option<Result<StreamEvent, MessageError>>> {
+        self.inner = None;
+        self.idle = None;
+        self.deadline = None;
+        Poll::Ready(Some(Err(error)))
+    }
+}
+
+impl<S> Stream for Watchdog<S>
+where
+    S: Stream<Item = Result<StreamEvent, MessageError>>,
+{
+    type Item = Result<StreamEvent, MessageError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let Some(inner) = this.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        match inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(event)) => {
+                this.last_event = Instant::now();
+                if let Ok(StreamEvent::Ping) = event {
+                    this.pings += 1;
+                }
+                if let (Some(sleep), Some(idle)) = (this.idle.as_mut(), this.timeouts.idle) {
+                    sleep.as_mut().reset(this.last_event + idle);
+                }
+                return Poll::Ready(Some(event));
+            }
+            Poll::Ready(None) => {
+                this.inner = None;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => {}
+        }
+
+        if let Some(deadline) = this.deadline.as_mut() {
+            if deadline.as_mut().poll(cx).is_ready() {
+                let deadline = this.timeouts.deadline.unwrap_or_default();
+                return this.expire(MessageError::DeadlineExceeded(deadline));
+            }
+        }
+        if let Some(idle) = this.idle.as_mut() {
+            if idle.as_mut().poll(cx).is_ready() {
+                let idle = this.timeouts.idle.unwrap_or_default();
+                return this.expire(MessageError::IdleTimeout(idle));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+type StreamItem = Result<StreamEvent, MessageError>;
+
+#[derive(Debug, Default)]
+struct BroadcastState {
+    /// Every item received so far, for subscribers that replay from the start
+    history: Vec<StreamItem>,
+    subscribers: Vec<mpsc::Sender<StreamItem>>,
+    finished: bool,
+    /// Whether the [`StreamBroadcast`] handle is still alive and may add subscribers
+    handle_alive: bool,
+}
+
+/// Fans the events of a message stream out to several subscribers
+///
+/// Created with [`MessageStreamExt::broadcast`]. The stream is polled by a background
+/// task and every item is sent to every subscriber through a bounded buffer, so the
+/// slowest subscriber sets the pace. Subscribers created with
+/// [`subscribe_from_start`](StreamBroadcast::subscribe_from_start) first replay every
+/// item received so far.
+///
+/// The stream is dropped, aborting the request, once it ends or once the handle and
+/// every subscriber have been dropped.
+#[derive(Debug)]
+pub struct StreamBroadcast {
+    state: Arc<Mutex<BroadcastState>>,
+    capacity: usize,
+}
+
+impl StreamBroadcast {
+    fn spawn<S>(stream: S, capacity: usize) -> Self
+    where
+        S: Stream<Item = StreamItem> + Send + 'static,
+    {
+        let state = Arc::new(Mutex::new(BroadcastState {
+            handle_alive: true,
+            ..Default::default()
+        }));
+        tokio::spawn(Self::pump(stream, state.clone()));
+        Self {
+            state,
+            capacity: capacity.max(1),
+        }
+    }
+
+    async fn pump<S>(stream: S, state: Arc<Mutex<BroadcastState>>)
+    where
+        S: Stream<Item = StreamItem> + Send,
+    {
+        let mut stream = std::pin::pin!(stream);
+        while let Some(item) = stream.next().await {
+            let subscribers = {
+                let mut state = lock(&state);
+                state.history.push(item.clone());
+                state.subscribers.clone()
+            };
+
+            for subscriber in &subscribers {
+                // A closed channel means the subscriber was dropped
+                let _ = subscriber.send(item.clone()).await;
+            }
+
+            let mut state = lock(&state);
+            state
+                .subscribers
+                .retain(|subscriber| !subscriber.is_closed());
+            if state.subscribers.is_empty() && !state.handle_alive {
+                break;
+            }
+        }
+
+        let mut state = lock(&state);
+        state.finished = true;
+        state.subscribers.clear();
+    }
+
+    /// Subscribe to the items received from now on
+    pub fn subscribe(&self) -> BroadcastSubscriber {
+        self.subscriber(false)
+    }
+
+    /// Subscribe to every item, starting from the beginning of the message
+    pub fn subscribe_from_start(&self) -> BroadcastSubscriber {
+        self.subscriber(true)
+    }
+
+    /// Whether the underlying stream has ended
+    pub fn is_finished(&self) -> bool {
+        lock(&self.state).finished
+    }
+
+    fn subscriber(&self, replay: bool) -> BroadcastSubscriber {
+        let (sender, receiver) = mpsc::channel(self.capacity);
+        let mut state = lock(&self.state);
+        let replay = if replay {
+            state.history.iter().cloned().collect()
+        } else {
+            VecDeque::new()
+        };
+        if !state.finished {
+            state.subscribers.push(sender);
+        }
+        BroadcastSubscriber { replay, receiver }
+    }
+}
+
+impl Drop for StreamBroadcast {
+    fn drop(&mut self) {
+        lock(&self.state).handle_alive = false;
+    }
+}
+
+fn lock(state: &Mutex<BroadcastState>) -> MutexGuard<'_, BroadcastState> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Stream of the items of a [`StreamBroadcast`]
+#[derive(Debug)]
+pub struct BroadcastSubscriber {
+    replay: VecDeque<StreamItem>,
+    receiver: mpsc::Receiver<StreamItem>,
+}
+
+impl Stream for BroadcastSubscriber {
+    type Item = StreamItem;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(item) = self.replay.pop_front() {
+            return Poll::Ready(Some(item));
+        }
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Adapters for the stream returned by `create_message_streaming`
+pub trait MessageStreamExt: Stream<Item = Result<StreamEvent, MessageError>> + Sized {
+    /// Yield only the text of the response
+    ///
+    /// `error` events are yielded as errors; every other non-text event is skipped.
+    fn text_stream(self) -> impl Stream<Item = Result<String, MessageError>> {
+        self.filter_map(|event| {
+            future::ready(match event {
+                Ok(StreamEvent::ContentBlockDelta {
+                    delta: ContentBlockDelta::TextDelta { text },
+                    ..
+                }) => Some(Ok(text)),
+                Ok(StreamEvent::Error { error }) => Some(Err(error.into())),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            })
+        })
+    }
+
+    /// Start building callbacks for the events of the stream
+    fn callbacks<'a>(self) -> StreamCallbacks<'a, Self> {
+        StreamCallbacks::new(self)
+    }
+
+    /// Expose the text of the response as an [`AsyncRead`]
+    ///
+    /// Stream errors are surfaced as [`io::Error`]s wrapping the `MessageError`.
+    fn into_async_read(self) -> impl AsyncRead + Unpin {
+        let bytes = self
+            .text_stream()
+            .map(|text| text.map(Bytes::from).map_err(io::Error::other));
+        StreamReader::new(Box::pin(bytes))
+    }
+
+    /// Enforce an idle timeout between events and an overall deadline
+    fn with_timeouts(self, timeouts: StreamTimeouts) -> Watchdog<Self> {
+        Watchdog::new(self, timeouts)
+    }
+
+    /// Poll the stream in a background task and fan its events out to subscribers
+    ///
+    /// Each subscriber buffers up to `capacity` events. Must be called within a Tokio
+    /// runtime.
+    fn broadcast(self, capacity: usize) -> StreamBroadcast
+    where
+        Self: Send + 'static,
+    {
+        StreamBroadcast::spawn(self, capacity)
+    }
+}
+
+impl<S> MessageStreamExt for S where S: Stream<Item = Result<StreamEvent, MessageError>> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::message::StopReason;
+    use tokio::io::AsyncReadExt;
+
+    fn events() -> Vec<Result<StreamEvent, MessageError>> {
+        [
+            r#"{"type":"message_start","message":{"id":"msg_1","type":"message","role":"assistant","content":[],"model":"claude-sonnet-4-5","stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":25,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"ping"}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Let me "}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"check."}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"get_weather","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"city\": "}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"Paris\"}"}}"#,
+            r#"{"type":"content_block_stop","index":1}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use","stop_sequence":null},"usage":{"output_tokens":30}}"#,
+            r#"{"type":"message_stop"}"#,
+        ]
+        .iter()
+        .map(|data| Ok(serde_json::from_str(data).unwrap()))
+        .collect()
+    }
+
+    #[test]
+    fn accumulator_rebuilds_message() {
+        let mut accumulator = MessageAccumulator::new();
+        for event in events() {
+            accumulator.push(&event.unwrap()).unwrap();
+        }
+        assert!(accumulator.is_finished());
+
+        let message = accumulator.into_message().unwrap();
+        assert_eq!(message.stop_reason, Some(StopReason::ToolUse));
+        assert_eq!(message.usage.input_tokens, 25);
+        assert_eq!(message.usage.output_tokens, 30);
+        assert_eq!(message.content[0], ContentBlock::text("Let me check."));
+        assert_eq!(
+            message.content[1],
+            ContentBlock::ToolUse {
+                id: "toolu_1".to_string(),
+                name: "get_weather".to_string(),
+                input: serde_json::json!({"city": "Paris"}),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn text_stream_yields_text_and_errors() {
+        let mut events = events();
+        events.insert(
+            4,
+            Ok(serde_json::from_str(
+                r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+            )
+            .unwrap()),
+        );
+
+        let chunks: Vec<_> = futures_util::stream::iter(events)
+            .text_stream()
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].as_ref().unwrap(), "Let me ");
+        assert!(chunks[1].is_err());
+        assert_eq!(chunks[2].as_ref().unwrap(), "check.");
+    }
+
+    #[tokio::test]
+    async fn callbacks_receive_events() {
+        let mut text = String::new();
+        let mut tools = Vec::new();
+        let mut inputs = Vec::new();
+        let mut stopped = false;
+
+        let message = futures_util::stream::iter(events())
+            .callbacks()
+            .on_text(|chunk| text.push_str(chunk))
+            .on_tool_use(|tool_use| tools.push(tool_use.clone()))
+            .on_tool_input(|tool_use| inputs.push(tool_use.input.clone()))
+            .on_message_stop(|_| stopped = true)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(text, "Let me check.");
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].input, serde_json::json!({"city": "Paris"}));
+        assert_eq!(
+            inputs,
+            vec![serde_json::json!({}), serde_json::json!({"city": "Paris"})]
+        );
+        assert!(stopped);
+        assert_eq!(message.content.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn async_read_yields_text() {
+        let mut reader = futures_util::stream::iter(events()).into_async_read();
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await.unwrap();
+        assert_eq!(text, "Let me check.");
+    }
+
+    fn event(data: &str) -> StreamEvent {
+        serde_json::from_str(data).unwrap()
+    }
+
+    fn push_all(splicer: &mut StreamSplicer, events: &[&str]) -> Vec<StreamEvent> {
+        events
+            .iter()
+            .filter_map(|data| splicer.push(event(data)).unwrap())
+            .collect()
+    }
+
+    const START: &str = r#"{"type":"message_start","message":{"id":"msg_1","type":"message","role":"assistant","content":[],"model":"claude-sonnet-4-5","stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":25,"output_tokens":1}}}"#;
+    const TEXT_START: &str =
+        r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#;
+    const STOP: &str = r#"{"type":"content_block_stop","index":0}"#;
+
+    fn text_delta(index: usize, text: &str) -> String {
+        serde_json::json!({
+            "type": "content_block_delta",
+            "index": index,
+            "delta": {"type": "text_delta", "text": text},
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn splicer_continues_open_text_block() {
+        let mut splicer = StreamSplicer::default();
+        let first = text_delta(0, "Hello, ");
+        assert_eq!(
+            push_all(&mut splicer, &[START, TEXT_START, &first]).len(),
+            3
+        );
+        assert_eq!(splicer.prefill().as_deref(), Some("Hello,"));
+        assert!(splicer.output_tokens() > 1);
+
+        splicer.resume();
+        let second = text_delta(0, " world");
+        let forwarded = push_all(
+            &mut splicer,
+            &[
+                START,
+                TEXT_START,
+                &second,
+                STOP,
+                r#"{"type":"message_stop"}"#,
+            ],
+        );
+        assert_eq!(forwarded.len(), 3);
+        assert!(matches!(
+            &forwarded[0],
+            StreamEvent::ContentBlockDelta {
+                index: 0,
+                delta: ContentBlockDelta::TextDelta { text },
+            } if text == "world"
+        ));
+        assert!(splicer.is_finished());
+        assert_eq!(
+            splicer.message().unwrap().content,
+            vec![ContentBlock::text("Hello, world")]
+        );
+    }
+
+    #[test]
+    fn splicer_shifts_blocks_after_closed_block() {
+        let mut splicer = StreamSplicer::default();
+        let first = text_delta(0, "Intro.");
+        push_all(&mut splicer, &[START, TEXT_START, &first, STOP]);
+
+        splicer.resume();
+        let second = text_delta(0, "More.");
+        let forwarded = push_all(&mut splicer, &[START, TEXT_START, &second, STOP]);
+        assert!(matches!(
+            forwarded[0],
+            StreamEvent::ContentBlockStart { index: 1, .. }
+        ));
+        assert_eq!(splicer.message().unwrap().content.len(), 2);
+    }
+
+    #[test]
+    fn splicer_refuses_to_prefill_tool_use() {
+        let mut splicer = StreamSplicer::default();
+        assert_eq!(splicer.prefill().as_deref(), Some(""));
+        push_all(
+            &mut splicer,
+            &[
+                START,
+                r#"{"type":"content_block_start","index":0,"content_block":{"type":"tool_use","id":"toolu_1","name":"get_weather","input":{}}}"#,
+            ],
+        );
+        assert!(splicer.prefill().is_none());
+    }
+
+    #[tokio::test]
+    async fn broadcast_fans_out_and_replays() {
+        let (sender, receiver) = mpsc::channel(16);
+        let stream = channel_stream(receiver);
+        let broadcast = stream.broadcast(2);
+
+        let live = broadcast.subscribe();
+        let events = events();
+        sender.send(events[0].clone()).await.unwrap();
+        sender.send(events[1].clone()).await.unwrap();
+
+        let mut live = live;
+        assert!(matches!(
+            live.next().await,
+            Some(Ok(StreamEvent::MessageStart { .. }))
+        ));
+
+        let late = broadcast.subscribe_from_start();
+        for event in &events[2..] {
+            sender.send(event.clone()).await.unwrap();
+        }
+        drop(sender);
+
+        let (live, late): (Vec<_>, Vec<_>) = tokio::join!(live.collect(), late.collect());
+        assert_eq!(live.len(), events.len() - 1);
+        assert_eq!(late.len(), events.len());
+        assert!(broadcast.is_finished());
+
+        let after_end: Vec<_> = broadcast.subscribe_from_start().collect().await;
+        assert_eq!(after_end.len(), events.len());
+        assert_eq!(broadcast.subscribe().collect::<Vec<_>>().await.len(), 0);
+    }
+
+    fn channel_stream(
+        mut receiver: mpsc::Receiver<StreamItem>,
+    ) -> impl Stream<Item = StreamItem> + Send + 'static {
+        futures_util::stream::poll_fn(move |cx| receiver.poll_recv(cx))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watchdog_enforces_idle_timeout() {
+        let timeouts = StreamTimeouts::new().with_idle_timeout(Duration::from_millis(20));
+        let mut stream = futures_util::stream::iter(events())
+            .chain(futures_util::stream::pending())
+            .with_timeouts(timeouts);
+
+        for _ in 0..events().len() {
+            assert!(stream.next().await.unwrap().is_ok());
+        }
+        assert_eq!(stream.pings(), 1);
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(MessageError::IdleTimeout(_)))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watchdog_pings_keep_stream_alive_until_deadline() {
+        let pings = futures_util::stream::unfold((), |_| async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Some((Ok(StreamEvent::Ping), ()))
+        });
+        let timeouts = StreamTimeouts::new()
+            .with_idle_timeout(Duration::from_millis(50))
+            .with_deadline(Duration::from_millis(100));
+        let results: Vec<_> = pings.with_timeouts(timeouts).collect().await;
+
+        let (last, pings) = results.split_last().unwrap();
+        assert!(pings.len() >= 3);
+        assert!(pings.iter().all(|event| event.is_ok()));
+        assert!(matches!(last, Err(MessageError::DeadlineExceeded(_))));
+    }
+}
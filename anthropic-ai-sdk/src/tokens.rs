@@ -0,0 +1,476 @@
+//! Offline token estimation
+//!
+//! This module provides [`TokenEstimator`], a local and conservative approximation of
+//! the input token count of a request. It never touches the network, which makes it
+//! suitable for routing decisions, rate limiting and context trimming where a
+//! `count_tokens` call per request would be too slow or too expensive.
+//!
+//! The estimate is built from simple heuristics: characters per token for text,
+//! serialized JSON size for tool definitions and tool calls, and pixel dimensions for
+//! images. It can be calibrated against real `count_tokens` responses, and clones of an
+//! estimator share their calibration.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::tokens::TokenEstimator;
+//! use anthropic_ai_sdk::types::message::{
+//!     CreateMessageParams, Message, MessageError, RequiredMessageParams, Role,
+//! };
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+//! let estimator = TokenEstimator::new();
+//!
+//! let params = CreateMessageParams::new(RequiredMessageParams {
+//!     model: "claude-sonnet-4-5".to_string(),
+//!     messages: vec![Message::new_text(Role::User, "Hello, Claude")],
+//!     max_tokens: 1024,
+//! });
+//!
+//! println!("Estimated input tokens: {}", estimator.estimate(&params));
+//!
+//! // Occasionally compare against the real count to improve future estimates
+//! let actual = estimator.calibrate_with(&client, &params).await?;
+//! println!("Actual input tokens: {}", actual);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::image::{MAX_LONG_EDGE, MAX_PIXELS, dimensions, estimate_image_tokens, fit_within};
+use crate::types::message::{
+    ContentBlock, CountMessageTokensParams, CreateMessageParams, DocumentSource, ImageSource,
+    Message, MessageClient, MessageContent, MessageError, Tool,
+};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::sync::{Arc, RwLock};
+
+/// Tokens added for every request regardless of its content
+const REQUEST_OVERHEAD: u32 = 8;
+/// Tokens added for every message (role and separators)
+const MESSAGE_OVERHEAD: u32 = 4;
+/// Tokens added for every content block
+const BLOCK_OVERHEAD: u32 = 3;
+/// Tokens added by the tool use system prompt when any tool is provided
+const TOOL_SYSTEM_PROMPT: u32 = 346;
+/// Tokens added for every tool definition
+const TOOL_OVERHEAD: u32 = 10;
+/// Conservative token count for a single PDF page (extracted text plus page image)
+const PDF_TOKENS_PER_PAGE: u32 = 3_000;
+/// Token count used for documents whose content is not available locally
+const UNKNOWN_DOCUMENT_TOKENS: u32 = 10_000;
+
+/// Weight of a new calibration sample in the running average
+const CALIBRATION_WEIGHT: f64 = 0.2;
+/// Smallest heuristic estimate used for calibration; below it the fixed request
+/// overhead dominates the ratio
+const MIN_CALIBRATION_TOKENS: f64 = 50.0;
+/// Largest calibration factor, so that a bad sample cannot inflate every estimate
+const MAX_CALIBRATION_FACTOR: f64 = 4.0;
+
+#[derive(Debug)]
+struct Calibration {
+    /// Ratio between actual and estimated token counts
+    factor: f64,
+    /// Number of samples that contributed to the factor
+    samples: u32,
+}
+
+/// Local, conservative estimator for input token counts
+///
+/// Cloning an estimator is cheap; clones share their calibration so that every
+/// subsystem benefits from samples recorded by any of them.
+#[derive(Debug, Clone)]
+pub struct TokenEstimator {
+    /// Average number of ASCII characters per token
+    chars_per_token: f64,
+    /// Multiplier applied on top of the calibrated estimate
+    safety_margin: f64,
+    calibration: Arc<RwLock<Calibration>>,
+}
+
+impl Default for TokenEstimator {
+    fn default() -> Self {
+        Self {
+            chars_per_token: 3.0,
+            safety_margin: 1.1,
+            calibration: Arc::new(RwLock::new(Calibration {
+                factor: 1.0,
+                samples: 0,
+            })),
+        }
+    }
+}
+
+impl TokenEstimator {
+    /// Create a new estimator with conservative defaults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the average number of ASCII characters per token
+    pub fn with_chars_per_token(mut self, chars_per_token: f64) -> Self {
+        self.chars_per_token = chars_per_token.max(0.5);
+        self
+    }
+
+    /// Set the multiplier applied on top of the calibrated estimate
+    pub fn with_safety_margin(mut self, safety_margin: f64) -> Self {
+        self.safety_margin = safety_margin.max(1.0);
+        self
+    }
+
+    /// Current calibration factor (actual tokens / heuristic tokens)
+    pub fn calibration_factor(&self) -> f64 {
+        self.calibration.read().unwrap().factor
+    }
+
+    /// Number of calibration samples recorded so far
+    pub fn calibration_samples(&self) -> u32 {
+        self.calibration.read().unwrap().samples
+    }
+
+    /// Forget all calibration samples
+    pub fn reset_calibration(&self) {
+        let mut calibration = self.calibration.write().unwrap();
+        calibration.factor = 1.0;
+        calibration.samples = 0;
+    }
+
+    /// Estimates the input tokens of a message creation request
+    pub fn estimate(&self, params: &CreateMessageParams) -> u32 {
+        self.finish(self.raw_request(
+            params.system.as_deref(),
+            &params.messages,
+            params.tools.as_deref(),
+        ))
+    }
+
+    /// Estimates the input tokens of a token counting request
+    pub fn estimate_count_params(&self, params: &CountMessageTokensParams) -> u32 {
+        self.finish(self.raw_request(
+            params.system.as_deref(),
+            &params.messages,
+            params.tools.as_deref(),
+        ))
+    }
+
+    /// Estimates the tokens of a single message
+    pub fn estimate_message(&self, message: &Message) -> u32 {
+        self.finish(self.raw_message(message))
+    }
+
+    /// Estimates the tokens of a piece of text
+    pub fn estimate_text(&self, text: &str) -> u32 {
+        self.finish(self.raw_text(text))
+    }
+
+    /// Records the actual token count of a request to improve future estimates
+    ///
+    /// `actual_tokens` should come from a `count_tokens` response (or the
+    /// `input_tokens` usage of a response) for the same request. Empty counts and very
+    /// small requests are ignored. The factor never drops below 1.0, so estimates stay
+    /// conservative, and never exceeds 4.0.
+    pub fn calibrate(&self, params: &CreateMessageParams, actual_tokens: u32) {
+        let raw = self.raw_request(
+            params.system.as_deref(),
+            &params.messages,
+            params.tools.as_deref(),
+        );
+        self.record_sample(raw, actual_tokens);
+    }
+
+    /// Counts the tokens of a request through the API and calibrates with the result
+    ///
+    /// Returns the actual token count.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageError` if the `count_tokens` request fails.
+    pub async fn calibrate_with<C>(
+        &self,
+        client: &C,
+        params: &CreateMessageParams,
+    ) -> Result<u32, MessageError>
+    where
+        C: MessageClient + Sync,
+    {
+        let count_params = CountMessageTokensParams::from(params);
+        let response = client.count_tokens(Some(&count_params)).await?;
+        self.calibrate(params, response.input_tokens);
+        Ok(response.input_tokens)
+    }
+
+    fn record_sample(&self, raw: f64, actual_tokens: u32) {
+        if actual_tokens == 0 || raw < MIN_CALIBRATION_TOKENS {
+            return;
+        }
+        let ratio = (f64::from(actual_tokens) / raw).clamp(1.0, MAX_CALIBRATION_FACTOR);
+        let mut calibration = self.calibration.write().unwrap();
+        calibration.factor = if calibration.samples == 0 {
+            ratio
+        } else {
+            calibration.factor * (1.0 - CALIBRATION_WEIGHT) + ratio * CALIBRATION_WEIGHT
+        };
+        calibration.samples += 1;
+    }
+
+    fn finish(&self, raw: f64) -> u32 {
+        let estimate = raw * self.calibration_factor() * self.safety_margin;
+        estimate.ceil().min(u32::MAX as f64) as u32
+    }
+
+    fn raw_request(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: Option<&[Tool]>,
+    ) -> f64 {
+        let mut total = REQUEST_OVERHEAD as f64;
+
+        if let Some(system) = system {
+            total += self.raw_text(system);
+        }
+
+        if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+            total += TOOL_SYSTEM_PROMPT as f64;
+            for tool in tools {
+                total += TOOL_OVERHEAD as f64 + self.raw_json(tool);
+            }
+        }
+
+        total + messages.iter().map(|m| self.raw_message(m)).sum::<f64>()
+    }
+
+    fn raw_message(&self, message: &Message) -> f64 {
+        let content = match &message.content {
+            MessageContent::Text { content } => self.raw_text(content),
+            MessageContent::Blocks { content } => content
+                .iter()
+                .map(|block| BLOCK_OVERHEAD as f64 + self.raw_block(block))
+                .sum(),
+        };
+        MESSAGE_OVERHEAD as f64 + content
+    }
+
+    fn raw_block(&self, block: &ContentBlock) -> f64 {
+        match block {
+            ContentBlock::Text { text } => self.raw_text(text),
+            ContentBlock::Image { source } => image_source_tokens(source) as f64,
+            ContentBlock::ToolUse { name, input, .. } => self.raw_text(name) + self.raw_json(input),
+            ContentBlock::ToolResult { content, .. } => self.raw_text(content),
+            ContentBlock::Thinking { thinking, .. } => self.raw_text(thinking),
+            ContentBlock::RedactedThinking { data } => self.raw_text(data),
+            ContentBlock::Document {
+                source,
+                title,
+                context,
+            } => {
+                let metadata = title.as_deref().map_or(0.0, |t| self.raw_text(t))
+                    + context.as_deref().map_or(0.0, |c| self.raw_text(c));
+                metadata + self.raw_document(source)
+            }
+        }
+    }
+
+    fn raw_document(&self, source: &DocumentSource) -> f64 {
+        match source {
+            DocumentSource::Text { data, .. } => self.raw_text(data),
+            DocumentSource::Base64 { data, .. } => match STANDARD.decode(data) {
+                Ok(bytes) => match count_pdf_pages(&bytes) {
+                    0 => bytes.len() as f64 / self.chars_per_token,
+                    pages => f64::from(pages) * f64::from(PDF_TOKENS_PER_PAGE),
+                },
+                Err(_) => data.len() as f64 / self.chars_per_token,
+            },
+            DocumentSource::Url { .. } | DocumentSource::File { .. } => {
+                UNKNOWN_DOCUMENT_TOKENS as f64
+            }
+        }
+    }
+
+    fn raw_json<T: serde::Serialize + ?Sized>(&self, value: &T) -> f64 {
+        serde_json::to_string(value)
+            .map(|json| self.raw_text(&json))
+            .unwrap_or_default()
+    }
+
+    /// ASCII text is estimated by characters per token; every other character is
+    /// counted as a full token, which is conservative for CJK and emoji.
+    fn raw_text(&self, text: &str) -> f64 {
+        let (ascii, other) = text.chars().fold((0usize, 0usize), |(ascii, other), c| {
+            if c.is_ascii() {
+                (ascii + 1, other)
+            } else {
+                (ascii, other + 1)
+            }
+        });
+        ascii as f64 / self.chars_per_token + other as f64
+    }
+}
+
+/// Estimates the tokens of an image, assuming the maximum size when the image data
+/// is not available locally or cannot be read
+fn image_source_tokens(source: &ImageSource) -> u32 {
+    let fallback = estimate_image_tokens(MAX_PIXELS, 1);
+    if source.type_ != "base64" {
+        return fallback;
+    }
+
+    STANDARD
+        .decode(&source.data)
+        .ok()
+        .and_then(|bytes| dimensions(&bytes))
+        .map(|(width, height)| {
+            let (width, height) = fit_within(width, height, MAX_LONG_EDGE, MAX_PIXELS);
+            estimate_image_tokens(width, height)
+        })
+        .unwrap_or(fallback)
+}
+
+/// Counts the page objects in a PDF without parsing it
+fn count_pdf_pages(bytes: &[u8]) -> u32 {
+    if !bytes.starts_with(b"%PDF") {
+        return 0;
+    }
+    const MARKER: &[u8] = b"/Type";
+    let mut pages = 0;
+    let mut i = 0;
+    while let Some(offset) = bytes[i..].windows(MARKER.len()).position(|w| w == MARKER) {
+        i += offset + MARKER.len();
+        let rest = &bytes[i..];
+        let name = rest.trim_ascii_start();
+        if name.starts_with(b"/Page") && !name.starts_with(b"/Pages") {
+            pages += 1;
+        }
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::message::{RequiredMessageParams, Role};
+
+    fn params(messages: Vec<Message>) -> CreateMessageParams {
+        CreateMessageParams::new(RequiredMessageParams {
+            model: "claude-sonnet-4-5".to_string(),
+            messages,
+            max_tokens: 1024,
+        })
+    }
+
+    #[test]
+    fn longer_text_costs_more() {
+        let estimator = TokenEstimator::new();
+        let short = estimator.estimate(&params(vec![Message::new_text(Role::User, "Hi")]));
+        let long = estimator.estimate(&params(vec![Message::new_text(
+            Role::User,
+            "Hi ".repeat(1000),
+        )]));
+        assert!(long > short);
+        assert!(long >= 1000);
+    }
+
+    #[test]
+    fn non_ascii_text_counts_one_token_per_character() {
+        let estimator = TokenEstimator::new().with_safety_margin(1.0);
+        assert_eq!(estimator.estimate_text("こんにちは"), 5);
+        assert_eq!(estimator.estimate_text("abcdef"), 2);
+    }
+
+    #[test]
+    fn tools_add_system_prompt_overhead() {
+        let estimator = TokenEstimator::new();
+        let base = params(vec![Message::new_text(Role::User, "Hi")]);
+        let with_tools = params(vec![Message::new_text(Role::User, "Hi")]).with_tools(vec![Tool {
+            name: "get_weather".to_string(),
+            description: Some("Get the weather".to_string()),
+            input_schema: serde_json::json!({"type": "object"}),
+        }]);
+        assert!(estimator.estimate(&with_tools) > estimator.estimate(&base) + TOOL_SYSTEM_PROMPT);
+    }
+
+    #[test]
+    fn images_are_estimated_by_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&750u32.to_be_bytes());
+        png.extend_from_slice(&100u32.to_be_bytes());
+        let readable = ImageSource {
+            type_: "base64".to_string(),
+            media_type: "image/png".to_string(),
+            data: STANDARD.encode(&png),
+        };
+        assert_eq!(image_source_tokens(&readable), 100);
+
+        let unreadable = ImageSource {
+            type_: "base64".to_string(),
+            media_type: "image/png".to_string(),
+            data: "not base64!".to_string(),
+        };
+        assert_eq!(
+            image_source_tokens(&unreadable),
+            estimate_image_tokens(MAX_PIXELS, 1)
+        );
+    }
+
+    #[test]
+    fn counts_pdf_pages() {
+        let pdf = b"%PDF-1.7 /Type /Pages /Type /Page /Type/Page";
+        assert_eq!(count_pdf_pages(pdf), 2);
+        assert_eq!(count_pdf_pages(b"/Type /Page"), 0);
+    }
+
+    #[test]
+    fn huge_pdfs_do_not_overflow() {
+        let pages = 1_500_000;
+        let pdf = format!("%PDF-1.7 {}", "/Type /Page ".repeat(pages));
+        let source = DocumentSource::Base64 {
+            media_type: "application/pdf".to_string(),
+            data: STANDARD.encode(pdf),
+        };
+        let tokens = TokenEstimator::new().raw_document(&source);
+        assert_eq!(tokens, pages as f64 * f64::from(PDF_TOKENS_PER_PAGE));
+    }
+
+    #[test]
+    fn calibration_is_shared_between_clones() {
+        let estimator = TokenEstimator::new().with_safety_margin(1.0);
+        let shared = estimator.clone();
+        let request = params(vec![Message::new_text(
+            Role::User,
+            "Hello there ".repeat(20),
+        )]);
+
+        let before = estimator.estimate(&request);
+        shared.calibrate(&request, before * 2);
+
+        assert_eq!(estimator.calibration_samples(), 1);
+        let after = estimator.estimate(&request);
+        assert!(after >= before * 2 - 1 && after <= before * 2 + 1);
+
+        estimator.reset_calibration();
+        assert_eq!(shared.calibration_factor(), 1.0);
+    }
+
+    #[test]
+    fn calibration_ignores_degenerate_samples() {
+        let estimator = TokenEstimator::new();
+        let tiny = params(vec![Message::new_text(Role::User, "Hi")]);
+        estimator.calibrate(&tiny, 100);
+        let request = params(vec![Message::new_text(
+            Role::User,
+            "Hello there ".repeat(20),
+        )]);
+        estimator.calibrate(&request, 0);
+        assert_eq!(estimator.calibration_samples(), 0);
+
+        estimator.calibrate(&request, 1);
+        assert_eq!(estimator.calibration_factor(), 1.0);
+        estimator.reset_calibration();
+        estimator.calibrate(&request, 1_000_000);
+        assert_eq!(estimator.calibration_factor(), MAX_CALIBRATION_FACTOR);
+    }
+}
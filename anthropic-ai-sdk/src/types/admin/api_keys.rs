@@ -10,6 +10,7 @@ use super::workspace_members::{
     GetWorkspaceMemberResponse, ListWorkspaceMembersParams, ListWorkspaceMembersResponse,
 };
 use super::workspaces::{GetWorkspaceResponse, ListWorkspacesParams, ListWorkspacesResponse};
+use crate::client::RequestError;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -35,6 +36,8 @@ impl From<String> for AdminError {
     }
 }
 
+impl RequestError for AdminError {}
+
 #[async_trait]
 pub trait AdminClient {
     async fn list_api_keys<'a>(
@@ -269,7 +272,7 @@ pub struct ApiKey {
 }
 
 /// Parameters for updating an API key
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Default)]
 pub struct AdminUpdateApiKeyParams {
     /// Name of the API key
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -282,10 +285,7 @@ pub struct AdminUpdateApiKeyParams {
 impl AdminUpdateApiKeyParams {
     /// Create a new UpdateApiKeyParams with default values
     pub fn new() -> Self {
-        Self {
-            name: None,
-            status: None,
-        }
+        Self::default()
     }
 
     /// Set the name of the API key
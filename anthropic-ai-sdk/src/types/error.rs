@@ -0,0 +1,151 @@
+//! API error types
+//!
+//! This module contains the error taxonomy shared by every API. Errors are reported in
+//! the same shape whether they are returned as the body of a failed request or as an
+//! `error` event in the middle of a stream.
+//!
+//! ```json
+//! {"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}
+//! ```
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Type of an error returned by the API
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ApiErrorKind {
+    /// There was an issue with the format or content of the request (400)
+    InvalidRequest,
+    /// There is an issue with the API key (401)
+    Authentication,
+    /// The API key does not have permission to use the resource (403)
+    Permission,
+    /// The requested resource was not found (404)
+    NotFound,
+    /// The request exceeds the maximum allowed number of bytes (413)
+    RequestTooLarge,
+    /// The account has hit a rate limit (429)
+    RateLimit,
+    /// An unexpected error occurred internal to the API (500)
+    Api,
+    /// The API is temporarily overloaded (529)
+    Overloaded,
+    /// An error type this SDK does not know about yet
+    Other(String),
+}
+
+impl ApiErrorKind {
+    /// Parse the `type` field of an API error
+    pub fn from_type(type_: &str) -> Self {
+        match type_ {
+            "invalid_request_error" => Self::InvalidRequest,
+            "authentication_error" => Self::Authentication,
+            "permission_error" => Self::Permission,
+            "not_found_error" => Self::NotFound,
+            "request_too_large" => Self::RequestTooLarge,
+            "rate_limit_error" => Self::RateLimit,
+            "api_error" => Self::Api,
+            "overloaded_error" => Self::Overloaded,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    /// The `type` field of the error as sent by the API
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::InvalidRequest => "invalid_request_error",
+            Self::Authentication => "authentication_error",
+            Self::Permission => "permission_error",
+            Self::NotFound => "not_found_error",
+            Self::RequestTooLarge => "request_too_large",
+            Self::RateLimit => "rate_limit_error",
+            Self::Api => "api_error",
+            Self::Overloaded => "overloaded_error",
+            Self::Other(other) => other,
+        }
+    }
+
+    /// Whether a request failing with this error may succeed if retried later
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::RateLimit | Self::Api | Self::Overloaded)
+    }
+}
+
+impl fmt::Display for ApiErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for ApiErrorKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ApiErrorKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let type_ = String::deserialize(deserializer)?;
+        Ok(Self::from_type(&type_))
+    }
+}
+
+/// Error reported by the API
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiErrorDetail {
+    /// Type of the error
+    #[serde(rename = "type")]
+    pub kind: ApiErrorKind,
+    /// Human readable description of the error
+    pub message: String,
+}
+
+impl ApiErrorDetail {
+    /// Parse the body of a failed request
+    ///
+    /// Returns `None` if the body is not an API error.
+    pub fn parse(body: &str) -> Option<Self> {
+        serde_json::from_str::<ApiErrorResponse>(body)
+            .ok()
+            .map(|response| response.error)
+    }
+}
+
+impl fmt::Display for ApiErrorDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+/// Body of a failed request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiErrorResponse {
+    /// Type of the response (always "error")
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// The error
+    pub error: ApiErrorDetail,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_error_bodies() {
+        let body = r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        let detail = ApiErrorDetail::parse(body).unwrap();
+        assert_eq!(detail.kind, ApiErrorKind::Overloaded);
+        assert!(detail.kind.is_retryable());
+        assert_eq!(detail.to_string(), "overloaded_error: Overloaded");
+
+        let body = r#"{"type":"error","error":{"type":"billing_error","message":"No credit"}}"#;
+        let detail = ApiErrorDetail::parse(body).unwrap();
+        assert_eq!(
+            detail.kind,
+            ApiErrorKind::Other("billing_error".to_string())
+        );
+        assert!(!detail.kind.is_retryable());
+
+        assert!(ApiErrorDetail::parse("Bad Gateway").is_none());
+    }
+}
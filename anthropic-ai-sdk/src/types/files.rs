@@ -1,5 +1,6 @@
 //! Types for the Files API
 
+use crate::client::RequestError;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -29,6 +30,8 @@ impl From<String> for FileError {
     }
 }
 
+impl RequestError for FileError {}
+
 /// Parameters for listing files
 #[derive(Debug, Serialize, Default)]
 pub struct ListFilesParams {
@@ -1,19 +1,85 @@
+use crate::client::RequestError;
+use crate::types::error::{ApiErrorDetail, ApiErrorKind};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Error types for the Messages API
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum MessageError {
     #[error("API request failed: {0}")]
     RequestFailed(String),
     #[error("API error: {0}")]
     ApiError(String),
+    /// Error reported by the API, either as a failed request or mid-stream
+    #[error("API error: {0}")]
+    Api(ApiErrorDetail),
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
+    /// No stream event was received within the idle timeout
+    #[error("Stream idle for more than {0:?}")]
+    IdleTimeout(Duration),
+    /// The stream did not complete before its deadline
+    #[error("Stream did not complete within {0:?}")]
+    DeadlineExceeded(Duration),
+}
+
+impl MessageError {
+    /// Type of the API error, if the error was reported by the API
+    pub fn kind(&self) -> Option<&ApiErrorKind> {
+        match self {
+            MessageError::Api(detail) => Some(&detail.kind),
+            _ => None,
+        }
+    }
+
+    /// The error as the API reports it
+    ///
+    /// API errors keep their type; other errors are reported as `api_error`.
+    pub fn error_detail(&self) -> ApiErrorDetail {
+        match self {
+            MessageError::Api(detail) => detail.clone(),
+            other => ApiErrorDetail {
+                kind: ApiErrorKind::Api,
+                message: other.to_string(),
+            },
+        }
+    }
+
+    /// Whether the request may succeed if retried later
+    ///
+    /// Transport failures, timeouts and rate limit, overloaded and internal API errors
+    /// are retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            MessageError::Api(detail) => detail.kind.is_retryable(),
+            MessageError::RequestFailed(_)
+            | MessageError::IdleTimeout(_)
+            | MessageError::DeadlineExceeded(_) => true,
+            MessageError::ApiError(_) | MessageError::BudgetExceeded(_) => false,
+        }
+    }
 }
 
 impl From<String> for MessageError {
     fn from(error: String) -> Self {
-        MessageError::ApiError(error)
+        match ApiErrorDetail::parse(&error) {
+            Some(detail) => MessageError::Api(detail),
+            None => MessageError::ApiError(error),
+        }
+    }
+}
+
+impl RequestError for MessageError {}
+
+impl From<StreamError> for MessageError {
+    fn from(error: StreamError) -> Self {
+        MessageError::Api(ApiErrorDetail {
+            kind: ApiErrorKind::from_type(&error.type_),
+            message: error.message,
+        })
     }
 }
 
@@ -46,7 +112,7 @@ pub struct RequiredMessageParams {
 }
 
 /// Parameters for creating a message
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
 pub struct CreateMessageParams {
     /// Maximum number of tokens to generate
     pub max_tokens: u32,
@@ -155,6 +221,63 @@ impl CreateMessageParams {
     }
 }
 
+/// Limits for continuing a message that stopped at `max_tokens`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContinuationOptions {
+    /// Maximum number of requests, including the first one
+    pub max_rounds: u32,
+    /// Maximum number of output tokens across all requests
+    pub max_total_tokens: Option<u32>,
+}
+
+impl Default for ContinuationOptions {
+    fn default() -> Self {
+        Self {
+            max_rounds: 4,
+            max_total_tokens: None,
+        }
+    }
+}
+
+impl ContinuationOptions {
+    /// Create options allowing up to 4 requests and no token cap
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of requests, including the first one
+    pub fn with_max_rounds(mut self, max_rounds: u32) -> Self {
+        self.max_rounds = max_rounds;
+        self
+    }
+
+    /// Cap the number of output tokens across all requests
+    pub fn with_max_total_tokens(mut self, max_total_tokens: u32) -> Self {
+        self.max_total_tokens = Some(max_total_tokens);
+        self
+    }
+
+    /// `max_tokens` for the next request, or `None` if no further request may be sent
+    /// after `rounds` requests that generated `output_tokens` in total
+    pub(crate) fn next_max_tokens(
+        &self,
+        max_tokens: u32,
+        rounds: u32,
+        output_tokens: u32,
+    ) -> Option<u32> {
+        if rounds >= self.max_rounds {
+            return None;
+        }
+        match self.max_total_tokens {
+            Some(cap) => match cap.saturating_sub(output_tokens) {
+                0 => None,
+                remaining => Some(max_tokens.min(remaining)),
+            },
+            None => Some(max_tokens),
+        }
+    }
+}
+
 /// Message in a conversation
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Message {
@@ -166,7 +289,7 @@ pub struct Message {
 }
 
 /// Role of a message sender
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum Role {
     User,
@@ -212,6 +335,15 @@ pub enum ContentBlock {
     /// Redacted thinking
     #[serde(rename = "redacted_thinking")]
     RedactedThinking { data: String },
+    /// Document content (PDF or plain text)
+    #[serde(rename = "document")]
+    Document {
+        source: DocumentSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<String>,
+    },
 }
 
 /// Source of an image
@@ -226,8 +358,56 @@ pub struct ImageSource {
     pub data: String,
 }
 
+/// Source of a document
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum DocumentSource {
+    /// Base64-encoded document data (e.g. a PDF)
+    #[serde(rename = "base64")]
+    Base64 { media_type: String, data: String },
+    /// Plain text document
+    #[serde(rename = "text")]
+    Text { media_type: String, data: String },
+    /// Document fetched from a URL
+    #[serde(rename = "url")]
+    Url { url: String },
+    /// Document uploaded through the Files API
+    #[serde(rename = "file")]
+    File { file_id: String },
+}
+
+impl DocumentSource {
+    /// Create a source for a base64-encoded PDF
+    pub fn pdf(data: impl Into<String>) -> Self {
+        Self::Base64 {
+            media_type: "application/pdf".to_string(),
+            data: data.into(),
+        }
+    }
+
+    /// Create a source for a plain text document
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text {
+            media_type: "text/plain".to_string(),
+            data: text.into(),
+        }
+    }
+
+    /// Create a source for a document hosted at a URL
+    pub fn url(url: impl Into<String>) -> Self {
+        Self::Url { url: url.into() }
+    }
+
+    /// Create a source for a file uploaded through the Files API
+    pub fn file(file_id: impl Into<String>) -> Self {
+        Self::File {
+            file_id: file_id.into(),
+        }
+    }
+}
+
 /// Tool definition
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Tool {
     /// Name of the tool
     pub name: String,
@@ -239,7 +419,7 @@ pub struct Tool {
 }
 
 /// Tool choice configuration
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type")]
 pub enum ToolChoice {
     /// Let model choose whether to use tools
@@ -257,7 +437,7 @@ pub enum ToolChoice {
 }
 
 /// Configuration for extended thinking
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Thinking {
     /// Must be at least 1024 tokens
     pub budget_tokens: usize,
@@ -265,13 +445,13 @@ pub struct Thinking {
     pub type_: ThinkingType,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
 pub enum ThinkingType {
     #[serde(rename = "enabled")]
     Enabled,
 }
 /// Message metadata
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct Metadata {
     /// Custom metadata fields
     #[serde(flatten)]
@@ -279,7 +459,7 @@ pub struct Metadata {
 }
 
 /// Response from creating a message
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CreateMessageResponse {
     /// Content blocks in the response
     pub content: Vec<ContentBlock>,
@@ -300,8 +480,21 @@ pub struct CreateMessageResponse {
     pub usage: Usage,
 }
 
+impl CreateMessageResponse {
+    /// Text of the response: its text blocks, concatenated
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
 /// Reason for stopping message generation
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum StopReason {
     EndTurn,
@@ -311,22 +504,158 @@ pub enum StopReason {
     Refusal,
 }
 
+impl StopReason {
+    /// The stop reason as sent by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::EndTurn => "end_turn",
+            Self::MaxTokens => "max_tokens",
+            Self::StopSequence => "stop_sequence",
+            Self::ToolUse => "tool_use",
+            Self::Refusal => "refusal",
+        }
+    }
+}
+
 /// Token usage statistics
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Usage {
     /// Input tokens used
     pub input_tokens: u32,
     /// Output tokens used
     pub output_tokens: u32,
+    /// Input tokens written to the prompt cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<u32>,
+    /// Input tokens read from the prompt cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<u32>,
+    /// Breakdown of cache writes by TTL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation: Option<CacheCreation>,
+    /// Server tool requests made while generating the response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_tool_use: Option<ServerToolUsage>,
+    /// Service tier the request was processed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<ServiceTier>,
+}
+
+/// Cache writes broken down by TTL
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheCreation {
+    /// Input tokens written with a 5 minute TTL
+    #[serde(default)]
+    pub ephemeral_5m_input_tokens: u32,
+    /// Input tokens written with a 1 hour TTL
+    #[serde(default)]
+    pub ephemeral_1h_input_tokens: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Server tool usage statistics
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServerToolUsage {
+    /// Number of web search requests
+    #[serde(default)]
+    pub web_search_requests: u32,
+}
+
+/// Service tier a request was processed with
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceTier {
+    Standard,
+    Priority,
+    Batch,
+    /// A tier this SDK does not know about yet
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct StreamUsage {
     /// Input tokens used (may be missing in some events)
     #[serde(default)]
     pub input_tokens: u32,
     /// Output tokens used
     pub output_tokens: u32,
+    /// Input tokens written to the prompt cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<u32>,
+    /// Input tokens read from the prompt cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<u32>,
+    /// Server tool requests made while generating the response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_tool_use: Option<ServerToolUsage>,
+}
+
+impl AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        fn add(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+            match (a, b) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+            }
+        }
+
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens = add(
+            self.cache_creation_input_tokens,
+            other.cache_creation_input_tokens,
+        );
+        self.cache_read_input_tokens =
+            add(self.cache_read_input_tokens, other.cache_read_input_tokens);
+        self.cache_creation = match (self.cache_creation, other.cache_creation) {
+            (Some(a), Some(b)) => Some(CacheCreation {
+                ephemeral_5m_input_tokens: a.ephemeral_5m_input_tokens
+                    + b.ephemeral_5m_input_tokens,
+                ephemeral_1h_input_tokens: a.ephemeral_1h_input_tokens
+                    + b.ephemeral_1h_input_tokens,
+            }),
+            (a, b) => a.or(b),
+        };
+        self.server_tool_use = match (self.server_tool_use, other.server_tool_use) {
+            (Some(a), Some(b)) => Some(ServerToolUsage {
+                web_search_requests: a.web_search_requests + b.web_search_requests,
+            }),
+            (a, b) => a.or(b),
+        };
+        self.service_tier = self.service_tier.or(other.service_tier);
+    }
+}
+
+impl From<&Usage> for StreamUsage {
+    fn from(usage: &Usage) -> Self {
+        Self {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cache_creation_input_tokens: usage.cache_creation_input_tokens,
+            cache_read_input_tokens: usage.cache_read_input_tokens,
+            server_tool_use: usage.server_tool_use,
+        }
+    }
+}
+
+impl Usage {
+    /// Update the usage reported in `message_start` with the cumulative usage of a
+    /// `message_delta` event
+    pub fn apply_delta(&mut self, delta: &StreamUsage) {
+        self.output_tokens = delta.output_tokens;
+        if delta.input_tokens > 0 {
+            self.input_tokens = delta.input_tokens;
+        }
+        if delta.cache_creation_input_tokens.is_some() {
+            self.cache_creation_input_tokens = delta.cache_creation_input_tokens;
+        }
+        if delta.cache_read_input_tokens.is_some() {
+            self.cache_read_input_tokens = delta.cache_read_input_tokens;
+        }
+        if delta.server_tool_use.is_some() {
+            self.server_tool_use = delta.server_tool_use;
+        }
+    }
 }
 
 impl Message {
@@ -370,12 +699,107 @@ impl ContentBlock {
             },
         }
     }
+
+    /// Create a new document block
+    pub fn document(source: DocumentSource) -> Self {
+        Self::Document {
+            source,
+            title: None,
+            context: None,
+        }
+    }
+
+    /// Create a new image block from raw image bytes
+    ///
+    /// The image is downscaled to the recommended size, re-encoded and stripped of
+    /// metadata using the default [`ImageOptions`](crate::image::ImageOptions).
+    #[cfg(feature = "image")]
+    pub fn image_from_bytes(bytes: &[u8]) -> Result<Self, crate::image::ImageError> {
+        Self::image_from_bytes_with_options(bytes, &crate::image::ImageOptions::default())
+    }
+
+    /// Create a new image block from raw image bytes using custom options
+    #[cfg(feature = "image")]
+    pub fn image_from_bytes_with_options(
+        bytes: &[u8],
+        options: &crate::image::ImageOptions,
+    ) -> Result<Self, crate::image::ImageError> {
+        crate::image::prepare_image(bytes, options).map(|image| image.into_content_block())
+    }
 }
 
-#[derive(Debug, Serialize, Default)]
+/// Parameters for counting the tokens of a message
+///
+/// Mirrors the fields of [`CreateMessageParams`] that contribute to the input token
+/// count, so a request can be counted exactly as it would be sent:
+///
+/// ```no_run
+/// # use anthropic_ai_sdk::types::message::{CountMessageTokensParams, CreateMessageParams};
+/// let params = CreateMessageParams::default();
+/// let count_params = CountMessageTokensParams::from(&params);
+/// ```
+#[derive(Debug, Serialize, Default, Clone)]
 pub struct CountMessageTokensParams {
+    /// Model to count tokens for
     pub model: String,
+    /// Input messages for the conversation
     pub messages: Vec<Message>,
+    /// System prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    /// Tools that the model may use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+    /// How the model should use tools
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// Configuration for enabling Claude's extended thinking.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<Thinking>,
+}
+
+impl CountMessageTokensParams {
+    /// Create new parameters with only required fields
+    pub fn new(model: impl Into<String>, messages: Vec<Message>) -> Self {
+        Self {
+            model: model.into(),
+            messages,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_system(mut self, system: impl Into<String>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    pub fn with_thinking(mut self, thinking: Thinking) -> Self {
+        self.thinking = Some(thinking);
+        self
+    }
+}
+
+impl From<&CreateMessageParams> for CountMessageTokensParams {
+    fn from(params: &CreateMessageParams) -> Self {
+        Self {
+            model: params.model.clone(),
+            messages: params.messages.clone(),
+            system: params.system.clone(),
+            tools: params.tools.clone(),
+            tool_choice: params.tool_choice.clone(),
+            thinking: params.thinking.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -383,7 +807,7 @@ pub struct CountMessageTokensResponse {
     pub input_tokens: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(tag = "type")]
 pub enum StreamEvent {
     #[serde(rename = "message_start")]
@@ -413,7 +837,7 @@ pub enum StreamEvent {
     Error { error: StreamError },
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct MessageStartContent {
     pub id: String,
     #[serde(rename = "type")]
@@ -426,7 +850,7 @@ pub struct MessageStartContent {
     pub usage: Usage,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(tag = "type")]
 pub enum ContentBlockDelta {
     #[serde(rename = "text_delta")]
@@ -439,15 +863,133 @@ pub enum ContentBlockDelta {
     SignatureDelta { signature: String },
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct MessageDeltaContent {
     pub stop_reason: Option<StopReason>,
     pub stop_sequence: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct StreamError {
     #[serde(rename = "type")]
     pub type_: String,
     pub message: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_params_mirror_create_params() {
+        let params = CreateMessageParams::new(RequiredMessageParams {
+            model: "claude-sonnet-4-5".to_string(),
+            messages: vec![Message::new_blocks(
+                Role::User,
+                vec![
+                    ContentBlock::document(DocumentSource::text("The sky is blue.")),
+                    ContentBlock::text("What color is the sky?"),
+                ],
+            )],
+            max_tokens: 1024,
+        })
+        .with_system("Answer briefly")
+        .with_temperature(0.2)
+        .with_tools(vec![Tool {
+            name: "lookup".to_string(),
+            description: None,
+            input_schema: serde_json::json!({"type": "object"}),
+        }])
+        .with_tool_choice(ToolChoice::Auto)
+        .with_thinking(Thinking {
+            budget_tokens: 2048,
+            type_: ThinkingType::Enabled,
+        });
+
+        let count_params = CountMessageTokensParams::from(&params);
+        let value = serde_json::to_value(&count_params).unwrap();
+
+        assert_eq!(value["model"], "claude-sonnet-4-5");
+        assert_eq!(value["system"], "Answer briefly");
+        assert_eq!(value["tools"][0]["name"], "lookup");
+        assert_eq!(value["tool_choice"]["type"], "auto");
+        assert_eq!(value["thinking"]["budget_tokens"], 2048);
+        assert_eq!(value["messages"][0]["content"][0]["source"]["type"], "text");
+        assert!(value.get("max_tokens").is_none());
+        assert!(value.get("temperature").is_none());
+    }
+
+    #[test]
+    fn document_block_round_trips() {
+        let block = ContentBlock::document(DocumentSource::pdf("JVBERi0="));
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "document",
+                "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERi0="}
+            })
+        );
+        let parsed: ContentBlock = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, block);
+    }
+
+    #[test]
+    fn api_errors_are_typed() {
+        let error = MessageError::from(
+            r#"{"type":"error","error":{"type":"rate_limit_error","message":"Slow down"}}"#
+                .to_string(),
+        );
+        assert_eq!(error.kind(), Some(&ApiErrorKind::RateLimit));
+        assert!(error.is_retryable());
+
+        let error = MessageError::from(StreamError {
+            type_: "invalid_request_error".to_string(),
+            message: "Bad".to_string(),
+        });
+        assert_eq!(error.kind(), Some(&ApiErrorKind::InvalidRequest));
+        assert!(!error.is_retryable());
+
+        let error = MessageError::from("Bad Gateway".to_string());
+        assert!(matches!(error, MessageError::ApiError(_)));
+    }
+
+    #[test]
+    fn continuation_options_limit_rounds_and_tokens() {
+        let options = ContinuationOptions::new()
+            .with_max_rounds(3)
+            .with_max_total_tokens(2500);
+        assert_eq!(options.next_max_tokens(1000, 0, 0), Some(1000));
+        assert_eq!(options.next_max_tokens(1000, 2, 2000), Some(500));
+        assert_eq!(options.next_max_tokens(1000, 3, 2000), None);
+        assert_eq!(options.next_max_tokens(1000, 2, 2500), None);
+        assert_eq!(
+            ContinuationOptions::new().next_max_tokens(1000, 3, 9000),
+            Some(1000)
+        );
+    }
+
+    #[test]
+    fn usage_sums_across_requests() {
+        let mut usage = Usage {
+            input_tokens: 10,
+            output_tokens: 100,
+            cache_read_input_tokens: Some(5),
+            ..Default::default()
+        };
+        usage += &Usage {
+            input_tokens: 110,
+            output_tokens: 50,
+            cache_creation_input_tokens: Some(7),
+            server_tool_use: Some(ServerToolUsage {
+                web_search_requests: 1,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(usage.input_tokens, 120);
+        assert_eq!(usage.output_tokens, 150);
+        assert_eq!(usage.cache_read_input_tokens, Some(5));
+        assert_eq!(usage.cache_creation_input_tokens, Some(7));
+        assert_eq!(usage.server_tool_use.unwrap().web_search_requests, 1);
+    }
+}
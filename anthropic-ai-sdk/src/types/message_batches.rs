@@ -2,8 +2,15 @@
 //!
 //! This module contains the types and functions for the Anthropic Message Batches API.
 //!
+use crate::client::RequestError;
+use crate::types::error::{ApiErrorDetail, ApiErrorResponse};
+use crate::types::message::{CreateMessageParams, CreateMessageResponse};
 use async_trait::async_trait;
+use futures_util::Stream;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io;
+use std::time::Duration;
 use thiserror::Error;
 use time::OffsetDateTime;
 use time::serde::rfc3339;
@@ -15,10 +22,73 @@ pub enum MessageBatchError {
     BatchTooLarge,
     #[error("Batch total size exceeds 256MB")]
     BatchSizeExceeded,
+    /// The request could not be sent, or its response could not be read
     #[error("API request failed: {0}")]
     RequestFailed(String),
     #[error("API error: {0}")]
     ApiError(String),
+    /// A rejecting usage budget that applies to a request of the batch is exceeded
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
+    /// A request of the batch cannot be sent
+    #[error("Invalid batch request: {0}")]
+    InvalidRequest(String),
+    /// A `custom_id` is not 1 to 64 letters, digits, hyphens and underscores
+    #[error("Invalid custom_id {0:?}: expected 1 to 64 letters, digits, '-' or '_'")]
+    InvalidCustomId(String),
+    /// Two requests of the batch share a `custom_id`
+    #[error("Duplicate custom_id {0:?}")]
+    DuplicateCustomId(String),
+    /// Some batches were created before a later one failed
+    #[error("Failed after creating {} batches: {error}", batch_ids.len())]
+    PartiallySubmitted {
+        /// Batches created before the failure
+        batch_ids: Vec<String>,
+        /// The error the next batch failed with
+        error: Box<MessageBatchError>,
+    },
+    /// Polling the batch stopped before it was retrieved once
+    #[error("Polling message batch {0} ended without retrieving it")]
+    PollingEnded(String),
+    /// The batch did not end before the polling deadline
+    #[error("Message batch {0} did not end within {1:?}")]
+    Timeout(String, Duration),
+    /// A batch job store failed to read or write
+    #[error("Batch job store error: {0}")]
+    Store(String),
+    /// The batch has not ended, so its results cannot be retrieved yet
+    #[error("Results of message batch {0} are not available yet")]
+    ResultsNotAvailable(String),
+    /// Reading or writing batch results failed
+    #[error("Batch results I/O error: {0}")]
+    Io(#[from] io::Error),
+    /// A line of a results file is not a valid result
+    #[error("Failed to parse result on line {line}: {source}")]
+    Parse {
+        /// Line number, starting at 1
+        line: usize,
+        /// Why the line is invalid
+        source: serde_json::Error,
+    },
+    /// A result could not be serialized
+    #[error("Failed to serialize result: {0}")]
+    Serialize(#[source] serde_json::Error),
+}
+
+impl MessageBatchError {
+    /// Whether a request failing with this error may succeed if retried later
+    ///
+    /// Requests that could not be sent or whose response could not be read, and rate
+    /// limit, overloaded and internal API errors are retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RequestFailed(_) => true,
+            Self::ApiError(body) => {
+                ApiErrorDetail::parse(body).is_some_and(|detail| detail.kind.is_retryable())
+            }
+            _ => false,
+        }
+    }
 }
 
 impl From<String> for MessageBatchError {
@@ -27,6 +97,12 @@ impl From<String> for MessageBatchError {
     }
 }
 
+impl RequestError for MessageBatchError {
+    fn request_failed(message: String) -> Self {
+        MessageBatchError::RequestFailed(message)
+    }
+}
+
 #[async_trait]
 pub trait MessageBatchClient {
     /// Create a new message batch
@@ -47,16 +123,20 @@ pub trait MessageBatchClient {
         params: &'a RetrieveMessageBatchParams,
     ) -> Result<RetrieveMessageBatchResponse, MessageBatchError>;
 
+    /// Stream the results of an ended message batch
     async fn retrieve_message_batch_results<'a>(
         &'a self,
         params: &'a RetrieveMessageBatchResultsParams,
-    ) -> Result<RetrieveMessageBatchResultsResponse, MessageBatchError>;
+    ) -> Result<
+        impl Stream<Item = Result<MessageBatchResult, MessageBatchError>> + Send + 'a,
+        MessageBatchError,
+    >;
 
     /// Cancel a message batch
     async fn cancel_message_batch<'a>(
         &'a self,
         params: &'a CancelMessageBatchParams,
-    ) -> Result<CancelResponse, MessageBatchError>;
+    ) -> Result<MessageBatch, MessageBatchError>;
 
     /// Delete a message batch.
     ///
@@ -68,8 +148,14 @@ pub trait MessageBatchClient {
     ) -> Result<DeleteResponse, MessageBatchError>;
 }
 
+/// Maximum number of requests in a message batch
+pub const MAX_BATCH_REQUESTS: usize = 100_000;
+
+/// Maximum size of a message batch creation request, in bytes
+pub const MAX_BATCH_BYTES: usize = 256 * 1024 * 1024;
+
 /// Processing status of a Message Batch
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ProcessingStatus {
     InProgress,
@@ -78,7 +164,7 @@ pub enum ProcessingStatus {
 }
 
 /// Request counts for different statuses
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RequestCounts {
     /// Number of requests currently processing
     pub processing: u32,
@@ -92,8 +178,15 @@ pub struct RequestCounts {
     pub expired: u32,
 }
 
+impl RequestCounts {
+    /// Number of requests in the batch
+    pub fn total(&self) -> u32 {
+        self.processing + self.succeeded + self.errored + self.canceled + self.expired
+    }
+}
+
 /// Response structure for Message Batch creation
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageBatch {
     /// Unique identifier for the batch
     pub id: String,
@@ -123,59 +216,154 @@ pub struct MessageBatch {
     pub results_url: Option<String>,
 }
 
+impl MessageBatch {
+    /// Whether processing has ended, so results can be retrieved
+    pub fn is_ended(&self) -> bool {
+        self.processing_status == ProcessingStatus::Ended
+    }
+
+    /// Fraction of the requests that are no longer processing, from 0.0 to 1.0
+    pub fn progress_fraction(&self) -> f64 {
+        let total = self.request_counts.total();
+        if total == 0 {
+            return if self.is_ended() { 1.0 } else { 0.0 };
+        }
+        let done = total - self.request_counts.processing;
+        f64::from(done) / f64::from(total)
+    }
+
+    /// Time left before the batch expires, or `None` if it has ended
+    ///
+    /// Requests still processing when the batch expires end as `expired`.
+    pub fn time_remaining(&self) -> Option<Duration> {
+        if self.is_ended() {
+            return None;
+        }
+        let remaining = self.expires_at - OffsetDateTime::now_utc();
+        Some(Duration::try_from(remaining).unwrap_or(Duration::ZERO))
+    }
+}
+
 /// Parameters for creating a message batch
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CreateMessageBatchParams {
     /// List of message creation requests
     pub requests: Vec<MessageRequest>,
 }
 
 /// Individual message request within a batch
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageRequest {
     /// Custom identifier for tracking this request
     #[serde(skip_serializing_if = "Option::is_none")]
     pub custom_id: Option<String>,
-    /// Request parameters
-    pub params: MessageRequestParams,
-}
-
-/// Parameters for an individual message request
-#[derive(Debug, Serialize)]
-pub struct MessageRequestParams {
-    /// Model to use for this message
-    pub model: String,
-    /// Maximum number of tokens to generate
-    pub max_tokens: u32,
-    /// System prompt for the message
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub system: Option<String>,
-    /// User message content
-    pub messages: Vec<Message>,
-}
-
-/// Message content
-#[derive(Debug, Serialize)]
-pub struct Message {
-    /// Role of the message sender
-    pub role: String,
-    /// Content of the message
-    pub content: String,
+    /// Request parameters, the same as for `create_message`
+    ///
+    /// `stream` must not be set.
+    pub params: CreateMessageParams,
 }
 
 impl CreateMessageBatchParams {
     /// Create a new CreateMessageBatchParams with the given requests
+    ///
+    /// The requests are checked when the batch is created; use
+    /// [`try_new`](Self::try_new) to check them up front, or
+    /// [`BatchPlanner`](crate::batch::planner::BatchPlanner) to split requests that do
+    /// not fit in one batch.
     pub fn new(requests: Vec<MessageRequest>) -> Self {
-        if requests.len() > 100_000 {
-            panic!("Batch size exceeds maximum limit of 100,000 requests");
-        }
         Self { requests }
     }
+
+    /// Create a new CreateMessageBatchParams, checking the requests
+    ///
+    /// # Errors
+    ///
+    /// Returns the first problem found by [`validate`](Self::validate).
+    pub fn try_new(requests: Vec<MessageRequest>) -> Result<Self, MessageBatchError> {
+        let params = Self::new(requests);
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Checks the requests before the batch is created
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if:
+    /// - The batch has more than [`MAX_BATCH_REQUESTS`] requests
+    /// - A request is invalid, see [`MessageRequest::validate`]
+    /// - Two requests share a `custom_id`
+    /// - The serialized batch is larger than [`MAX_BATCH_BYTES`]
+    pub fn validate(&self) -> Result<(), MessageBatchError> {
+        if self.requests.len() > MAX_BATCH_REQUESTS {
+            return Err(MessageBatchError::BatchTooLarge);
+        }
+        let mut custom_ids = HashSet::with_capacity(self.requests.len());
+        for request in &self.requests {
+            let custom_id = request.validate()?;
+            if !custom_ids.insert(custom_id) {
+                return Err(MessageBatchError::DuplicateCustomId(custom_id.to_string()));
+            }
+        }
+        if self.serialized_size()? > MAX_BATCH_BYTES {
+            return Err(MessageBatchError::BatchSizeExceeded);
+        }
+        Ok(())
+    }
+
+    /// Size of the batch serialized as JSON, in bytes
+    pub fn serialized_size(&self) -> Result<usize, MessageBatchError> {
+        json_size(self)
+    }
+
+    /// Read requests from JSONL, one `{"custom_id": ..., "params": ...}` object per line
+    ///
+    /// Blank lines are skipped. The requests are not checked; call
+    /// [`validate`](Self::validate) or submit them through a
+    /// [`BatchPlanner`](crate::batch::planner::BatchPlanner).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anthropic_ai_sdk::types::message_batches::CreateMessageBatchParams;
+    /// use std::io::BufReader;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = std::fs::File::open("requests.jsonl")?;
+    /// let params = CreateMessageBatchParams::from_jsonl(BufReader::new(file))?;
+    /// params.validate()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` naming the line that cannot be read or parsed.
+    pub fn from_jsonl(reader: impl io::BufRead) -> Result<Self, MessageBatchError> {
+        let mut requests = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let number = index + 1;
+            let line = line.map_err(|e| {
+                MessageBatchError::InvalidRequest(format!("Failed to read line {}: {}", number, e))
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let request = serde_json::from_str(&line).map_err(|e| {
+                MessageBatchError::InvalidRequest(format!(
+                    "Failed to parse request on line {}: {}",
+                    number, e
+                ))
+            })?;
+            requests.push(request);
+        }
+        Ok(Self::new(requests))
+    }
 }
 
 impl MessageRequest {
     /// Create a new MessageRequest
-    pub fn new(params: MessageRequestParams) -> Self {
+    pub fn new(params: CreateMessageParams) -> Self {
         Self {
             custom_id: None,
             params,
@@ -187,34 +375,69 @@ impl MessageRequest {
         self.custom_id = Some(custom_id.into());
         self
     }
-}
 
-impl MessageRequestParams {
-    /// Create a new MessageRequestParams
-    pub fn new(model: impl Into<String>, messages: Vec<Message>, max_tokens: u32) -> Self {
-        Self {
-            model: model.into(),
-            max_tokens,
-            system: None,
-            messages,
+    /// Checks that the request can be sent in a batch
+    ///
+    /// # Returns
+    ///
+    /// Returns the `custom_id` of the request.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MessageBatchError` if:
+    /// - The request has no `custom_id`, or it is not 1 to 64 letters, digits, hyphens
+    ///   and underscores
+    /// - The request sets `stream`, which batches do not support
+    pub fn validate(&self) -> Result<&str, MessageBatchError> {
+        let custom_id = self.custom_id.as_deref().ok_or_else(|| {
+            MessageBatchError::InvalidRequest("a request has no custom_id".to_string())
+        })?;
+        if !is_valid_id(custom_id) {
+            return Err(MessageBatchError::InvalidCustomId(custom_id.to_string()));
+        }
+        if self.params.stream.is_some() {
+            return Err(MessageBatchError::InvalidRequest(format!(
+                "request {} sets `stream`, which is not supported in batches",
+                custom_id
+            )));
         }
+        Ok(custom_id)
     }
 
-    /// Set a system prompt for this request
-    pub fn with_system(mut self, system: impl Into<String>) -> Self {
-        self.system = Some(system.into());
-        self
+    /// Size of the request serialized as JSON, in bytes
+    pub fn serialized_size(&self) -> Result<usize, MessageBatchError> {
+        json_size(self)
     }
 }
 
-impl Message {
-    /// Create a new Message
-    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
-        Self {
-            role: role.into(),
-            content: content.into(),
+/// Whether `id` is 1 to 64 letters, digits, hyphens and underscores
+pub(crate) fn is_valid_id(id: &str) -> bool {
+    (1..=64).contains(&id.len())
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Size of `value` serialized as JSON, without holding the serialized bytes
+fn json_size<T: Serialize>(value: &T) -> Result<usize, MessageBatchError> {
+    struct Counter(usize);
+
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
         }
     }
+
+    let mut counter = Counter(0);
+    serde_json::to_writer(&mut counter, value).map_err(|e| {
+        MessageBatchError::InvalidRequest(format!("Failed to serialize request: {}", e))
+    })?;
+    Ok(counter.0)
 }
 
 /// Response for listing message batches
@@ -285,7 +508,7 @@ impl RetrieveMessageBatchParams {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RetrieveMessageBatchResultsParams {
     /// ID of the message batch to retrieve
     pub message_batch_id: String,
@@ -303,7 +526,8 @@ impl RetrieveMessageBatchResultsParams {
 /// Response type for retrieving a message batch
 pub type RetrieveMessageBatchResponse = MessageBatch;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Result of a single request of a message batch, one line of the results file
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageBatchResult {
     /// Custom identifier provided in the original request
     pub custom_id: String,
@@ -311,56 +535,69 @@ pub struct MessageBatchResult {
     pub result: BatchRequestResult,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct BatchRequestResult {
-    /// Type of result (e.g., "succeeded")
-    #[serde(rename = "type")]
-    pub type_: String,
-    /// The resulting message if successful
-    pub message: MessageResponse,
-}
+/// Outcome of a request of a message batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchRequestResult {
+    /// The request succeeded
+    Succeeded {
+        /// The resulting message
+        message: CreateMessageResponse,
+    },
+    /// The request failed
+    Errored {
+        /// The error the request failed with
+        error: ApiErrorResponse,
+    },
+    /// The batch was canceled before the request was processed
+    Canceled,
+    /// The batch expired before the request was processed
+    Expired,
+}
+
+impl BatchRequestResult {
+    /// The resulting message, if the request succeeded
+    pub fn message(&self) -> Option<&CreateMessageResponse> {
+        match self {
+            Self::Succeeded { message } => Some(message),
+            _ => None,
+        }
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MessageResponse {
-    /// Unique identifier for the message
-    pub id: String,
-    /// Type of the response (always "message")
-    #[serde(rename = "type")]
-    pub type_: String,
-    /// Role of the message (e.g., "assistant")
-    pub role: String,
-    /// Model used for generation
-    pub model: String,
-    /// Content of the message
-    pub content: Vec<MessageContent>,
-    /// Reason for stopping generation
-    pub stop_reason: String,
-    /// Sequence that caused the stop
-    pub stop_sequence: Option<String>,
-    /// Token usage statistics
-    pub usage: TokenUsage,
-}
+    /// The error, if the request failed
+    pub fn error(&self) -> Option<&ApiErrorDetail> {
+        match self {
+            Self::Errored { error } => Some(&error.error),
+            _ => None,
+        }
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MessageContent {
-    /// Type of content (e.g., "text")
-    #[serde(rename = "type")]
-    pub type_: String,
-    /// The actual text content
-    pub text: String,
-}
+    /// The result type as sent by the API: `succeeded`, `errored`, `canceled` or
+    /// `expired`
+    pub fn status(&self) -> &'static str {
+        match self {
+            Self::Succeeded { .. } => "succeeded",
+            Self::Errored { .. } => "errored",
+            Self::Canceled => "canceled",
+            Self::Expired => "expired",
+        }
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TokenUsage {
-    /// Number of tokens in the input
-    pub input_tokens: u32,
-    /// Number of tokens in the output
-    pub output_tokens: u32,
-}
+    /// Whether the request succeeded
+    pub fn is_succeeded(&self) -> bool {
+        matches!(self, Self::Succeeded { .. })
+    }
 
-/// Response type for retrieving message batch results
-/// This will be a stream of MessageBatchResult objects, one per line
-pub type RetrieveMessageBatchResultsResponse = Vec<MessageBatchResult>;
+    /// Whether the request may succeed if resubmitted: it expired, or failed with a
+    /// retryable error
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Expired => true,
+            Self::Errored { error } => error.error.kind.is_retryable(),
+            _ => false,
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CancelMessageBatchParams {
@@ -375,22 +612,6 @@ impl CancelMessageBatchParams {
     }
 }
 
-/// Response type for cancelling a message batch
-#[derive(Debug, Deserialize)]
-pub struct CancelResponse {
-    pub id: String,
-    #[serde(rename = "type")]
-    pub obj_type: String,
-    pub processing_status: String,
-    pub request_counts: serde_json::Value,
-    pub ended_at: Option<String>,
-    pub created_at: String,
-    pub expires_at: String,
-    pub archived_at: Option<String>,
-    pub cancel_initiated_at: Option<String>,
-    pub results_url: Option<String>,
-}
-
 /// Parameters for deleting a message batch
 #[derive(Debug, Serialize)]
 pub struct DeleteMessageBatchParams {
@@ -416,3 +637,125 @@ pub struct DeleteResponse {
     #[serde(rename = "type")]
     pub obj_type: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::message::{Message, RequiredMessageParams, Role};
+
+    fn request(custom_id: &str) -> MessageRequest {
+        let params = CreateMessageParams::new(RequiredMessageParams {
+            model: "claude-3-5-haiku-latest".to_string(),
+            messages: vec![Message::new_text(Role::User, "Hello!")],
+            max_tokens: 100,
+        })
+        .with_temperature(0.5);
+        MessageRequest::new(params).with_custom_id(custom_id)
+    }
+
+    #[test]
+    fn requests_serialize_full_message_params() {
+        let batch = CreateMessageBatchParams::new(vec![request("req1")]);
+        assert!(batch.validate().is_ok());
+
+        let value = serde_json::to_value(&batch).unwrap();
+        let params = &value["requests"][0]["params"];
+        assert_eq!(value["requests"][0]["custom_id"], "req1");
+        assert_eq!(params["temperature"], 0.5);
+        assert_eq!(params["messages"][0]["role"], "user");
+        assert!(params.get("stream").is_none());
+
+        assert_eq!(
+            batch.serialized_size().unwrap(),
+            serde_json::to_vec(&batch).unwrap().len()
+        );
+
+        let mut streaming = request("req2");
+        streaming.params.stream = Some(true);
+        let batch = CreateMessageBatchParams::new(vec![streaming]);
+        assert!(matches!(
+            batch.validate(),
+            Err(MessageBatchError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn batches_report_progress() {
+        let mut batch: MessageBatch = serde_json::from_value(serde_json::json!({
+            "id": "msgbatch_1",
+            "type": "message_batch",
+            "created_at": "2024-09-24T18:37:24.100435Z",
+            "expires_at": "2999-09-25T18:37:24.100435Z",
+            "archived_at": null,
+            "cancel_initiated_at": null,
+            "ended_at": null,
+            "processing_status": "in_progress",
+            "request_counts": {
+                "processing": 3,
+                "succeeded": 1,
+                "errored": 0,
+                "canceled": 0,
+                "expired": 0
+            },
+            "results_url": null
+        }))
+        .unwrap();
+        assert!(!batch.is_ended());
+        assert_eq!(batch.progress_fraction(), 0.25);
+        assert!(batch.time_remaining().unwrap() > Duration::from_secs(3600));
+
+        batch.request_counts = RequestCounts::default();
+        assert_eq!(batch.progress_fraction(), 0.0);
+        batch.processing_status = ProcessingStatus::Ended;
+        assert_eq!(batch.progress_fraction(), 1.0);
+        assert!(batch.time_remaining().is_none());
+    }
+
+    #[test]
+    fn reads_requests_from_jsonl() {
+        let jsonl = r#"{"custom_id":"a","params":{"model":"claude-3-5-haiku-latest","max_tokens":100,"messages":[{"role":"user","content":"Hi"}]}}
+
+{"custom_id":"b","params":{"model":"claude-3-5-haiku-latest","max_tokens":100,"messages":[{"role":"user","content":"Hello"}]}}
+"#;
+        let params = CreateMessageBatchParams::from_jsonl(jsonl.as_bytes()).unwrap();
+        assert_eq!(params.requests.len(), 2);
+        assert_eq!(params.requests[1].custom_id.as_deref(), Some("b"));
+        assert!(params.validate().is_ok());
+
+        let broken = format!("{}\n\nnot json\n", jsonl.lines().next().unwrap());
+        let error = CreateMessageBatchParams::from_jsonl(broken.as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("line 3"), "{}", error);
+    }
+
+    #[test]
+    fn validation_checks_custom_ids_and_count() {
+        let valid = CreateMessageBatchParams::try_new(vec![request("a-1"), request("b_2")]);
+        assert!(valid.is_ok());
+
+        let duplicate = CreateMessageBatchParams::try_new(vec![request("a"), request("a")]);
+        assert!(matches!(
+            duplicate,
+            Err(MessageBatchError::DuplicateCustomId(id)) if id == "a"
+        ));
+
+        for custom_id in ["", "has space", &"x".repeat(65)] {
+            assert!(matches!(
+                request(custom_id).validate(),
+                Err(MessageBatchError::InvalidCustomId(_))
+            ));
+        }
+
+        let mut missing = request("a");
+        missing.custom_id = None;
+        assert!(matches!(
+            missing.validate(),
+            Err(MessageBatchError::InvalidRequest(_))
+        ));
+
+        let too_many = CreateMessageBatchParams::new(vec![request("a"); MAX_BATCH_REQUESTS + 1]);
+        assert!(matches!(
+            too_many.validate(),
+            Err(MessageBatchError::BatchTooLarge)
+        ));
+    }
+}
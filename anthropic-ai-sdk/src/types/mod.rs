@@ -1,4 +1,5 @@
 pub mod admin;
+pub mod error;
 pub mod files;
 pub mod message;
 pub mod message_batches;
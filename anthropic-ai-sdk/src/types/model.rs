@@ -2,6 +2,7 @@
 //!
 //! This module contains the types and functions for the Anthropic Models API.
 //!
+use crate::client::RequestError;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -27,6 +28,8 @@ impl From<String> for ModelError {
     }
 }
 
+impl RequestError for ModelError {}
+
 #[async_trait]
 pub trait ModelClient {
     async fn list_models<'a>(
@@ -0,0 +1,913 @@
+//! Usage tracking
+//!
+//! This module provides [`UsageTracker`], which aggregates token usage and cost across
+//! calls, grouped by model and by caller-provided tags, and [`TrackedClient`], a wrapper
+//! around any [`MessageClient`] that records the usage of every `create_message` call,
+//! every streamed response and every retrieved batch result.
+//!
+//! Budgets can be attached to the tracker to be notified when a threshold is crossed, or
+//! to reject new requests once it has been exceeded.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anthropic_ai_sdk::client::AnthropicClient;
+//! use anthropic_ai_sdk::types::message::{CreateMessageParams, MessageClient, MessageError};
+//! use anthropic_ai_sdk::usage::{Budget, UsageTracker};
+//!
+//! # async fn example(params: CreateMessageParams) -> Result<(), Box<dyn std::error::Error>> {
+//! let client = AnthropicClient::new::<MessageError>("your-api-key", "2023-06-01")?;
+//!
+//! let tracker = UsageTracker::new().with_budget(
+//!     Budget::cost(25.0)
+//!         .for_tag("tenant:acme")
+//!         .on_exceeded(|event| eprintln!("budget exceeded: {}", event))
+//!         .reject_when_exceeded(),
+//! );
+//!
+//! let acme = tracker.wrap(client).with_tag("tenant:acme").with_tag("feature:search");
+//! acme.create_message(Some(&params)).await?;
+//!
+//! let snapshot = tracker.snapshot();
+//! println!("Spent ${:.4} in {} requests", snapshot.total.cost, snapshot.total.requests);
+//! for (tag, totals) in &snapshot.by_tag {
+//!     println!("{}: {} tokens", tag, totals.total_tokens());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cost::CostCalculator;
+use crate::types::message::{
+    CountMessageTokensParams, CountMessageTokensResponse, CreateMessageParams,
+    CreateMessageResponse, MessageClient, MessageError, ServiceTier, StreamEvent, StreamUsage,
+    Usage,
+};
+use crate::types::message_batches::{
+    CancelMessageBatchParams, CreateMessageBatchParams, DeleteMessageBatchParams, DeleteResponse,
+    ListMessageBatchesParams, ListMessageBatchesResponse, MessageBatch, MessageBatchClient,
+    MessageBatchError, MessageBatchResult, RetrieveMessageBatchParams,
+    RetrieveMessageBatchResponse, RetrieveMessageBatchResultsParams,
+};
+use async_trait::async_trait;
+use futures_util::{Stream, StreamExt};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Aggregated usage of a group of requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct UsageTotals {
+    /// Number of recorded responses
+    pub requests: u64,
+    /// Base input tokens
+    pub input_tokens: u64,
+    /// Output tokens
+    pub output_tokens: u64,
+    /// Input tokens written to the prompt cache
+    pub cache_creation_input_tokens: u64,
+    /// Input tokens read from the prompt cache
+    pub cache_read_input_tokens: u64,
+    /// Web search requests
+    pub web_search_requests: u64,
+    /// Cost in USD
+    pub cost: f64,
+    /// Responses whose model has no known pricing and are not included in `cost`
+    pub unpriced_requests: u64,
+}
+
+impl UsageTotals {
+    /// Sum of input, output and cache tokens
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens
+            + self.output_tokens
+            + self.cache_creation_input_tokens
+            + self.cache_read_input_tokens
+    }
+
+    fn add(&mut self, usage: &Usage, cost: Option<f64>) {
+        self.requests += 1;
+        self.input_tokens += u64::from(usage.input_tokens);
+        self.output_tokens += u64::from(usage.output_tokens);
+        self.cache_creation_input_tokens +=
+            u64::from(usage.cache_creation_input_tokens.unwrap_or(0));
+        self.cache_read_input_tokens += u64::from(usage.cache_read_input_tokens.unwrap_or(0));
+        self.web_search_requests +=
+            u64::from(usage.server_tool_use.map_or(0, |u| u.web_search_requests));
+        match cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+}
+
+/// Point-in-time copy of the totals recorded by a [`UsageTracker`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageSnapshot {
+    /// Totals across every recorded response
+    pub total: UsageTotals,
+    /// Totals per model, keyed by the canonical model id when the catalog knows it
+    pub by_model: BTreeMap<String, UsageTotals>,
+    /// Totals per tag
+    pub by_tag: BTreeMap<String, UsageTotals>,
+}
+
+impl UsageSnapshot {
+    /// Totals of the given budget scope
+    pub fn totals(&self, scope: &BudgetScope) -> UsageTotals {
+        match scope {
+            BudgetScope::Total => self.total,
+            BudgetScope::Model(model) => self.by_model.get(model).copied().unwrap_or_default(),
+            BudgetScope::Tag(tag) => self.by_tag.get(tag).copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Group of requests a budget applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BudgetScope {
+    /// Every request recorded by the tracker
+    Total,
+    /// Requests served by a model
+    Model(String),
+    /// Requests carrying a tag
+    Tag(String),
+}
+
+impl fmt::Display for BudgetScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetScope::Total => write!(f, "total"),
+            BudgetScope::Model(model) => write!(f, "model {}", model),
+            BudgetScope::Tag(tag) => write!(f, "tag {}", tag),
+        }
+    }
+}
+
+/// Threshold of a budget
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BudgetLimit {
+    /// Total tokens, including cache reads and writes
+    Tokens(u64),
+    /// Cost in USD
+    Cost(f64),
+}
+
+impl BudgetLimit {
+    fn is_exceeded(&self, totals: &UsageTotals) -> bool {
+        match *self {
+            BudgetLimit::Tokens(limit) => totals.total_tokens() > limit,
+            BudgetLimit::Cost(limit) => totals.cost > limit,
+        }
+    }
+}
+
+impl fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetLimit::Tokens(limit) => write!(f, "{} tokens", limit),
+            BudgetLimit::Cost(limit) => write!(f, "${:.2}", limit),
+        }
+    }
+}
+
+/// Notification sent when a budget is exceeded
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetEvent {
+    /// Group of requests the budget applies to
+    pub scope: BudgetScope,
+    /// Threshold that was crossed
+    pub limit: BudgetLimit,
+    /// Totals of the scope at the time the threshold was crossed
+    pub totals: UsageTotals,
+}
+
+impl fmt::Display for BudgetEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} budget of {} exceeded ({} tokens, ${:.4})",
+            self.scope,
+            self.limit,
+            self.totals.total_tokens(),
+            self.totals.cost
+        )
+    }
+}
+
+type BudgetCallback = Arc<dyn Fn(&BudgetEvent) + Send + Sync>;
+
+/// Threshold on the usage of a group of requests
+#[derive(Clone)]
+pub struct Budget {
+    scope: BudgetScope,
+    limit: BudgetLimit,
+    on_exceeded: Option<BudgetCallback>,
+    reject: bool,
+}
+
+impl fmt::Debug for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Budget")
+            .field("scope", &self.scope)
+            .field("limit", &self.limit)
+            .field("on_exceeded", &self.on_exceeded.is_some())
+            .field("reject", &self.reject)
+            .finish()
+    }
+}
+
+impl Budget {
+    /// Budget on the total tokens of every recorded request
+    pub fn tokens(limit: u64) -> Self {
+        Self::new(BudgetLimit::Tokens(limit))
+    }
+
+    /// Budget on the cost in USD of every recorded request
+    pub fn cost(limit: f64) -> Self {
+        Self::new(BudgetLimit::Cost(limit))
+    }
+
+    fn new(limit: BudgetLimit) -> Self {
+        Self {
+            scope: BudgetScope::Total,
+            limit,
+            on_exceeded: None,
+            reject: false,
+        }
+    }
+
+    /// Restrict the budget to requests served by a model
+    ///
+    /// The model may be an id or an alias; both match requests and responses that
+    /// resolve to the same model in the tracker's catalog.
+    pub fn for_model(mut self, model: impl Into<String>) -> Self {
+        self.scope = BudgetScope::Model(model.into());
+        self
+    }
+
+    /// Restrict the budget to requests carrying a tag
+    pub fn for_tag(mut self, tag: impl Into<String>) -> Self {
+        self.scope = BudgetScope::Tag(tag.into());
+        self
+    }
+
+    /// Call `callback` once, when the budget is first exceeded
+    pub fn on_exceeded(mut self, callback: impl Fn(&BudgetEvent) + Send + Sync + 'static) -> Self {
+        self.on_exceeded = Some(Arc::new(callback));
+        self
+    }
+
+    /// Reject new requests in the scope of the budget once it has been exceeded
+    pub fn reject_when_exceeded(mut self) -> Self {
+        self.reject = true;
+        self
+    }
+
+    /// Group of requests the budget applies to
+    pub fn scope(&self) -> &BudgetScope {
+        &self.scope
+    }
+
+    /// Threshold of the budget
+    pub fn limit(&self) -> BudgetLimit {
+        self.limit
+    }
+
+    fn applies_to(&self, model: &str, tags: &[String]) -> bool {
+        match &self.scope {
+            BudgetScope::Total => true,
+            BudgetScope::Model(m) => m == model,
+            BudgetScope::Tag(tag) => tags.contains(tag),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BudgetState {
+    budget: Budget,
+    notified: bool,
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    snapshot: UsageSnapshot,
+    budgets: Vec<BudgetState>,
+}
+
+/// Aggregates token usage and cost across calls
+///
+/// Clones share their totals and budgets, so a single tracker can be handed to every
+/// part of a service.
+#[derive(Debug, Clone)]
+pub struct UsageTracker {
+    state: Arc<Mutex<TrackerState>>,
+    calculator: Arc<CostCalculator>,
+}
+
+impl Default for UsageTracker {
+    fn default() -> Self {
+        Self::with_cost_calculator(CostCalculator::new())
+    }
+}
+
+impl UsageTracker {
+    /// Create a tracker that prices usage with the built-in price table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a tracker that prices usage with the given calculator
+    pub fn with_cost_calculator(calculator: CostCalculator) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(TrackerState::default())),
+            calculator: Arc::new(calculator),
+        }
+    }
+
+    /// Add a budget to the tracker
+    pub fn with_budget(self, budget: Budget) -> Self {
+        self.add_budget(budget);
+        self
+    }
+
+    /// Add a budget to a tracker that is already in use
+    pub fn add_budget(&self, mut budget: Budget) {
+        if let BudgetScope::Model(model) = &budget.scope {
+            budget.scope = BudgetScope::Model(self.model_key(model));
+        }
+        let mut state = self.lock();
+        let notified = budget
+            .limit
+            .is_exceeded(&state.snapshot.totals(&budget.scope));
+        state.budgets.push(BudgetState { budget, notified });
+    }
+
+    /// Wrap a client so that the usage of every response it returns is recorded
+    pub fn wrap<C>(&self, client: C) -> TrackedClient<C> {
+        TrackedClient::new(client, self.clone())
+    }
+
+    /// Copy of the current totals
+    pub fn snapshot(&self) -> UsageSnapshot {
+        self.lock().snapshot.clone()
+    }
+
+    /// Clear the recorded totals and re-arm every budget
+    pub fn reset(&self) {
+        let mut state = self.lock();
+        state.snapshot = UsageSnapshot::default();
+        for budget in &mut state.budgets {
+            budget.notified = false;
+        }
+    }
+
+    /// Record the usage of a response
+    ///
+    /// The batch discount is applied when the usage reports the `batch` service tier.
+    pub fn record(&self, model: &str, usage: &Usage, tags: &[String]) {
+        let cost = self.calculator.cost(model, usage).ok().map(|c| c.total());
+        self.record_priced(model, usage, cost, tags);
+    }
+
+    /// Record the usage of a message response
+    pub fn record_response(&self, response: &CreateMessageResponse, tags: &[String]) {
+        self.record(&response.model, &response.usage, tags);
+    }
+
+    /// Record the usage of results from `retrieve_message_batch_results`
+    ///
+    /// Results are priced with the batch discount, and results of requests that did
+    /// not succeed are skipped. Recording the same results twice counts them twice.
+    pub fn record_batch_results(&self, results: &[MessageBatchResult], tags: &[String]) {
+        for result in results {
+            self.record_batch_result(result, tags);
+        }
+    }
+
+    /// Record the usage of a single result from `retrieve_message_batch_results`
+    pub fn record_batch_result(&self, result: &MessageBatchResult, tags: &[String]) {
+        if let Some(message) = result.result.message() {
+            let usage = Usage {
+                service_tier: Some(ServiceTier::Batch),
+                ..message.usage.clone()
+            };
+            self.record(&message.model, &usage, tags);
+        }
+    }
+
+    /// Checks the budgets that reject requests for a model and tags
+    ///
+    /// # Errors
+    ///
+    /// Returns the first exceeded budget that applies to the request
+    pub fn check(&self, model: &str, tags: &[String]) -> Result<(), BudgetEvent> {
+        let model = self.model_key(model);
+        let state = self.lock();
+        for BudgetState { budget, .. } in &state.budgets {
+            if !budget.reject || !budget.applies_to(&model, tags) {
+                continue;
+            }
+            let totals = state.snapshot.totals(&budget.scope);
+            if budget.limit.is_exceeded(&totals) {
+                return Err(BudgetEvent {
+                    scope: budget.scope.clone(),
+                    limit: budget.limit,
+                    totals,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn record_priced(&self, model: &str, usage: &Usage, cost: Option<f64>, tags: &[String]) {
+        let model = self.model_key(model);
+        let mut notifications = Vec::new();
+        {
+            let mut state = self.lock();
+            let TrackerState { snapshot, budgets } = &mut *state;
+            snapshot.total.add(usage, cost);
+            snapshot
+                .by_model
+                .entry(model.clone())
+                .or_default()
+                .add(usage, cost);
+            for tag in tags {
+                snapshot
+                    .by_tag
+                    .entry(tag.clone())
+                    .or_default()
+                    .add(usage, cost);
+            }
+
+            for state in budgets.iter_mut() {
+                let budget = &state.budget;
+                if state.notified || !budget.applies_to(&model, tags) {
+                    continue;
+                }
+                let totals = snapshot.totals(&budget.scope);
+                if budget.limit.is_exceeded(&totals) {
+                    state.notified = true;
+                    if let Some(callback) = &budget.on_exceeded {
+                        let event = BudgetEvent {
+                            scope: budget.scope.clone(),
+                            limit: budget.limit,
+                            totals,
+                        };
+                        notifications.push((callback.clone(), event));
+                    }
+                }
+            }
+        }
+
+        // Callbacks run without the lock so they can inspect the tracker
+        for (callback, event) in notifications {
+            callback(&event);
+        }
+    }
+
+    /// Canonical id of a model, so aliases and dated ids are grouped together
+    fn model_key(&self, model: &str) -> String {
+        self.calculator
+            .catalog()
+            .resolve(model)
+            .unwrap_or(model)
+            .to_string()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TrackerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Client wrapper that records usage in a [`UsageTracker`]
+///
+/// Every response is recorded under its model and under the tags of the wrapper.
+/// Requests are rejected with [`MessageError::BudgetExceeded`] or
+/// [`MessageBatchError::BudgetExceeded`] when a rejecting budget that applies to them has
+/// been exceeded.
+#[derive(Debug, Clone)]
+pub struct TrackedClient<C> {
+    inner: C,
+    tracker: UsageTracker,
+    tags: Vec<String>,
+}
+
+impl<C> TrackedClient<C> {
+    /// Wrap a client without tags
+    pub fn new(inner: C, tracker: UsageTracker) -> Self {
+        Self {
+            inner,
+            tracker,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Add a tag to every response recorded through this client
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Get the tags of this client
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Get the tracker usage is recorded in
+    pub fn get_tracker(&self) -> &UsageTracker {
+        &self.tracker
+    }
+
+    /// Get the wrapped client
+    pub fn get_inner(&self) -> &C {
+        &self.inner
+    }
+
+    fn check_budget(&self, model: &str) -> Result<(), String> {
+        self.tracker
+            .check(model, &self.tags)
+            .map_err(|event| event.to_string())
+    }
+}
+
+#[async_trait]
+impl<C: MessageClient + Sync> MessageClient for TrackedClient<C> {
+    async fn create_message<'a>(
+        &'a self,
+        params: Option<&'a CreateMessageParams>,
+    ) -> Result<CreateMessageResponse, MessageError> {
+        let model = params.map(|p| p.model.as_str()).unwrap_or_default();
+        self.check_budget(model)
+            .map_err(MessageError::BudgetExceeded)?;
+
+        let response = self.inner.create_message(params).await?;
+        self.tracker.record_response(&response, &self.tags);
+        Ok(response)
+    }
+
+    async fn count_tokens<'a>(
+        &'a self,
+        params: Option<&'a CountMessageTokensParams>,
+    ) -> Result<CountMessageTokensResponse, MessageError> {
+        self.inner.count_tokens(params).await
+    }
+
+    /// Streams the response, recording its usage when the `message_delta` event arrives
+    ///
+    /// If the stream fails or is dropped before `message_delta`, the usage reported by
+    /// `message_start` is recorded instead.
+    async fn create_message_streaming<'a>(
+        &'a self,
+        body: &'a CreateMessageParams,
+    ) -> Result<
+        impl futures_util::Stream<Item = Result<StreamEvent, MessageError>> + 'a,
+        MessageError,
+    > {
+        self.check_budget(&body.model)
+            .map_err(MessageError::BudgetExceeded)?;
+
+        let stream = self.inner.create_message_streaming(body).await?;
+        let mut pending = PendingUsage {
+            tracker: &self.tracker,
+            tags: &self.tags,
+            started: None,
+        };
+        Ok(stream.map(move |event| {
+            match &event {
+                Ok(StreamEvent::MessageStart { message }) => {
+                    pending.started = Some((message.model.clone(), message.usage.clone()));
+                }
+                // Usage in message_delta is cumulative, so the response is recorded once
+                Ok(StreamEvent::MessageDelta {
+                    usage: Some(delta), ..
+                }) => pending.record(Some(delta)),
+                Err(_) => pending.record(None),
+                _ => {}
+            }
+            event
+        }))
+    }
+}
+
+#[async_trait]
+impl<C: MessageBatchClient + Sync> MessageBatchClient for TrackedClient<C> {
+    async fn create_message_batch<'a>(
+        &'a self,
+        params: &'a CreateMessageBatchParams,
+    ) -> Result<MessageBatch, MessageBatchError> {
+        for request in &params.requests {
+            self.check_budget(&request.params.model)
+                .map_err(MessageBatchError::BudgetExceeded)?;
+        }
+        self.inner.create_message_batch(params).await
+    }
+
+    async fn list_message_batches<'a>(
+        &'a self,
+        params: Option<&'a ListMessageBatchesParams>,
+    ) -> Result<ListMessageBatchesResponse, MessageBatchError> {
+        self.inner.list_message_batches(params).await
+    }
+
+    async fn retrieve_message_batch<'a>(
+        &'a self,
+        params: &'a RetrieveMessageBatchParams,
+    ) -> Result<RetrieveMessageBatchResponse, MessageBatchError> {
+        self.inner.retrieve_message_batch(params).await
+    }
+
+    /// Retrieves the results of a batch, recording their usage as they are streamed
+    async fn retrieve_message_batch_results<'a>(
+        &'a self,
+        params: &'a RetrieveMessageBatchResultsParams,
+    ) -> Result<
+        impl Stream<Item = Result<MessageBatchResult, MessageBatchError>> + Send + 'a,
+        MessageBatchError,
+    > {
+        let results = self.inner.retrieve_message_batch_results(params).await?;
+        Ok(results.inspect(|result| {
+            if let Ok(result) = result {
+                self.tracker.record_batch_result(result, &self.tags);
+            }
+        }))
+    }
+
+    async fn cancel_message_batch<'a>(
+        &'a self,
+        params: &'a CancelMessageBatchParams,
+    ) -> Result<MessageBatch, MessageBatchError> {
+        self.inner.cancel_message_batch(params).await
+    }
+
+    async fn delete_message_batch<'a>(
+        &'a self,
+        params: &'a DeleteMessageBatchParams,
+    ) -> Result<DeleteResponse, MessageBatchError> {
+        self.inner.delete_message_batch(params).await
+    }
+}
+
+/// Usage of a streamed response that has started but not been recorded yet
+///
+/// Recorded once: when `message_delta` arrives, or as reported by `message_start` if
+/// the stream fails or is dropped first.
+struct PendingUsage<'a> {
+    tracker: &'a UsageTracker,
+    tags: &'a [String],
+    started: Option<(String, Usage)>,
+}
+
+impl PendingUsage<'_> {
+    fn record(&mut self, delta: Option<&StreamUsage>) {
+        if let Some((model, mut usage)) = self.started.take() {
+            if let Some(delta) = delta {
+                usage.apply_delta(delta);
+            }
+            self.tracker.record(&model, &usage, self.tags);
+        }
+    }
+}
+
+impl Drop for PendingUsage<'_> {
+    fn drop(&mut self) {
+        self.record(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::local::LocalBatchExecutor;
+    use crate::batch::test_support::{MockMessages, response};
+    use crate::catalog::ModelCatalog;
+    use crate::types::message::{MessageDeltaContent, MessageStartContent, Role, StopReason};
+    use crate::types::message_batches::MessageRequest;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MockClient;
+
+    /// Responses report the dated model id, like the API
+    fn response_model(model: &str) -> String {
+        let catalog = ModelCatalog::builtin();
+        catalog.resolve(model).unwrap_or(model).to_string()
+    }
+
+    fn usage(input_tokens: u32, output_tokens: u32) -> Usage {
+        Usage {
+            input_tokens,
+            output_tokens,
+            ..Default::default()
+        }
+    }
+
+    #[async_trait]
+    impl MockMessages for MockClient {
+        async fn create(
+            &self,
+            params: &CreateMessageParams,
+        ) -> Result<CreateMessageResponse, MessageError> {
+            Ok(CreateMessageResponse {
+                usage: usage(1_000, 500),
+                ..response(&response_model(&params.model))
+            })
+        }
+
+        fn stream(&self, body: &CreateMessageParams) -> Vec<StreamEvent> {
+            vec![
+                StreamEvent::MessageStart {
+                    message: MessageStartContent {
+                        id: "msg_1".to_string(),
+                        type_: "message".to_string(),
+                        role: Role::Assistant,
+                        content: vec![],
+                        model: response_model(&body.model),
+                        stop_reason: None,
+                        stop_sequence: None,
+                        usage: Usage {
+                            cache_read_input_tokens: Some(200),
+                            ..usage(100, 1)
+                        },
+                    },
+                },
+                StreamEvent::MessageDelta {
+                    delta: MessageDeltaContent {
+                        stop_reason: Some(StopReason::EndTurn),
+                        stop_sequence: None,
+                    },
+                    usage: Some(StreamUsage {
+                        input_tokens: 0,
+                        output_tokens: 42,
+                        cache_creation_input_tokens: None,
+                        cache_read_input_tokens: None,
+                        server_tool_use: None,
+                    }),
+                },
+                StreamEvent::MessageStop,
+            ]
+        }
+    }
+
+    fn params(model: &str) -> CreateMessageParams {
+        CreateMessageParams {
+            model: model.to_string(),
+            max_tokens: 16,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn groups_usage_by_model_and_tag() {
+        let tracker = UsageTracker::new();
+        let acme = tracker.wrap(MockClient).with_tag("tenant:acme");
+        let globex = tracker.wrap(MockClient).with_tag("tenant:globex");
+
+        acme.create_message(Some(&params("claude-haiku-4-5")))
+            .await
+            .unwrap();
+        acme.create_message(Some(&params("claude-sonnet-4-5")))
+            .await
+            .unwrap();
+        globex
+            .create_message(Some(&params("claude-haiku-4-5")))
+            .await
+            .unwrap();
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.total.requests, 3);
+        assert_eq!(snapshot.total.total_tokens(), 4_500);
+        assert_eq!(snapshot.by_model["claude-haiku-4-5-20251001"].requests, 2);
+        assert_eq!(snapshot.by_tag["tenant:acme"].input_tokens, 2_000);
+        assert_eq!(snapshot.by_tag["tenant:globex"].output_tokens, 500);
+        // Haiku 4.5 is $1/$5 per MTok
+        assert!((snapshot.by_tag["tenant:globex"].cost - 0.0035).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn records_streaming_usage_from_message_delta() {
+        let tracker = UsageTracker::new();
+        let client = tracker.wrap(MockClient);
+
+        let events: Vec<_> = client
+            .create_message_streaming(&params("claude-haiku-4-5"))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(events.len(), 3);
+
+        let totals = tracker.snapshot().total;
+        assert_eq!(totals.requests, 1);
+        assert_eq!(totals.input_tokens, 100);
+        assert_eq!(totals.output_tokens, 42);
+        assert_eq!(totals.cache_read_input_tokens, 200);
+    }
+
+    #[tokio::test]
+    async fn records_message_start_usage_of_interrupted_streams() {
+        let tracker = UsageTracker::new();
+        let client = tracker.wrap(MockClient);
+
+        let body = params("claude-haiku-4-5");
+        let mut stream = Box::pin(client.create_message_streaming(&body).await.unwrap());
+        assert!(stream.next().await.unwrap().is_ok());
+        assert_eq!(tracker.snapshot().total.requests, 0);
+        drop(stream);
+
+        let totals = tracker.snapshot().total;
+        assert_eq!(totals.requests, 1);
+        assert_eq!(totals.input_tokens, 100);
+        assert_eq!(totals.output_tokens, 1);
+    }
+
+    #[tokio::test]
+    async fn budgets_notify_once_and_reject() {
+        let notified = Arc::new(AtomicUsize::new(0));
+        let counter = notified.clone();
+        let tracker = UsageTracker::new().with_budget(
+            Budget::tokens(2_000)
+                .for_tag("tenant:acme")
+                .on_exceeded(move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .reject_when_exceeded(),
+        );
+        let acme = tracker.wrap(MockClient).with_tag("tenant:acme");
+        let other = tracker.wrap(MockClient);
+
+        acme.create_message(Some(&params("claude-haiku-4-5")))
+            .await
+            .unwrap();
+        acme.create_message(Some(&params("claude-haiku-4-5")))
+            .await
+            .unwrap();
+        assert_eq!(notified.load(Ordering::SeqCst), 1);
+
+        let result = acme.create_message(Some(&params("claude-haiku-4-5"))).await;
+        assert!(matches!(result, Err(MessageError::BudgetExceeded(_))));
+        let batches = tracker
+            .wrap(LocalBatchExecutor::new(MockClient))
+            .with_tag("tenant:acme");
+        let requests = vec![MessageRequest::new(params("claude-haiku-4-5")).with_custom_id("a")];
+        let result = batches
+            .create_message_batch(&CreateMessageBatchParams::new(requests))
+            .await;
+        assert!(matches!(result, Err(MessageBatchError::BudgetExceeded(_))));
+        assert!(
+            other
+                .create_message(Some(&params("claude-haiku-4-5")))
+                .await
+                .is_ok()
+        );
+        assert_eq!(notified.load(Ordering::SeqCst), 1);
+
+        tracker.reset();
+        assert!(
+            acme.create_message(Some(&params("claude-haiku-4-5")))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn model_budgets_match_aliases_and_dated_ids() {
+        let notified = Arc::new(AtomicUsize::new(0));
+        let counter = notified.clone();
+        let tracker = UsageTracker::new().with_budget(
+            Budget::tokens(1_000)
+                .for_model("claude-sonnet-4-5")
+                .on_exceeded(move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .reject_when_exceeded(),
+        );
+        let client = tracker.wrap(MockClient);
+
+        let response = client
+            .create_message(Some(&params("claude-sonnet-4-5")))
+            .await
+            .unwrap();
+        assert_eq!(response.model, "claude-sonnet-4-5-20250929");
+        assert_eq!(notified.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            tracker.snapshot().by_model["claude-sonnet-4-5-20250929"].requests,
+            1
+        );
+
+        let result = client
+            .create_message(Some(&params("claude-sonnet-4-5")))
+            .await;
+        assert!(matches!(result, Err(MessageError::BudgetExceeded(_))));
+        let result = client
+            .create_message(Some(&params("claude-sonnet-4-5-20250929")))
+            .await;
+        assert!(matches!(result, Err(MessageError::BudgetExceeded(_))));
+        assert!(
+            client
+                .create_message(Some(&params("claude-haiku-4-5")))
+                .await
+                .is_ok()
+        );
+    }
+}
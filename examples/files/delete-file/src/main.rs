@@ -82,18 +82,15 @@ async fn main() {
             error!("Failed to delete file: {}", e);
             
             // Provide helpful error messages
-            match e {
-                FileError::ApiError(ref msg) => {
-                    if msg.contains("not found") {
-                        error!("The file with ID '{}' was not found.", file_id);
-                        error!("It may have already been deleted or never existed.");
-                    } else if msg.contains("unauthorized") || msg.contains("forbidden") {
-                        error!("Access denied. Please check your API key permissions.");
-                    } else if msg.contains("in use") || msg.contains("referenced") {
-                        error!("The file may be in use by another resource and cannot be deleted.");
-                    }
+            if let FileError::ApiError(ref msg) = e {
+                if msg.contains("not found") {
+                    error!("The file with ID '{}' was not found.", file_id);
+                    error!("It may have already been deleted or never existed.");
+                } else if msg.contains("unauthorized") || msg.contains("forbidden") {
+                    error!("Access denied. Please check your API key permissions.");
+                } else if msg.contains("in use") || msg.contains("referenced") {
+                    error!("The file may be in use by another resource and cannot be deleted.");
                 }
-                _ => {}
             }
             std::process::exit(1);
         }
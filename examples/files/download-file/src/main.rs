@@ -82,17 +82,17 @@ async fn main() {
                 }
             }
             
-            match File::create(&path) {
+            match File::create(path) {
                 Ok(mut file) => {
                     match file.write_all(&file_content) {
                         Ok(_) => {
                             info!("File saved successfully to: {}", output_path);
                             
                             // Verify file size
-                            if let Ok(saved_metadata) = std::fs::metadata(&path) {
+                            if let Ok(saved_metadata) = std::fs::metadata(path) {
                                 info!("Saved file size: {} bytes", saved_metadata.len());
                                 
-                                if saved_metadata.len() as u64 != metadata.size_bytes {
+                                if saved_metadata.len() != metadata.size_bytes {
                                     error!(
                                         "Warning: Saved file size ({}) doesn't match expected size ({})",
                                         saved_metadata.len(),
@@ -117,15 +117,12 @@ async fn main() {
             error!("Failed to download file: {}", e);
             
             // Provide helpful error messages
-            match e {
-                FileError::ApiError(ref msg) => {
-                    if msg.contains("not found") {
-                        error!("The file with ID '{}' was not found.", file_id);
-                    } else if msg.contains("unauthorized") || msg.contains("forbidden") {
-                        error!("Access denied. Please check your API key permissions.");
-                    }
+            if let FileError::ApiError(ref msg) = e {
+                if msg.contains("not found") {
+                    error!("The file with ID '{}' was not found.", file_id);
+                } else if msg.contains("unauthorized") || msg.contains("forbidden") {
+                    error!("Access denied. Please check your API key permissions.");
                 }
-                _ => {}
             }
             std::process::exit(1);
         }
@@ -56,16 +56,13 @@ async fn main() {
             error!("Error retrieving file metadata: {}", e);
             
             // Provide helpful error messages
-            match e {
-                FileError::ApiError(ref msg) => {
-                    if msg.contains("not found") {
-                        error!("The file with ID '{}' was not found.", file_id);
-                        error!("Please ensure the file ID is correct and the file exists.");
-                    } else if msg.contains("unauthorized") || msg.contains("forbidden") {
-                        error!("Access denied. Please check your API key permissions.");
-                    }
+            if let FileError::ApiError(ref msg) = e {
+                if msg.contains("not found") {
+                    error!("The file with ID '{}' was not found.", file_id);
+                    error!("Please ensure the file ID is correct and the file exists.");
+                } else if msg.contains("unauthorized") || msg.contains("forbidden") {
+                    error!("Access denied. Please check your API key permissions.");
                 }
-                _ => {}
             }
         }
     }
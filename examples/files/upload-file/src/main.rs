@@ -77,17 +77,14 @@ async fn main() {
             error!("Failed to upload file: {}", e);
             
             // Provide helpful error messages
-            match e {
-                FileError::ApiError(ref msg) => {
-                    if msg.contains("too large") || msg.contains("size") {
-                        error!("The file may be too large. Please check Anthropic's file size limits.");
-                    } else if msg.contains("unauthorized") || msg.contains("forbidden") {
-                        error!("Access denied. Please check your API key permissions.");
-                    } else if msg.contains("unsupported") || msg.contains("type") {
-                        error!("The file type may not be supported. Please check Anthropic's supported file types.");
-                    }
+            if let FileError::ApiError(ref msg) = e {
+                if msg.contains("too large") || msg.contains("size") {
+                    error!("The file may be too large. Please check Anthropic's file size limits.");
+                } else if msg.contains("unauthorized") || msg.contains("forbidden") {
+                    error!("Access denied. Please check your API key permissions.");
+                } else if msg.contains("unsupported") || msg.contains("type") {
+                    error!("The file type may not be supported. Please check Anthropic's supported file types.");
                 }
-                _ => {}
             }
             std::process::exit(1);
         }
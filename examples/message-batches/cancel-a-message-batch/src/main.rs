@@ -26,10 +26,10 @@ async fn main() {
         .cancel_message_batch(&CancelMessageBatchParams::new("msgbatch_batch_id"))
         .await
     {
-        Ok(results) => {
+        Ok(batch) => {
             info!(
-                "Successfully retrieved message batch results: {:?}",
-                results
+                "Message batch {} is {:?}: {:?}",
+                batch.id, batch.processing_status, batch.request_counts
             );
         }
         Err(e) => {
@@ -1,7 +1,7 @@
 use anthropic_ai_sdk::client::AnthropicClient;
+use anthropic_ai_sdk::types::message::{CreateMessageParams, Message, RequiredMessageParams, Role};
 use anthropic_ai_sdk::types::message_batches::{
-    CreateMessageBatchParams, Message, MessageBatchClient, MessageBatchError, MessageRequest,
-    MessageRequestParams,
+    CreateMessageBatchParams, MessageBatchClient, MessageBatchError, MessageRequest,
 };
 use std::env;
 use tracing::{error, info};
@@ -23,9 +23,13 @@ async fn main() {
 
     let client = AnthropicClient::new::<MessageBatchError>(api_key, api_version).unwrap();
 
-    let messages = vec![Message::new("user", "Hello!")];
-    let request_params = MessageRequestParams::new("claude-3-haiku", messages, 100)
-        .with_system("You are a helpful assistant");
+    let request_params = CreateMessageParams::new(RequiredMessageParams {
+        model: "claude-3-5-haiku-latest".to_string(),
+        messages: vec![Message::new_text(Role::User, "Hello!")],
+        max_tokens: 100,
+    })
+    .with_system("You are a helpful assistant")
+    .with_temperature(0.5);
 
     let request = MessageRequest::new(request_params).with_custom_id("req1");
 
@@ -1,7 +1,8 @@
 use anthropic_ai_sdk::client::AnthropicClient;
 use anthropic_ai_sdk::types::message_batches::{
-    MessageBatchClient, MessageBatchError, RetrieveMessageBatchResultsParams,
+    BatchRequestResult, MessageBatchClient, MessageBatchError, RetrieveMessageBatchResultsParams,
 };
+use futures_util::StreamExt;
 use std::env;
 use tracing::{error, info};
 
@@ -22,20 +23,32 @@ async fn main() {
 
     let client = AnthropicClient::new::<MessageBatchError>(api_key, api_version).unwrap();
 
-    match client
-        .retrieve_message_batch_results(&RetrieveMessageBatchResultsParams::new(
-            "msgbatch_batch_id",
-        ))
-        .await
-    {
-        Ok(results) => {
-            info!(
-                "Successfully retrieved message batch results: {:?}",
-                results
-            );
-        }
+    let params = RetrieveMessageBatchResultsParams::new("msgbatch_batch_id");
+    let results = match client.retrieve_message_batch_results(&params).await {
+        Ok(results) => results,
         Err(e) => {
             error!("Error: {}", e);
+            return;
+        }
+    };
+
+    let mut results = Box::pin(results);
+    while let Some(result) = results.next().await {
+        match result {
+            Ok(result) => match result.result {
+                BatchRequestResult::Succeeded { message } => {
+                    info!("{} succeeded: {:?}", result.custom_id, message.content);
+                }
+                BatchRequestResult::Errored { error } => {
+                    error!("{} errored: {}", result.custom_id, error.error);
+                }
+                BatchRequestResult::Canceled => info!("{} was canceled", result.custom_id),
+                BatchRequestResult::Expired => info!("{} expired", result.custom_id),
+            },
+            Err(e) => {
+                error!("Error: {}", e);
+                break;
+            }
         }
     }
 }
@@ -25,10 +25,11 @@ async fn main() {
 
     let client = AnthropicClient::new::<MessageError>(api_key, api_version).unwrap();
 
-    let body = CountMessageTokensParams {
-        model: "claude-3-7-sonnet-latest".to_string(),
-        messages: vec![Message::new_text(Role::User, "Hello, Claude")],
-    };
+    let body = CountMessageTokensParams::new(
+        "claude-3-7-sonnet-latest",
+        vec![Message::new_text(Role::User, "Hello, Claude")],
+    )
+    .with_system("You are a helpful assistant");
 
     info!("body: {:?}", body);
 